@@ -5,12 +5,13 @@ use defmt::*;
 use embassy_executor::Spawner;
 use embassy_nrf::twim::{self, Twim};
 use embassy_nrf::{bind_interrupts, peripherals};
-use embassy_time::Timer;
+use embassy_time::{Delay, Timer};
 use static_cell::ConstStaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
 use da728x::{DA728x, Variant};
-use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, OperationMode, DrivingMode};
+use da728x::melody::{Note, Tempo};
+use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, OperationMode, DrivingMode, IdleState};
 
 bind_interrupts!(struct Irqs {
     SERIAL20 => twim::InterruptHandler<peripherals::SERIAL20>;
@@ -53,6 +54,11 @@ async fn main(_spawner: Spawner) {
         driving_mode: DrivingMode::WIDEBAND,
         acceleration: false,
         rapid_stop: false,
+        idle_state: IdleState::IDLE,
+        i2c_timeout: true,
+        amplitude_pid: false,
+        erm_overdrive: None,
+        low_latency: false,
     };
 
     haptics.configure(actuator_config, device_config).await.unwrap();
@@ -77,22 +83,16 @@ async fn main(_spawner: Spawner) {
 
         Timer::after_millis(2_000).await;
 
-        // Tetris melody notes (frequency in Hz, duration in ms)
-        // Is not 100% correct
+        // Tetris melody
+        let tempo = Tempo::new(400);
+        let (q, e) = (tempo.quarter(), tempo.eighth());
         let tetris_melody = [
-            (659, 150), (494, 75), (523, 75), (587, 150), (523, 75), (494, 75),
-            (440, 150), (440, 75), (523, 75), (659, 150), (587, 75), (523, 75),
-            (494, 225), (523, 75), (587, 150), (659, 150),
-            (523, 150), (440, 150), (440, 150),
+            (Note::E5, q), (Note::B4, e), (Note::C5, e), (Note::D5, q), (Note::C5, e), (Note::B4, e),
+            (Note::A4, q), (Note::A4, e), (Note::C5, e), (Note::E5, q), (Note::D5, e), (Note::C5, e),
+            (Note::B4, tempo.dotted(q)), (Note::C5, e), (Note::D5, q), (Note::E5, q),
+            (Note::C5, q), (Note::A4, q), (Note::A4, q),
         ];
-
-        for &(freq, dur) in tetris_melody.iter() {
-            haptics.set_frequency(freq).await.unwrap();
-            haptics.set_override_value(127).await.unwrap();
-            Timer::after_millis(dur).await;
-            haptics.set_override_value(0).await.unwrap();
-            Timer::after_millis(90).await;
-        }
+        haptics.play_melody(&tetris_melody, &mut Delay).await.unwrap();
 
         Timer::after_millis(2_000).await;
 
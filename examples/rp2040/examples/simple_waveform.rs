@@ -57,7 +57,7 @@ use embassy_rp::peripherals::I2C0;
 use embassy_time::Timer;
 use {defmt_rtt as _, panic_probe as _};
 
-use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, OperationMode};
+use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, OperationMode, IdleState};
 use da728x::waveform::{
     FrameBuilder, Gain, SequenceBuilder, SnippetBuilder, Timebase, WaveformMemoryBuilder,
 };
@@ -138,6 +138,11 @@ async fn main(_spawner: Spawner) {
         driving_mode: DrivingMode::FREQUENCY_TRACK,
         acceleration: false,
         rapid_stop: false,
+        idle_state: IdleState::IDLE,
+        i2c_timeout: true,
+        amplitude_pid: false,
+        erm_overdrive: None,
+        low_latency: false,
     };
 
     haptics.configure(actuator_config, device_config).await.unwrap();
@@ -51,19 +51,23 @@ use defmt::*;
 use embassy_executor::Spawner;
 use embassy_rp::i2c::{self, Config, I2c};
 use embassy_rp::peripherals::I2C0;
-use embassy_time::Timer;
+use embassy_time::{Delay, Timer};
 use {defmt_rtt as _, panic_probe as _};
 
-use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, OperationMode};
+use da728x::melody::{Note, Tempo};
+use da728x::config::{ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, OperationMode, IdleState};
 use da728x::{Variant, DA728x};
 
-/// Tetris Theme (Korobeiniki) melody - frequency in Hz and duration in ms.
-const TETRIS_MELODY: &[(u16, u64)] = &[
-    (659, 150), (494, 75), (523, 75), (587, 150), (523, 75), (494, 75),
-    (440, 150), (440, 75), (523, 75), (659, 150), (587, 75), (523, 75),
-    (494, 225), (523, 75), (587, 150), (659, 150),
-    (523, 150), (440, 150), (440, 150),
-];
+/// Tetris Theme (Korobeiniki) melody, one quarter note per beat.
+fn tetris_melody(tempo: Tempo) -> [(Note, u32); 19] {
+    let (q, e) = (tempo.quarter(), tempo.eighth());
+    [
+        (Note::E5, q), (Note::B4, e), (Note::C5, e), (Note::D5, q), (Note::C5, e), (Note::B4, e),
+        (Note::A4, q), (Note::A4, e), (Note::C5, e), (Note::E5, q), (Note::D5, e), (Note::C5, e),
+        (Note::B4, tempo.dotted(q)), (Note::C5, e), (Note::D5, q), (Note::E5, q),
+        (Note::C5, q), (Note::A4, q), (Note::A4, q),
+    ]
+}
 
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
@@ -104,6 +108,11 @@ async fn main(_spawner: Spawner) {
         driving_mode: DrivingMode::WIDEBAND,
         acceleration: false,
         rapid_stop: false,
+        idle_state: IdleState::IDLE,
+        i2c_timeout: true,
+        amplitude_pid: false,
+        erm_overdrive: None,
+        low_latency: false,
     };
 
     haptics.configure(actuator_config, device_config).await.unwrap();
@@ -113,18 +122,7 @@ async fn main(_spawner: Spawner) {
     loop {
         info!("Playing Tetris melody...");
 
-        for &(freq, duration) in TETRIS_MELODY.iter() {
-            // Set frequency for this note
-            haptics.set_frequency(freq).await.unwrap();
-
-            // Play note at full amplitude
-            haptics.set_override_value(127).await.unwrap();
-            Timer::after_millis(duration).await;
-
-            // Brief silence between notes
-            haptics.set_override_value(0).await.unwrap();
-            Timer::after_millis(50).await;
-        }
+        haptics.play_melody(&tetris_melody(Tempo::new(400)), &mut Delay).await.unwrap();
 
         info!("Melody complete!");
 
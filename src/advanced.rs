@@ -0,0 +1,110 @@
+//! Internal tuning registers (TOP_INT_CFG group), `advanced` feature.
+//!
+//! The defaults cover the vast majority of actuators (5.7 Advanced Operation).
+//! Changing these without measuring the actuator response can make the loops
+//! unstable, the Smart Canvas GUI LRA tuning tool helps to find suitable values.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::registers::{Register, TOP_INT_CFG1, TOP_INT_CFG8};
+use crate::DA728x;
+
+/// BEMF amplitude below which an actuator fault is raised (TOP_INT_CFG1.BEMF_FAULT_LIM, 5.7.14).
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BemfFaultLimit {
+    DISABLED = 0,
+    /// 4.9 mV (reset value)
+    MV_4_9 = 1,
+    /// 27.9 mV
+    MV_27_9 = 2,
+    /// 49.9 mV
+    MV_49_9 = 3,
+}
+
+/// Frequency tracking PI controller coefficients (TOP_INT_CFG6 / TOP_INT_CFG7).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyPid {
+    /// FRQ_PID_Kp, proportional coefficient.
+    pub kp: u16,
+    /// FRQ_PID_Ki, integral coefficient.
+    pub ki: u16,
+}
+
+impl FrequencyPid {
+    /// Reset values.
+    pub const DEFAULT: Self = Self { kp: 0x0E20, ki: 0x0320 };
+}
+
+impl Default for FrequencyPid {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Set the frequency tracking PI coefficients.
+    ///
+    /// If FREQ_TRACKING_AUTO_ADJ is enabled, make sure the loop is stable both before
+    /// and after the initial lock. Not allowed while enabled, to avoid updating the
+    /// coefficients halfway.
+    pub async fn set_frequency_pid(&mut self, pid: FrequencyPid) -> Result<(), Error> {
+        if self.enabled {
+            return Err(Error::WrongMode);
+        }
+
+        let kp = pid.kp.to_be_bytes();
+        let ki = pid.ki.to_be_bytes();
+        self.write_register(Register::TOP_INT_CFG6_H, kp[0]).await?;
+        self.write_register(Register::TOP_INT_CFG6_L, kp[1]).await?;
+        self.write_register(Register::TOP_INT_CFG7_H, ki[0]).await?;
+        self.write_register(Register::TOP_INT_CFG7_L, ki[1]).await
+    }
+
+    /// Read the frequency tracking PI coefficients.
+    pub async fn get_frequency_pid(&mut self) -> Result<FrequencyPid, Error> {
+        let mut buffer = [0u8; 4];
+        self.read_registers(Register::TOP_INT_CFG6_H, &mut buffer).await?;
+
+        Ok(FrequencyPid {
+            kp: u16::from_be_bytes([buffer[0], buffer[1]]),
+            ki: u16::from_be_bytes([buffer[2], buffer[3]]),
+        })
+    }
+
+    /// Set the Rapid Stop threshold (TOP_INT_CFG8.RAPID_STOP_LIM, 0..=7, reset value 4).
+    ///
+    /// Decrease it if the actuator is not fully stopped, increase it if it overshoots
+    /// (5.7.2 Rapid Stop). Must stay above the frequency tracking BEMF limit.
+    pub async fn set_rapid_stop_limit(&mut self, limit: u8) -> Result<(), Error> {
+        if limit > 7 {
+            return Err(Error::InvalidValue);
+        }
+
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?);
+        if top_int_cfg8.FRQ_TRACK_BEMF_LIM() >= limit {
+            return Err(Error::InvalidValue);
+        }
+
+        self.write_register(Register::TOP_INT_CFG8, top_int_cfg8.with_RAPID_STOP_LIM(limit).into()).await
+    }
+
+    /// Read the Rapid Stop threshold.
+    pub async fn get_rapid_stop_limit(&mut self) -> Result<u8, Error> {
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?);
+        Ok(top_int_cfg8.RAPID_STOP_LIM())
+    }
+
+    /// Set the BEMF fault limit.
+    ///
+    /// Note: `configure()` disables it for coin ERMs.
+    pub async fn set_bemf_fault_limit(&mut self, limit: BemfFaultLimit) -> Result<(), Error> {
+        let top_int_cfg1 = TOP_INT_CFG1::from(self.read_register(Register::TOP_INT_CFG1).await?)
+            .with_BEMF_FAULT_LIM(limit as u8);
+        self.write_register(Register::TOP_INT_CFG1, top_int_cfg1.into()).await
+    }
+}
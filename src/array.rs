@@ -0,0 +1,121 @@
+//! Managing several DA728x devices as one spatial haptics array.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::{ActuatorConfig, DeviceConfig};
+use crate::errors::Error;
+use crate::registers::{Register, TOP_CTL1};
+use crate::waveform::WaveformMemory;
+use crate::DA728x;
+
+/// A fixed-size group of DA728x devices that are configured identically and
+/// triggered together.
+///
+/// Each device owns its own I2C handle (e.g. a shared-bus device wrapper), so
+/// the devices can live on the same bus at different addresses.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut array = HapticArray::new([front_left, front_right, rear_left, rear_right]);
+/// array.configure(actuator_config, device_config).await?;
+/// array.upload_waveform_memory(&memory, true).await?;
+/// array.enable().await?;
+/// array.play_sequence(0, 0).await?;
+/// ```
+pub struct HapticArray<I2C, const N: usize> {
+    devices: [DA728x<I2C>; N],
+}
+
+impl<I2C, const N: usize> HapticArray<I2C, N>
+where
+    I2C: I2c,
+{
+    /// Create an array from already constructed devices.
+    pub fn new(devices: [DA728x<I2C>; N]) -> Self {
+        Self { devices }
+    }
+
+    /// Get the devices in this array.
+    pub fn devices(&self) -> &[DA728x<I2C>; N] {
+        &self.devices
+    }
+
+    /// Get mutable access to the devices, e.g. to address a single one.
+    pub fn devices_mut(&mut self) -> &mut [DA728x<I2C>; N] {
+        &mut self.devices
+    }
+
+    /// Release the devices.
+    pub fn into_inner(self) -> [DA728x<I2C>; N] {
+        self.devices
+    }
+
+    /// Configure all devices with the same ActuatorConfig and DeviceConfig.
+    pub async fn configure(
+        &mut self,
+        actuator_config: ActuatorConfig,
+        device_config: DeviceConfig,
+    ) -> Result<(), Error> {
+        for device in self.devices.iter_mut() {
+            device.configure(actuator_config, device_config).await?;
+        }
+        Ok(())
+    }
+
+    /// Upload the same waveform memory to all devices.
+    pub async fn upload_waveform_memory(
+        &mut self,
+        memory: &WaveformMemory,
+        lock_after: bool,
+    ) -> Result<(), Error> {
+        for device in self.devices.iter_mut() {
+            device.upload_waveform_memory(memory, lock_after).await?;
+        }
+        Ok(())
+    }
+
+    /// Enable the configured operation mode on all devices.
+    pub async fn enable(&mut self) -> Result<(), Error> {
+        for device in self.devices.iter_mut() {
+            device.enable().await?;
+        }
+        Ok(())
+    }
+
+    /// Disable all devices.
+    pub async fn disable(&mut self) -> Result<(), Error> {
+        for device in self.devices.iter_mut() {
+            device.disable().await?;
+        }
+        Ok(())
+    }
+
+    /// Set the same override value on all devices (DRO mode).
+    pub async fn set_override_value(&mut self, value: i8) -> Result<(), Error> {
+        for device in self.devices.iter_mut() {
+            device.set_override_value(value).await?;
+        }
+        Ok(())
+    }
+
+    /// Select and start the same sequence on all devices with minimal skew.
+    ///
+    /// All sequence selections and TOP_CTL1 reads are done up front, so the
+    /// actual start is a series of back-to-back single-byte writes.
+    pub async fn play_sequence(&mut self, sequence_id: u8, loops: u8) -> Result<(), Error> {
+        let mut top_ctl1 = [0u8; N];
+
+        for (device, value) in self.devices.iter_mut().zip(top_ctl1.iter_mut()) {
+            device.select_sequence(sequence_id, loops).await?;
+            let current = TOP_CTL1::from(device.read_register(Register::TOP_CTL1).await?);
+            *value = current.with_SEQ_START(true).into();
+        }
+
+        for (device, value) in self.devices.iter_mut().zip(top_ctl1.iter()) {
+            device.write_register(Register::TOP_CTL1, *value).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Converting audio into haptic playback plans (requires the `alloc` feature).
+//!
+//! The PCM samples are split into frames. For every frame the amplitude envelope
+//! (mean absolute value, normalised to the loudest frame) becomes the drive level,
+//! and the zero crossings of the low-pass filtered signal give the dominant
+//! low-frequency content. The result can be played back as plain DRO levels or
+//! as a wideband frequency/amplitude schedule.
+
+use alloc::vec::Vec;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::{DrivingMode, OperationMode};
+use crate::effect::HapticEffect;
+use crate::errors::Error;
+use crate::DA728x;
+
+/// Parameters of the audio to haptics conversion.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioConfig {
+    pub sample_rate_Hz: u32,
+    /// Length of one haptic frame, i.e. the envelope rate.
+    pub frame_ms: u32,
+    /// Lowest frequency reported, lower content results in no frequency.
+    pub min_frequency_Hz: u16,
+    /// Cutoff of the low-pass filter, higher content is ignored.
+    pub max_frequency_Hz: u16,
+    /// Frames with a mean absolute value below this are silent.
+    pub noise_floor: u16,
+}
+
+impl AudioConfig {
+    pub const DEFAULT: AudioConfig = AudioConfig {
+        sample_rate_Hz: 8_000,
+        frame_ms: 20,
+        min_frequency_Hz: 25,
+        max_frequency_Hz: 300,
+        noise_floor: 200,
+    };
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// One step of a haptic playback plan.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HapticFrame {
+    pub duration_ms: u32,
+    /// Override value (0..=127).
+    pub level: i8,
+    /// Dominant low frequency, if any was detected.
+    pub frequency_Hz: Option<u16>,
+}
+
+/// Convert PCM samples (mono, signed 16 bit) into a haptic playback plan.
+pub fn audio_to_haptics(samples: &[i16], config: &AudioConfig) -> Result<Vec<HapticFrame>, Error> {
+    let frame_len = (config.sample_rate_Hz as u64 * config.frame_ms as u64 / 1000) as usize;
+    if frame_len == 0 || config.min_frequency_Hz > config.max_frequency_Hz {
+        return Err(Error::InvalidValue);
+    }
+
+    // One-pole low-pass, alpha ≈ 2π·fc/fs in Q16
+    let alpha = (411_775u64 * config.max_frequency_Hz as u64 / config.sample_rate_Hz as u64).min(65_536) as i64;
+    let mut filtered: i64 = 0;
+
+    let mut envelopes = Vec::with_capacity(samples.len() / frame_len + 1);
+    let mut frequencies = Vec::with_capacity(samples.len() / frame_len + 1);
+
+    for frame in samples.chunks(frame_len) {
+        let mut sum: u64 = 0;
+        let mut crossings: u32 = 0;
+        let mut previous_positive = filtered >= 0;
+
+        for &sample in frame {
+            sum += sample.unsigned_abs() as u64;
+            filtered += (sample as i64 - filtered) * alpha / 65_536;
+            let positive = filtered >= 0;
+            if positive != previous_positive {
+                crossings += 1;
+            }
+            previous_positive = positive;
+        }
+
+        let envelope = (sum / frame.len() as u64) as u16;
+        let duration_ms = frame.len() as u32 * 1000 / config.sample_rate_Hz;
+        let frequency = (crossings * 500).checked_div(duration_ms).unwrap_or(0);
+
+        envelopes.push(envelope);
+        frequencies.push((frequency.min(u16::MAX as u32) as u16, duration_ms));
+    }
+
+    let loudest = envelopes.iter().copied().max().unwrap_or(0).max(1);
+
+    Ok(envelopes
+        .iter()
+        .zip(frequencies.iter())
+        .map(|(&envelope, &(frequency, duration_ms))| {
+            let audible = envelope >= config.noise_floor;
+            HapticFrame {
+                duration_ms,
+                level: if audible { (envelope as u32 * 127 / loudest as u32) as i8 } else { 0 },
+                frequency_Hz: (audible
+                    && (config.min_frequency_Hz..=config.max_frequency_Hz).contains(&frequency))
+                .then_some(frequency),
+            }
+        })
+        .collect())
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a haptic plan in DRO mode, then stop the drive.
+    ///
+    /// With WIDEBAND driving, the detected frequencies are applied with `set_frequency()`
+    /// as well, otherwise only the levels are used.
+    /// Every run of non-silent frames is checked against the protection profile.
+    pub async fn play_haptic_plan<D: DelayNs>(
+        &mut self,
+        plan: &[HapticFrame],
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::DRO_MODE {
+            return Err(Error::WrongMode);
+        }
+        let wideband = device_config.driving_mode == DrivingMode::WIDEBAND;
+
+        // Consecutive non-silent frames drive the actuator continuously
+        let (mut on_ms, mut strongest) = (0u32, 0i8);
+        for frame in plan {
+            if frame.level == 0 {
+                (on_ms, strongest) = (0, 0);
+                continue;
+            }
+            on_ms = on_ms.saturating_add(frame.duration_ms);
+            if frame.level.unsigned_abs() > strongest.unsigned_abs() {
+                strongest = frame.level;
+            }
+            self.check_protection(strongest, on_ms)?;
+        }
+
+        for frame in plan {
+            if let (true, Some(frequency)) = (wideband, frame.frequency_Hz) {
+                self.set_frequency(frequency).await?;
+            }
+            self.set_override_value(frame.level).await?;
+            delay.delay_ms(frame.duration_ms).await;
+        }
+
+        self.set_override_value(0).await?;
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+impl HapticEffect for [HapticFrame] {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.play_haptic_plan(self, delay).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(frequency: u32, amplitude: i16, len: usize) -> Vec<i16> {
+        let half_period = (8_000 / frequency / 2) as usize;
+        (0..len)
+            .map(|i| if (i / half_period).is_multiple_of(2) { amplitude } else { -amplitude })
+            .collect()
+    }
+
+    #[test]
+    fn test_envelope_and_frequency() {
+        let config = AudioConfig { frame_ms: 100, ..AudioConfig::DEFAULT };
+        let mut samples = square(100, 10_000, 800);
+        samples.extend(square(100, 5_000, 800));
+        samples.extend(core::iter::repeat_n(0, 800));
+
+        let plan = audio_to_haptics(&samples, &config).unwrap();
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[0].duration_ms, 100);
+        assert_eq!(plan[0].level, 127);
+        assert_eq!(plan[1].level, 63);
+        assert!(plan[0].frequency_Hz.unwrap().abs_diff(100) <= 10);
+        assert_eq!(plan[2].level, 0);
+        assert_eq!(plan[2].frequency_Hz, None);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = AudioConfig { frame_ms: 0, ..AudioConfig::DEFAULT };
+        assert!(audio_to_haptics(&[0; 16], &config).is_err());
+    }
+}
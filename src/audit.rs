@@ -0,0 +1,169 @@
+//! Register readback audit against the state the driver programmed.
+//!
+//! `health_check()` only looks at the configuration registers to detect a reset.
+//! `audit_state()` also compares the registers the driver changes at runtime, so
+//! writes by other bus masters, register scripts or a debugger show up as well.
+//!
+//! `verify_power_on_defaults()` checks the opposite: whether the device still holds
+//! its reset values, i.e. it just powered up or browned out and nobody configured it.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::registers::{Register, SEQ_CTL2, TOP_CTL1};
+use crate::{v2i_register, DA728x};
+
+/// Maximum number of registers compared by `audit_state()` and `verify_power_on_defaults()`.
+pub const MAX_AUDITED_REGISTERS: usize = 16;
+
+/// Reset values of the registers `configure()` and playback change (datasheet register map).
+const POWER_ON_DEFAULTS: [(Register, u8); 13] = [
+    (Register::CIF_I2C1, 0x40),
+    (Register::FRQ_LRA_PER_H, 0x21),
+    (Register::FRQ_LRA_PER_L, 0x4F),
+    (Register::ACTUATOR1, 0x5A),
+    (Register::ACTUATOR2, 0x78),
+    (Register::ACTUATOR3, 0x17),
+    (Register::CALIB_V2I_H, 0x01),
+    (Register::CALIB_V2I_L, 0x0D),
+    (Register::TOP_CFG1, 0x1E),
+    (Register::TOP_CTL1, 0x00),
+    (Register::TOP_CTL2, 0x00),
+    (Register::SEQ_CTL2, 0x00),
+    (Register::MEM_CTL2, 0x80),
+];
+
+/// A register that does not hold the value the driver expects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegisterMismatch {
+    pub register: Register,
+    pub expected: u8,
+    pub actual: u8,
+}
+
+/// Result of [`DA728x::audit_state`] and [`DA728x::verify_power_on_defaults`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateAudit {
+    /// Number of registers compared.
+    pub checked: usize,
+    mismatches: [Option<RegisterMismatch>; MAX_AUDITED_REGISTERS],
+}
+
+impl StateAudit {
+    fn new() -> Self {
+        Self {
+            checked: 0,
+            mismatches: [None; MAX_AUDITED_REGISTERS],
+        }
+    }
+
+    fn compare(&mut self, register: Register, expected: u8, actual: u8) {
+        if expected != actual {
+            self.mismatches[self.checked] = Some(RegisterMismatch { register, expected, actual });
+        }
+        self.checked += 1;
+    }
+
+    /// Whether every compared register matched.
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.iter().all(Option::is_none)
+    }
+
+    /// The registers that did not match.
+    pub fn mismatches(&self) -> impl Iterator<Item = &RegisterMismatch> {
+        self.mismatches.iter().flatten()
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Read back the registers the driver programmed and report every mismatch.
+    ///
+    /// Compares TOP_CFG1, ACTUATOR1..3 and CALIB_V2I with the configuration, TOP_CTL1
+    /// (except SEQ_START, which clears itself) with the last value written by the
+    /// driver and SEQ_CTL2 with the sequence selected by `select_sequence()`.
+    /// Registers the driver has no expectation for are skipped, see `StateAudit::checked`.
+    ///
+    /// # Errors
+    /// Returns `NotConfigured` before `configure()`.
+    pub async fn audit_state(&mut self) -> Result<StateAudit, Error> {
+        let (Some(actuator_config), Some(device_config)) = (self.actuator_config, self.device_config) else {
+            return Err(Error::NotConfigured);
+        };
+
+        let mut audit = StateAudit::new();
+
+        for (register, expected) in self.expected_config_registers(&actuator_config, &device_config) {
+            let actual = self.read_register(register).await?;
+            audit.compare(register, expected, actual);
+        }
+
+        let v2i = v2i_register(&actuator_config).to_be_bytes();
+        let mut actual = [0u8; 2];
+        self.read_registers(Register::CALIB_V2I_H, &mut actual).await?;
+        audit.compare(Register::CALIB_V2I_H, v2i[0], actual[0]);
+        audit.compare(Register::CALIB_V2I_L, v2i[1], actual[1]);
+
+        if let Some(top_ctl1) = self.top_ctl1_cache {
+            let expected = top_ctl1.with_SEQ_START(false);
+            let actual = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?).with_SEQ_START(false);
+            audit.compare(Register::TOP_CTL1, expected.into(), actual.into());
+        }
+
+        if let Some((sequence_id, loops)) = self.selected_sequence {
+            let expected = SEQ_CTL2::new().with_PS_SEQ_ID(sequence_id).with_PS_SEQ_LOOP(loops);
+            let actual = self.read_register(Register::SEQ_CTL2).await?;
+            audit.compare(Register::SEQ_CTL2, expected.into(), actual);
+        }
+
+        Ok(audit)
+    }
+
+    /// Check whether the device looks freshly reset.
+    ///
+    /// Compares the registers written by `configure()`, the operation mode and the
+    /// waveform memory lock with their reset values. A clean result after the device
+    /// was configured means it lost its configuration, e.g. in a brown-out, while
+    /// mismatches on a device that was never configured by this driver mean someone
+    /// else set it up.
+    pub async fn verify_power_on_defaults(&mut self) -> Result<StateAudit, Error> {
+        let mut audit = StateAudit::new();
+
+        for (register, expected) in POWER_ON_DEFAULTS {
+            let actual = self.read_register(register).await?;
+            audit.compare(register, expected, actual);
+        }
+
+        Ok(audit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_collects_mismatches() {
+        let mut audit = StateAudit::new();
+        audit.compare(Register::TOP_CFG1, 0x10, 0x10);
+        assert!(audit.is_clean());
+
+        audit.compare(Register::TOP_CTL1, 0x03, 0x00);
+        audit.compare(Register::SEQ_CTL2, 0x21, 0x21);
+        assert_eq!(audit.checked, 3);
+        assert!(!audit.is_clean());
+
+        let mut mismatches = audit.mismatches();
+        assert_eq!(
+            mismatches.next(),
+            Some(&RegisterMismatch {
+                register: Register::TOP_CTL1,
+                expected: 0x03,
+                actual: 0x00,
+            })
+        );
+        assert_eq!(mismatches.next(), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Multiple waveform memory banks swapped at runtime.
+//!
+//! The waveform memory holds only 100 bytes. Apps with a larger effect set can
+//! prepare several [`WaveformMemory`] images and swap the one on the chip with
+//! `switch_bank()` whenever another set of effects is needed.
+//!
+//! Only the bytes that differ from the image on the chip are rewritten, so a swap
+//! between similar banks takes a few transfers. The memory can only be written
+//! while the device is INACTIVE, so an enabled device is disabled for the swap. The
+//! returned [`BankSwap`] estimates how long the device was unavailable.
+//!
+//! Applications usually refer to effects by name rather than by bank and sequence
+//! ID. Implement [`BankedEffect`] for an effect enum and `play_effect()` loads the
+//! right bank and plays the sequence:
+//!
+//! ```ignore
+//! #[derive(Clone, Copy)]
+//! enum Effect { Click, Success, Explosion }
+//!
+//! impl BankedEffect for Effect {
+//!     fn location(&self) -> EffectLocation {
+//!         match self {
+//!             Effect::Click => EffectLocation { bank: 0, sequence_id: 0 },
+//!             Effect::Success => EffectLocation { bank: 0, sequence_id: 1 },
+//!             Effect::Explosion => EffectLocation { bank: 1, sequence_id: 0 },
+//!         }
+//!     }
+//! }
+//!
+//! let mut banks = WaveformBanks::new([&ui_effects, &game_effects]);
+//! banks.play_effect(&mut haptics, Effect::Explosion).await?;
+//! ```
+
+use core::ops::Range;
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::registers::{Register, MEM_CTL2};
+use crate::waveform::WaveformMemory;
+use crate::DA728x;
+
+/// Unchanged bytes up to this gap are rewritten rather than starting a new transfer,
+/// which would cost a start condition, the device and the register address.
+const MERGE_GAP: usize = 2;
+
+/// Summary of a `switch_bank()`.
+///
+/// The transfer counts are the driver's estimate of the swap itself, restarting a
+/// looping sequence is not included. With the `stats` feature, `i2c_stats()`
+/// counts the actual transfers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BankSwap {
+    /// Waveform memory bytes written.
+    pub bytes_written: usize,
+    /// Whether the operation mode had to be disabled during the swap.
+    pub disabled: bool,
+    /// I2C transfers of the swap, including disabling, the TOP_CTL1 reads, unlocking,
+    /// locking and enabling.
+    pub transfers: usize,
+    /// Bytes sent and received in these transfers, including register addresses.
+    pub transfer_bytes: usize,
+}
+
+impl BankSwap {
+    /// Estimated duration of the swap at an I2C clock of `bus_hz` in µs, the time
+    /// the device was unavailable if it had to be disabled.
+    ///
+    /// Counts 9 clocks per byte plus the device address, start and stop of every
+    /// transfer, HAL overhead between transfers comes on top.
+    pub fn estimated_duration_us(&self, bus_hz: u32) -> u32 {
+        let clocks = (self.transfer_bytes + self.transfers) as u64 * 9 + self.transfers as u64 * 2;
+        (clocks * 1_000_000 / bus_hz.max(1) as u64) as u32
+    }
+
+    /// Count a single register write or read: the register address and one value.
+    fn add_register_transfer(&mut self) {
+        self.transfers += 1;
+        self.transfer_bytes += 2;
+    }
+}
+
+/// Byte ranges where `new` differs from `old`, joining ranges up to `MERGE_GAP` apart.
+fn changed_ranges<'a>(old: &'a [u8], new: &'a [u8]) -> impl Iterator<Item = Range<usize>> + 'a {
+    let differs = move |i: usize| old.get(i) != Some(&new[i]);
+    let mut pos = 0;
+
+    core::iter::from_fn(move || {
+        let start = (pos..new.len()).find(|&i| differs(i))?;
+        let mut end = start + 1;
+        while let Some(next) = (end..new.len().min(end + MERGE_GAP + 1)).find(|&i| differs(i)) {
+            end = next + 1;
+        }
+        pos = end;
+        Some(start..end)
+    })
+}
+
+/// Where an effect is stored in a [`WaveformBanks`] set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectLocation {
+    /// Index of the bank.
+    pub bank: usize,
+    /// Sequence ID within the bank.
+    pub sequence_id: u8,
+}
+
+/// An application effect stored as a sequence in one of the waveform banks.
+pub trait BankedEffect {
+    /// The bank and sequence the effect is stored in, checked by `WaveformBanks::locate()`.
+    fn location(&self) -> EffectLocation;
+}
+
+impl BankedEffect for EffectLocation {
+    fn location(&self) -> EffectLocation {
+        *self
+    }
+}
+
+/// A fixed set of waveform memory images, one of them active on the device.
+#[derive(Debug, Clone, Copy)]
+pub struct WaveformBanks<'a, const N: usize> {
+    banks: [&'a WaveformMemory; N],
+    active: Option<usize>,
+}
+
+impl<'a, const N: usize> WaveformBanks<'a, N> {
+    /// No bank is active until the first `switch_to()`.
+    pub fn new(banks: [&'a WaveformMemory; N]) -> Self {
+        Self { banks, active: None }
+    }
+
+    /// The bank with the given index.
+    pub fn bank(&self, index: usize) -> Option<&'a WaveformMemory> {
+        self.banks.get(index).copied()
+    }
+
+    /// Index of the bank last switched to.
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Make bank `index` the active one on `device`, see `DA728x::switch_bank()`.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` for an unknown bank, otherwise like `switch_bank()`.
+    pub async fn switch_to<I2C: I2c>(&mut self, device: &mut DA728x<I2C>, index: usize) -> Result<BankSwap, Error> {
+        let memory = self.bank(index).ok_or(Error::InvalidValue)?;
+        self.active = None;
+        let swap = device.switch_bank(memory).await?;
+        self.active = Some(index);
+        Ok(swap)
+    }
+
+    /// Check that `effect` refers to an existing bank and sequence.
+    pub fn locate<E: BankedEffect>(&self, effect: &E) -> Result<EffectLocation, Error> {
+        let location = effect.location();
+        let memory = self.bank(location.bank).ok_or(Error::InvalidValue)?;
+        if location.sequence_id >= memory.num_sequences() {
+            return Err(Error::InvalidValue);
+        }
+        Ok(location)
+    }
+
+    /// Play `effect` once, switching to its bank first if needed.
+    ///
+    /// The device must be configured for RTWM_MODE and enabled. The bank is always
+    /// passed to `switch_bank()`, which only writes if the memory on the chip differs,
+    /// so a memory uploaded by other code since the last switch is replaced.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if the effect's bank or sequence does not exist,
+    /// `WrongMode` outside RTWM_MODE, otherwise like `switch_to()`.
+    pub async fn play_effect<I2C: I2c, E: BankedEffect>(
+        &mut self,
+        device: &mut DA728x<I2C>,
+        effect: E,
+    ) -> Result<(), Error> {
+        let location = self.locate(&effect)?;
+        if device.device_config().map(|config| config.operation_mode) != Some(OperationMode::RTWM_MODE)
+            || !device.is_enabled()
+        {
+            return Err(Error::WrongMode);
+        }
+
+        self.switch_to(device, location.bank).await?;
+
+        device.play_sequence(location.sequence_id, 0).await
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Replace the waveform memory on the chip with `memory` and resume.
+    ///
+    /// Only the bytes differing from the image uploaded last are written, the whole
+    /// image if it is not known. If the device was enabled, the operation mode is
+    /// disabled for the swap and re-enabled afterwards, and a sequence started with
+    /// `play_sequence_forever()` is restarted if the new image has a sequence with the
+    /// same ID. The memory is locked afterwards.
+    ///
+    /// # Errors
+    /// Returns `DeviceBusy` if the device is still active after disabling it, e.g.
+    /// started with a command on a blocking bus, see `set_busy_policy()`.
+    pub async fn switch_bank(&mut self, memory: &WaveformMemory) -> Result<BankSwap, Error> {
+        self.check_memory_size(memory)?;
+
+        let new = memory.as_bytes();
+        let mut old = [0u8; crate::waveform::MAX_MEMORY_SIZE];
+        let old = match self.uploaded_memory {
+            Some((uploaded, locked)) if locked && uploaded.as_bytes() == new => return Ok(BankSwap::default()),
+            Some((uploaded, _)) => {
+                old[..uploaded.len()].copy_from_slice(uploaded.as_bytes());
+                &old[..uploaded.len()]
+            }
+            None => &old[..0],
+        };
+
+        let mut swap = BankSwap {
+            disabled: self.enabled,
+            ..BankSwap::default()
+        };
+
+        let looping_sequence = self.looping_sequence;
+        if swap.disabled {
+            if self.top_ctl1_cache.is_none() {
+                swap.add_register_transfer();
+            }
+            self.disable().await?;
+            swap.add_register_transfer();
+        }
+
+        // `ensure_idle()` already read TOP_CTL1, so MEM_CTL2 is written directly
+        // instead of reading it again in `unlock_waveform_memory()`
+        self.ensure_idle().await?;
+        swap.add_register_transfer();
+        self.uploaded_memory = None;
+        self.write_register(Register::MEM_CTL2, MEM_CTL2::new().with_WAV_MEM_LOCK(true).into()).await?;
+        swap.add_register_transfer();
+
+        let chunk_size = self.memory_chunk_size();
+        for range in changed_ranges(old, new) {
+            swap.bytes_written += range.len();
+            swap.transfers += range.len().div_ceil(chunk_size);
+            swap.transfer_bytes += range.len() + range.len().div_ceil(chunk_size);
+            self.write_memory_bytes(range.start, &new[range]).await?;
+        }
+
+        self.lock_waveform_memory().await?;
+        self.uploaded_memory = Some((*memory, true));
+        swap.add_register_transfer();
+
+        if swap.disabled {
+            self.enable().await?;
+            swap.add_register_transfer();
+
+            if let Some(sequence_id) = looping_sequence
+                && sequence_id < memory.num_sequences()
+            {
+                self.play_sequence_forever(sequence_id).await?;
+            }
+        }
+
+        Ok(swap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_ranges() {
+        let old = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let new = [1, 0, 3, 4, 5, 6, 0, 8, 0, 10, 11, 12];
+
+        let mut ranges = changed_ranges(&old, &new);
+        assert_eq!(ranges.next(), Some(1..2));
+        // Joined across the unchanged byte 7, and past the end of the old image
+        assert_eq!(ranges.next(), Some(6..12));
+        assert_eq!(ranges.next(), None);
+
+        assert_eq!(changed_ranges(&old, &old).next(), None);
+        assert_eq!(changed_ranges(&[], &new).next(), Some(0..12));
+    }
+
+    #[test]
+    fn test_locate_effect() {
+        use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+
+        let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+        let banks = WaveformBanks::new([&memory, &memory]);
+
+        let location = EffectLocation { bank: 1, sequence_id: 1 };
+        assert_eq!(banks.locate(&location).unwrap(), location);
+        assert!(matches!(banks.locate(&EffectLocation { bank: 2, sequence_id: 0 }), Err(Error::InvalidValue)));
+        assert!(matches!(banks.locate(&EffectLocation { bank: 0, sequence_id: 2 }), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_switch_bank_disables_every_mode() {
+        use crate::config::DrivingMode;
+        use crate::mock::{block_on, device_config, lra, MockDevice};
+        use crate::registers::TOP_CTL1;
+        use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+        use crate::Variant;
+
+        let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+            haptic.enable().await.unwrap();
+
+            #[cfg(feature = "stats")]
+            let before = haptic.i2c_stats();
+            let swap = haptic.switch_bank(&memory).await.unwrap();
+            assert!(swap.disabled);
+            assert!(haptic.is_enabled());
+            #[cfg(feature = "stats")]
+            {
+                let cost = haptic.i2c_stats().since(&before);
+                assert_eq!(swap.transfers, (cost.reads + cost.writes) as usize);
+                assert_eq!(swap.transfer_bytes, (cost.bytes_read + cost.bytes_written) as usize);
+            }
+
+            let top_ctl1 = TOP_CTL1::from(haptic.i2c.registers[Register::TOP_CTL1 as usize]);
+            assert_eq!(top_ctl1.OPERATION_MODE(), OperationMode::DRO_MODE as u8);
+            assert_eq!(haptic.switch_bank(&memory).await.unwrap(), BankSwap::default());
+        });
+    }
+
+    #[test]
+    fn test_play_effect_restores_replaced_memory() {
+        use crate::config::DrivingMode;
+        use crate::mock::{block_on, device_config, lra, MockDevice};
+        use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+        use crate::Variant;
+
+        let memory = |end: u8| {
+            let snippet = SnippetBuilder::new().ramp(1, end).unwrap().build().unwrap();
+            let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+            let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+            WaveformMemoryBuilder::new(true)
+                .add_snippet(snippet).unwrap()
+                .add_sequence(sequence).unwrap()
+                .build()
+                .unwrap()
+        };
+        let (ui, other) = (memory(15), memory(8));
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::RTWM_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+            haptic.enable().await.unwrap();
+
+            let mut banks = WaveformBanks::new([&ui]);
+            let effect = EffectLocation { bank: 0, sequence_id: 0 };
+            banks.play_effect(&mut haptic, effect).await.unwrap();
+
+            haptic.disable().await.unwrap();
+            haptic.upload_waveform_memory(&other, true).await.unwrap();
+            haptic.enable().await.unwrap();
+
+            banks.play_effect(&mut haptic, effect).await.unwrap();
+            let (uploaded, _) = haptic.uploaded_memory.unwrap();
+            assert_eq!(uploaded.as_bytes(), ui.as_bytes());
+        });
+    }
+
+    #[test]
+    fn test_swap_duration() {
+        let swap = BankSwap {
+            bytes_written: 8,
+            disabled: true,
+            transfers: 4,
+            transfer_bytes: 16,
+        };
+        // (16 + 4) * 9 + 4 * 2 = 188 clocks
+        assert_eq!(swap.estimated_duration_us(400_000), 470);
+        assert_eq!(swap.estimated_duration_us(100_000), 1_880);
+    }
+}
@@ -0,0 +1,247 @@
+//! Duty-cycle budget protecting the actuator from overheating.
+//!
+//! Most actuators are only rated for continuous drive at a limited duty cycle,
+//! e.g. at most 30 % on-time over any 10 s. [`DutyCycleBudget`] records the on-time
+//! of the effects played and admits a new effect only if it keeps the on-time within
+//! the window below the limit. Rejected effects can be retried after the returned
+//! time, or deferred with `admit_or_wait()`.
+//!
+//! The chip has no notion of wall-clock time, so timestamps are passed in by the
+//! caller, e.g. `embassy_time::Instant::now().as_millis()`.
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::errors::Error;
+
+/// Duty-cycle rating of an actuator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DutyCycleLimit {
+    /// Maximum on-time within any `window_ms`, in percent (1..=100).
+    pub max_percent: u8,
+    /// Length of the sliding window in ms.
+    pub window_ms: u32,
+    /// Minimum off-time between two effects in ms (cool-down).
+    pub cool_down_ms: u32,
+}
+
+impl DutyCycleLimit {
+    /// Maximum on-time within the window in ms.
+    pub fn max_on_ms(&self) -> u32 {
+        (self.window_ms as u64 * self.max_percent as u64 / 100) as u32
+    }
+}
+
+/// An effect played at `start_ms` for `duration_ms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OnTime {
+    start_ms: u64,
+    duration_ms: u32,
+}
+
+impl OnTime {
+    fn end_ms(&self) -> u64 {
+        self.start_ms + self.duration_ms as u64
+    }
+}
+
+/// Tracks the on-time of the last `N` effects against a [`DutyCycleLimit`].
+///
+/// If more than `N` effects fall into the window, the two oldest are merged into
+/// one, counting their on-time as late as possible. This never underestimates the
+/// on-time, but may defer effects a little longer than needed.
+#[derive(Debug, Clone)]
+pub struct DutyCycleBudget<const N: usize> {
+    limit: DutyCycleLimit,
+    records: [OnTime; N],
+    len: usize,
+}
+
+impl<const N: usize> DutyCycleBudget<N> {
+    /// # Errors
+    /// Returns `InvalidValue` if `max_percent` is not in 1..=100, the window is
+    /// empty or `N` is less than 2.
+    pub fn new(limit: DutyCycleLimit) -> Result<Self, Error> {
+        if !(1..=100).contains(&limit.max_percent) || limit.window_ms == 0 || N < 2 {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self {
+            limit,
+            records: [OnTime { start_ms: 0, duration_ms: 0 }; N],
+            len: 0,
+        })
+    }
+
+    pub fn limit(&self) -> DutyCycleLimit {
+        self.limit
+    }
+
+    /// Forget all recorded on-time, e.g. after the actuator had time to cool down.
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    /// On-time recorded within the window ending at `now_ms`.
+    pub fn on_time_ms(&self, now_ms: u64) -> u32 {
+        self.on_time_between(now_ms.saturating_sub(self.limit.window_ms as u64), now_ms)
+    }
+
+    fn on_time_between(&self, from_ms: u64, to_ms: u64) -> u32 {
+        self.records[..self.len]
+            .iter()
+            .map(|record| {
+                let start = record.start_ms.max(from_ms);
+                let end = record.end_ms().min(to_ms);
+                end.saturating_sub(start) as u32
+            })
+            .sum()
+    }
+
+    /// Whether an effect of `duration_ms` started at `start_ms` stays within the limit.
+    fn fits(&self, start_ms: u64, duration_ms: u32) -> bool {
+        if let Some(last) = self.records[..self.len].last()
+            && start_ms < last.end_ms() + self.limit.cool_down_ms as u64
+        {
+            return false;
+        }
+
+        let end_ms = start_ms + duration_ms as u64;
+        let window_start = end_ms.saturating_sub(self.limit.window_ms as u64);
+        self.on_time_between(window_start, end_ms) + duration_ms <= self.limit.max_on_ms()
+    }
+
+    /// Time until an effect of `duration_ms` fits into the budget, 0 if it fits now.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if the effect alone exceeds the limit.
+    pub fn wait_time_ms(&self, now_ms: u64, duration_ms: u32) -> Result<u32, Error> {
+        if duration_ms > self.limit.max_on_ms() {
+            return Err(Error::InvalidValue);
+        }
+        if self.fits(now_ms, duration_ms) {
+            return Ok(0);
+        }
+
+        // The recorded on-time only leaves the window as time passes, so the
+        // earliest start that fits can be found by bisection. Recorded effects end
+        // within one window from now, so everything fits after two windows.
+        let mut low = 0u64;
+        let mut high = 2 * self.limit.window_ms as u64 + self.limit.cool_down_ms as u64;
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if self.fits(now_ms + mid, duration_ms) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        Ok(high as u32)
+    }
+
+    /// Record an effect of `duration_ms` starting at `now_ms` if it fits into the budget.
+    ///
+    /// # Errors
+    /// Returns `DutyCycleExceeded` with the time after which the effect would fit,
+    /// or `InvalidValue` if the effect alone exceeds the limit.
+    pub fn admit(&mut self, now_ms: u64, duration_ms: u32) -> Result<(), Error> {
+        let retry_in_ms = self.wait_time_ms(now_ms, duration_ms)?;
+        if retry_in_ms > 0 {
+            return Err(Error::DutyCycleExceeded { retry_in_ms });
+        }
+
+        self.record(now_ms, duration_ms);
+        Ok(())
+    }
+
+    /// Like `admit()`, but wait until the effect fits instead of rejecting it.
+    ///
+    /// Returns the time waited in ms, the effect is recorded as starting after the wait.
+    pub async fn admit_or_wait<D: DelayNs>(
+        &mut self,
+        now_ms: u64,
+        duration_ms: u32,
+        delay: &mut D,
+    ) -> Result<u32, Error> {
+        let wait_ms = self.wait_time_ms(now_ms, duration_ms)?;
+        if wait_ms > 0 {
+            delay.delay_ms(wait_ms).await;
+        }
+
+        self.record(now_ms + wait_ms as u64, duration_ms);
+        Ok(wait_ms)
+    }
+
+    fn record(&mut self, start_ms: u64, duration_ms: u32) {
+        // Drop effects that left the window
+        let window_start = start_ms.saturating_sub(self.limit.window_ms as u64);
+        let expired = self.records[..self.len]
+            .iter()
+            .take_while(|record| record.end_ms() <= window_start)
+            .count();
+        self.records.copy_within(expired..self.len, 0);
+        self.len -= expired;
+
+        if self.len == N {
+            // Merge the two oldest, ending with the second one
+            let duration_ms = self.records[0].duration_ms + self.records[1].duration_ms;
+            self.records[1] = OnTime {
+                start_ms: self.records[1].end_ms().saturating_sub(duration_ms as u64),
+                duration_ms,
+            };
+            self.records.copy_within(1..self.len, 0);
+            self.len -= 1;
+        }
+
+        self.records[self.len] = OnTime { start_ms, duration_ms };
+        self.len += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: DutyCycleLimit = DutyCycleLimit {
+        max_percent: 30,
+        window_ms: 10_000,
+        cool_down_ms: 0,
+    };
+
+    #[test]
+    fn test_duty_cycle_window() {
+        let mut budget = DutyCycleBudget::<8>::new(LIMIT).unwrap();
+        budget.admit(0, 2_000).unwrap();
+        budget.admit(2_000, 1_000).unwrap();
+        assert_eq!(budget.on_time_ms(3_000), 3_000);
+
+        // The window is used up until the first effect starts leaving it
+        match budget.admit(3_000, 500) {
+            Err(Error::DutyCycleExceeded { retry_in_ms }) => assert_eq!(retry_in_ms, 7_000),
+            _ => panic!("effect should be deferred"),
+        }
+        budget.admit(10_500, 500).unwrap();
+
+        assert!(matches!(budget.admit(20_000, 3_001), Err(Error::InvalidValue)));
+        assert!(DutyCycleBudget::<8>::new(DutyCycleLimit { max_percent: 0, ..LIMIT }).is_err());
+    }
+
+    #[test]
+    fn test_cool_down() {
+        let mut budget = DutyCycleBudget::<4>::new(DutyCycleLimit { cool_down_ms: 200, ..LIMIT }).unwrap();
+        budget.admit(0, 100).unwrap();
+        assert_eq!(budget.wait_time_ms(150, 100).unwrap(), 150);
+        budget.admit(300, 100).unwrap();
+    }
+
+    #[test]
+    fn test_merging_is_conservative() {
+        let mut budget = DutyCycleBudget::<2>::new(LIMIT).unwrap();
+        budget.admit(0, 1_000).unwrap();
+        budget.admit(1_000, 1_000).unwrap();
+        budget.admit(4_000, 1_000).unwrap();
+        assert_eq!(budget.on_time_ms(5_000), 3_000);
+
+        // Merged into 0..2000, nothing was lost
+        assert_eq!(budget.wait_time_ms(5_000, 1_000).unwrap(), 5_000);
+    }
+}
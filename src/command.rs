@@ -0,0 +1,305 @@
+//! Synchronous command layer for interrupt and RTIC contexts.
+//!
+//! A [`Command`] is a single, pre-encoded register write. Commands can be created
+//! up front (validated against the configuration by the async driver), queued in a
+//! fixed-capacity [`CommandQueue`] and sent from an ISR or RTIC task with a blocking
+//! `embedded_hal::i2c::I2c`, one command per `poll()`. The async driver sends all of
+//! its register writes through the same encoding.
+//!
+//! # Example
+//!
+//! ```ignore
+//! // During setup, with the async driver
+//! let fire = haptic.trigger_command()?;
+//! let address = haptic.address();
+//!
+//! // Later, in an interrupt handler with a blocking I2C bus
+//! queue.push(fire).ok();
+//! while queue.poll(&mut i2c, address)? {}
+//! ```
+
+use embedded_hal::i2c::{Error as _, I2c};
+use embedded_hal_async::i2c::I2c as AsyncI2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::registers::{Register, SEQ_CTL2, TOP_CTL1, TOP_CTL2};
+use crate::DA728x;
+
+/// A single pre-encoded register write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Command {
+    bytes: [u8; 2],
+}
+
+impl Command {
+    /// Write `value` to `register`.
+    pub const fn write(register: Register, value: u8) -> Self {
+        Self {
+            bytes: [register as u8, value],
+        }
+    }
+
+    /// Write `value` to a raw register address, e.g. from a register script.
+    pub const fn write_raw(address: u8, value: u8) -> Self {
+        Self {
+            bytes: [address, value],
+        }
+    }
+
+    /// Register address of this command.
+    pub fn register(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    /// Value written by this command.
+    pub fn value(&self) -> u8 {
+        self.bytes[1]
+    }
+
+    /// The bytes sent on the bus.
+    pub fn as_bytes(&self) -> &[u8; 2] {
+        &self.bytes
+    }
+
+    /// Send the command with a blocking I2C bus.
+    pub fn execute<I: I2c>(&self, i2c: &mut I, address: u8) -> Result<(), Error> {
+        i2c.write(address, &self.bytes).map_err(|e| Error::I2c(e.kind()))
+    }
+}
+
+/// A fixed-capacity FIFO of commands.
+#[derive(Debug, Clone)]
+pub struct CommandQueue<const N: usize> {
+    commands: [Command; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for CommandQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CommandQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            commands: [Command { bytes: [0; 2] }; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Append a command, handing it back if the queue is full.
+    pub fn push(&mut self, command: Command) -> Result<(), Command> {
+        if self.len == N {
+            return Err(command);
+        }
+        self.commands[(self.head + self.len) % N] = command;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Get the next command without removing it.
+    pub fn peek(&self) -> Option<&Command> {
+        (self.len > 0).then(|| &self.commands[self.head])
+    }
+
+    /// Remove and return the next command.
+    pub fn pop(&mut self) -> Option<Command> {
+        let command = *self.peek()?;
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(command)
+    }
+
+    /// Send the next command, if any.
+    ///
+    /// Returns whether a command was sent. On a bus error the command stays
+    /// queued, so the next `poll()` retries it.
+    pub fn poll<I: I2c>(&mut self, i2c: &mut I, address: u8) -> Result<bool, Error> {
+        let Some(command) = self.peek() else {
+            return Ok(false);
+        };
+        command.execute(i2c, address)?;
+        self.pop();
+        Ok(true)
+    }
+}
+
+/// Encode a DRO override value into TOP_CTL2.OVERRIDE_VAL.
+///
+/// With acceleration enabled the chip reads the register as an unsigned amplitude,
+/// so only 0..=127 is valid and maps 1:1. Without acceleration it is a two's
+/// complement amplitude, negative values drive the actuator in reverse. The range
+/// is symmetric -127..=127, -128 (0x80) is rejected.
+pub fn override_register_value(value: i8, acceleration: bool) -> Result<u8, Error> {
+    if value == i8::MIN || (acceleration && value < 0) {
+        return Err(Error::InvalidValue);
+    }
+
+    Ok(value as u8)
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: AsyncI2c,
+{
+    /// Send a command with the async I2C bus.
+    ///
+    /// TOP_CTL1 writes also update the value cached for `disable()` and friends.
+    /// Commands executed on a blocking bus don't, which is fine for `trigger_command()`
+    /// and `stop_command()` as they only differ from the cache in SEQ_START.
+    pub async fn send(&mut self, command: Command) -> Result<(), Error> {
+        self.count_transfer(2, 0);
+        self.i2c
+            .write(self.address, command.as_bytes())
+            .await
+            .map_err(|e| Error::I2c(e.kind()))?;
+
+        if command.register() == Register::TOP_CTL1 as u8 {
+            self.top_ctl1_cache = Some(TOP_CTL1::from(command.value()));
+        }
+        Ok(())
+    }
+
+    /// Encode `set_override_value()` as a command, validated against the configuration.
+    ///
+    /// The value is scaled by the supply droop compensation and then clamped to the
+    /// ceiling set with `set_max_level()`, lowered by the thermal derating.
+    /// Sending the command directly bypasses the driver, so `drive_erm()` and
+    /// `crossfade_to()` don't know about the new level.
+    pub fn override_command(&self, value: i8) -> Result<Command, Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if self.actuator_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+
+        override_register_value(value, device_config.acceleration)?;
+        let raw = self.compensated_level(value) as u8;
+
+        if device_config.operation_mode != OperationMode::DRO_MODE {
+            return Err(Error::WrongMode);
+        }
+
+        Ok(Command::write(Register::TOP_CTL2, TOP_CTL2::from(raw).into()))
+    }
+
+    /// Encode `select_sequence()` as a command.
+    pub fn select_sequence_command(sequence_id: u8, loops: u8) -> Result<Command, Error> {
+        if sequence_id > 15 || loops > 15 {
+            return Err(Error::InvalidValue);
+        }
+
+        let seq_ctl2 = SEQ_CTL2::new()
+            .with_PS_SEQ_ID(sequence_id)
+            .with_PS_SEQ_LOOP(loops);
+        Ok(Command::write(Register::SEQ_CTL2, seq_ctl2.into()))
+    }
+
+    /// Encode `trigger()` as a command. The device must have been enabled with `enable()`.
+    pub fn trigger_command(&self) -> Result<Command, Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if !self.enabled {
+            return Err(Error::WrongMode);
+        }
+
+        Ok(Command::write(Register::TOP_CTL1, Self::top_ctl1(&device_config, true).into()))
+    }
+
+    /// Encode a command clearing SEQ_START in the configured operation mode.
+    pub fn stop_command(&self) -> Result<Command, Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if !self.enabled {
+            return Err(Error::WrongMode);
+        }
+
+        Ok(Command::write(Register::TOP_CTL1, Self::top_ctl1(&device_config, false).into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal::i2c::{ErrorType, Operation};
+
+    #[derive(Default)]
+    struct Recorder {
+        writes: [[u8; 2]; 4],
+        count: usize,
+    }
+
+    impl ErrorType for Recorder {
+        type Error = Infallible;
+    }
+
+    impl I2c for Recorder {
+        fn transaction(&mut self, _address: u8, operations: &mut [Operation<'_>]) -> Result<(), Infallible> {
+            for operation in operations {
+                if let Operation::Write(bytes) = operation {
+                    self.writes[self.count].copy_from_slice(bytes);
+                    self.count += 1;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_queue_order_and_capacity() {
+        let mut queue = CommandQueue::<2>::new();
+        assert!(queue.push(Command::write(Register::TOP_CTL2, 0x40)).is_ok());
+        assert!(queue.push(Command::write(Register::TOP_CTL2, 0x00)).is_ok());
+        assert!(queue.push(Command::write(Register::TOP_CTL1, 0x11)).is_err());
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.pop().unwrap().value(), 0x40);
+        assert!(queue.push(Command::write(Register::TOP_CTL1, 0x11)).is_ok());
+        assert_eq!(queue.pop().unwrap().value(), 0x00);
+        assert_eq!(queue.pop().unwrap().register(), Register::TOP_CTL1 as u8);
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn test_override_register_value() {
+        assert_eq!(override_register_value(0, false).unwrap(), 0x00);
+        assert_eq!(override_register_value(127, false).unwrap(), 0x7F);
+        assert_eq!(override_register_value(-1, false).unwrap(), 0xFF);
+        assert_eq!(override_register_value(-127, false).unwrap(), 0x81);
+        assert!(matches!(override_register_value(-128, false), Err(Error::InvalidValue)));
+
+        assert_eq!(override_register_value(0, true).unwrap(), 0x00);
+        assert_eq!(override_register_value(127, true).unwrap(), 0x7F);
+        assert!(matches!(override_register_value(-1, true), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_queue_poll() {
+        let mut queue = CommandQueue::<4>::new();
+        let mut i2c = Recorder::default();
+        queue.push(Command::write(Register::TOP_CTL2, 0x7F)).unwrap();
+        queue.push(Command::write(Register::TOP_CTL2, 0x00)).unwrap();
+
+        assert!(queue.poll(&mut i2c, 0x4A).unwrap());
+        assert!(queue.poll(&mut i2c, 0x4A).unwrap());
+        assert!(!queue.poll(&mut i2c, 0x4A).unwrap());
+        assert_eq!(i2c.count, 2);
+        assert_eq!(i2c.writes[0], [Register::TOP_CTL2 as u8, 0x7F]);
+        assert_eq!(i2c.writes[1], [Register::TOP_CTL2 as u8, 0x00]);
+    }
+}
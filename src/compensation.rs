@@ -0,0 +1,268 @@
+//! Drive level compensation for a sagging supply and thermal derating.
+//!
+//! Coin cells and small LiPos sag under load, and the actuator feels weaker as the
+//! supply drops. With a [`DroopCompensation`] set, `update_droop_compensation()`
+//! reads the supply measurement and scales every DRO override value by the ratio
+//! of the reference reading to the current one, up to a maximum boost. The scaled
+//! value is still clamped to `max_level()`.
+//!
+//! The DA728x only updates the supply measurement during playback, so call
+//! `update_droop_compensation()` periodically while driving, e.g. every 100 ms
+//! or between the notes of a melody.
+//!
+//! [`ThermalDerating`] lowers the override ceiling step by step while the device
+//! reports the overtemperature warning, instead of waiting for the critical
+//! threshold to shut the output down, and raises it again once the warning is gone.
+//!
+//! Actuators vary from unit to unit. `calibrate_unit_strength()` derives a per-unit
+//! scale factor from a strength measured at production, e.g. the peak acceleration
+//! from a test fixture, and scales the drive reference with it from then on. The
+//! DA728x only uses the back-EMF internally (frequency tracking, BEMF_FAULT_LIM) and
+//! has no register to read it back, so the measurement has to come from outside.
+//! Store the factor with the unit and restore it with `set_unit_calibration()` at
+//! boot, or keep it in the blob written by `save_profile()`.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::registers::{Register, IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_STATUS1};
+use crate::telemetry::{Channel, ThermalState};
+use crate::DA728x;
+
+/// Amplitude compensation relative to a reference supply reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DroopCompensation {
+    /// Raw supply measurement (ADC_VDD_H/L) at which no compensation is applied,
+    /// e.g. read with `read_measurement(Channel::Supply)` on a full battery.
+    pub reference_raw: u16,
+    /// Maximum increase of the override value in percent.
+    pub max_boost_percent: u8,
+}
+
+impl DroopCompensation {
+    /// Scale factor in ‰ for a raw supply measurement.
+    ///
+    /// A supply at or above the reference is not compensated. A reading of 0 means
+    /// no measurement was taken yet and is not compensated either.
+    pub fn scale_permille(&self, supply_raw: u16) -> u16 {
+        if supply_raw == 0 {
+            return 1000;
+        }
+
+        let max_permille = 1000 + self.max_boost_percent as u32 * 10;
+        (self.reference_raw as u32 * 1000 / supply_raw as u32).clamp(1000, max_permille) as u16
+    }
+}
+
+/// Progressive reduction of the override ceiling on overtemperature warnings.
+///
+/// The DA728x has no temperature reading, only the 105 °C warning and the 125 °C
+/// critical threshold, so the derating steps down on every update that sees the
+/// warning and recovers on every update that doesn't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalDerating {
+    /// Reduction per update while the warning is raised, in percent of `max_level()`.
+    pub step_percent: u8,
+    /// Lowest ceiling in percent of `max_level()`, also used above the critical threshold.
+    pub min_percent: u8,
+    /// Increase per update without warning, in percent of `max_level()`.
+    pub recovery_percent: u8,
+}
+
+impl ThermalDerating {
+    /// The ceiling in percent after an update seeing `thermal`.
+    pub fn next_percent(&self, percent: u8, thermal: ThermalState) -> u8 {
+        let min_percent = self.min_percent.min(100);
+        match thermal {
+            ThermalState::Normal => percent.saturating_add(self.recovery_percent).min(100),
+            ThermalState::Warning => percent.saturating_sub(self.step_percent).max(min_percent),
+            ThermalState::Critical => min_percent,
+        }
+    }
+}
+
+/// Unit scale factor in ‰ that brings a `measured` strength to `target`, starting
+/// from the factor `current_permille` the measurement was taken with.
+///
+/// The drive reference can't exceed the rated voltage, so the factor is at most 1000:
+/// calibrate to the weakest unit of a product line.
+pub fn unit_scale_permille(current_permille: u16, measured: u32, target: u32) -> Result<u16, Error> {
+    if measured == 0 {
+        return Err(Error::InvalidValue);
+    }
+
+    let permille = current_permille as u64 * target as u64 / measured as u64;
+    Ok(permille.clamp(1, 1000) as u16)
+}
+
+/// Scale an override value by `scale_permille`, rounding towards zero.
+pub(crate) fn scale_level(level: i8, scale_permille: u16) -> i32 {
+    level as i32 * scale_permille as i32 / 1000
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Enable supply droop compensation, `None` disables it and removes any scaling.
+    pub fn set_droop_compensation(&mut self, compensation: Option<DroopCompensation>) {
+        self.droop_compensation = compensation;
+        self.droop_scale_permille = 1000;
+    }
+
+    /// The compensation set with `set_droop_compensation()`.
+    pub fn droop_compensation(&self) -> Option<&DroopCompensation> {
+        self.droop_compensation.as_ref()
+    }
+
+    /// Read the supply and update the scaling of DRO override values.
+    ///
+    /// The new scaling applies from the next override value on. Returns the scale
+    /// factor in ‰, 1000 if no compensation is set.
+    pub async fn update_droop_compensation(&mut self) -> Result<u16, Error> {
+        let Some(compensation) = self.droop_compensation else {
+            return Ok(1000);
+        };
+
+        let supply = self.read_measurement(Channel::Supply).await?;
+        self.droop_scale_permille = compensation.scale_permille(supply.raw);
+        Ok(self.droop_scale_permille)
+    }
+
+    /// The current scale factor of DRO override values in ‰.
+    pub fn droop_scale_permille(&self) -> u16 {
+        self.droop_scale_permille
+    }
+
+    /// Enable thermal derating, `None` disables it and restores the full ceiling.
+    pub fn set_thermal_derating(&mut self, derating: Option<ThermalDerating>) {
+        self.thermal_derating = derating;
+        self.derating_percent = 100;
+    }
+
+    /// The derating set with `set_thermal_derating()`.
+    pub fn thermal_derating(&self) -> Option<&ThermalDerating> {
+        self.thermal_derating.as_ref()
+    }
+
+    /// Check the thermal state and step the override ceiling down or up.
+    ///
+    /// Call this periodically while driving. The overtemperature warning is latched,
+    /// so E_WARNING is cleared after it was seen, to find out at the next update
+    /// whether the device is still hot. This also clears other latched warnings.
+    /// Returns the ceiling in percent of `max_level()`.
+    pub async fn update_thermal_derating(&mut self) -> Result<u8, Error> {
+        let Some(derating) = self.thermal_derating else {
+            return Ok(100);
+        };
+
+        // IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG, IRQ_STATUS1
+        let mut irq = [0u8; 4];
+        self.read_registers(Register::IRQ_EVENT1, &mut irq).await?;
+        let irq_event1 = IRQ_EVENT1::from(irq[0]);
+
+        let thermal = if IRQ_STATUS1::from(irq[3]).STA_OVERTEMP_CRIT() {
+            ThermalState::Critical
+        } else if irq_event1.E_WARNING() && IRQ_EVENT_WARNING_DIAG::from(irq[1]).E_OVERTEMP_WARN() {
+            self.clear_events(IRQ_EVENT1::new().with_E_WARNING(true)).await?;
+            ThermalState::Warning
+        } else {
+            ThermalState::Normal
+        };
+
+        self.derating_percent = derating.next_percent(self.derating_percent, thermal);
+        Ok(self.derating_percent)
+    }
+
+    /// Set the per-unit strength calibration in ‰ (1..=1000) of the drive reference.
+    ///
+    /// Applies to all playback on top of `set_master_gain()` and `set_global_strength()`.
+    /// Can be set before `configure()`, is kept when the device is reconfigured and
+    /// is saved by `save_profile()`.
+    pub async fn set_unit_calibration(&mut self, scale_permille: u16) -> Result<(), Error> {
+        if !(1..=1000).contains(&scale_permille) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.unit_scale_permille = scale_permille;
+        match (self.actuator_config, self.device_config) {
+            (Some(actuator_config), Some(device_config)) => {
+                self.write_drive_reference(&actuator_config, &device_config).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The per-unit strength calibration in ‰.
+    pub fn unit_calibration(&self) -> u16 {
+        self.unit_scale_permille
+    }
+
+    /// Calibrate the unit from a strength measured at the current settings.
+    ///
+    /// `measured` and `target` are in any unit proportional to the drive level, e.g.
+    /// peak acceleration in mg of the same test effect. Measure with the master gain
+    /// and global strength the target was defined at. Returns the new factor in ‰.
+    pub async fn calibrate_unit_strength(&mut self, measured: u32, target: u32) -> Result<u16, Error> {
+        let scale_permille = unit_scale_permille(self.unit_scale_permille, measured, target)?;
+        self.set_unit_calibration(scale_permille).await?;
+        Ok(scale_permille)
+    }
+
+    /// The override ceiling after thermal derating, see `set_max_level()`.
+    pub fn effective_max_level(&self) -> u8 {
+        (self.max_level as u16 * self.derating_percent as u16 / 100) as u8
+    }
+
+    /// The level driven for the override value `level`: scaled by the droop
+    /// compensation, then clamped to `effective_max_level()`.
+    pub(crate) fn compensated_level(&self, level: i8) -> i8 {
+        let max_level = self.effective_max_level() as i32;
+        scale_level(level, self.droop_scale_permille).clamp(-max_level, max_level) as i8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_droop_scale() {
+        let compensation = DroopCompensation {
+            reference_raw: 4000,
+            max_boost_percent: 20,
+        };
+        assert_eq!(compensation.scale_permille(4000), 1000);
+        assert_eq!(compensation.scale_permille(4400), 1000);
+        assert_eq!(compensation.scale_permille(3636), 1100);
+        assert_eq!(compensation.scale_permille(2000), 1200);
+        assert_eq!(compensation.scale_permille(0), 1000);
+
+        assert_eq!(scale_level(100, 1100), 110);
+        assert_eq!(scale_level(-100, 1100), -110);
+        assert_eq!(scale_level(127, 1200), 152);
+    }
+
+    #[test]
+    fn test_unit_scale() {
+        assert_eq!(unit_scale_permille(1000, 1200, 1000).unwrap(), 833);
+        // Measured at 833 ‰, still 2 % too strong
+        assert_eq!(unit_scale_permille(833, 1020, 1000).unwrap(), 816);
+        assert_eq!(unit_scale_permille(1000, 800, 1000).unwrap(), 1000);
+        assert!(matches!(unit_scale_permille(1000, 0, 1000), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_thermal_derating_steps() {
+        let derating = ThermalDerating {
+            step_percent: 20,
+            min_percent: 50,
+            recovery_percent: 10,
+        };
+        assert_eq!(derating.next_percent(100, ThermalState::Warning), 80);
+        assert_eq!(derating.next_percent(60, ThermalState::Warning), 50);
+        assert_eq!(derating.next_percent(50, ThermalState::Normal), 60);
+        assert_eq!(derating.next_percent(95, ThermalState::Normal), 100);
+        assert_eq!(derating.next_percent(100, ThermalState::Critical), 50);
+    }
+}
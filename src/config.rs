@@ -13,8 +13,19 @@ pub struct ActuatorConfig
 #[allow(nonstandard_style)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActuatorType {
-    LRA = 0,
-    ERM = 1,
+    LRA,
+    /// Cylinder (bar) ERM, BEMF sensing is available.
+    ERM_BAR,
+    /// Flat coin ERM, the commutation prevents BEMF sensing, so Active Acceleration
+    /// and Rapid Stop are not available (see 5.7.18 Coin ERM Operation).
+    ERM_COIN,
+}
+
+impl ActuatorType {
+    /// Whether this is one of the ERM types (ACTUATOR_TYPE = 1).
+    pub fn is_erm(&self) -> bool {
+        *self != ActuatorType::LRA
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -23,6 +34,42 @@ pub struct DeviceConfig {
     pub driving_mode: DrivingMode,
     pub acceleration: bool,
     pub rapid_stop: bool,
+    pub idle_state: IdleState,
+    /// I2C interface timeout (CIF_I2C1.I2C_TO_ENABLE, enabled after reset).
+    /// The interface resets itself if SCL stays idle for ~35 ms mid-transaction.
+    pub i2c_timeout: bool,
+    /// Amplitude PID (TOP_CFG1.AMP_PID_EN), reduces the drive current to compensate
+    /// large BEMF amplitudes of bar ERMs. Only valid with `ActuatorType::ERM_BAR`.
+    /// The loop coefficients are factory tuned and not documented, so they are not exposed.
+    pub amplitude_pid: bool,
+    /// Manual overdrive and braking for coin ERMs in DRO mode, see `DA728x::drive_erm()`.
+    /// Only valid with `ActuatorType::ERM_COIN`.
+    pub erm_overdrive: Option<ErmOverdrive>,
+    /// Minimize the time from a trigger to perceptible motion: the device is kept in
+    /// STANDBY between effects regardless of `idle_state` (~0.15 ms instead of ~0.75 ms
+    /// until the output switches), and the operation mode is enabled right away, so the
+    /// first trigger or override value is a single register write. `configure()`
+    /// enables it, in RTWM and ETWM mode the upload of a locked waveform memory does.
+    /// Active Acceleration (`acceleration`) further shortens the rise time of LRAs.
+    pub low_latency: bool,
+}
+
+/// Overdrive on start and active braking on stop for coin ERMs.
+///
+/// Coin ERMs have no usable BEMF, so Active Acceleration and Rapid Stop are not available
+/// and the transitions have to be sped up manually (5.7.18 Coin ERM Operation).
+/// The full drive level is ACTUATOR_ABSMAX, braking reverses the drive polarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErmOverdrive {
+    /// Time the full drive level is applied when the level increases (0..=100 ms).
+    pub overdrive_ms: u8,
+    /// Time the reversed full drive level is applied when stopping (0..=100 ms).
+    pub brake_ms: u8,
+}
+
+impl ErmOverdrive {
+    /// Maximum overdrive / braking time in ms.
+    pub const MAX_MS: u8 = 100;
 }
 
 #[allow(nonstandard_style)]
@@ -30,11 +77,41 @@ pub struct DeviceConfig {
 pub enum OperationMode {
     INACTIVE = 0,
     DRO_MODE = 1,
+    /// Drive level from the GPI_0/PWM duty cycle. If the PWM signal is lost, the device
+    /// raises a PWM fault and stops driving, see `DA728x::handle_pwm_timeout()`.
     PWM_MODE = 2,
     RTWM_MODE = 3,
     ETWM_MODE = 4
 }
 
+impl OperationMode {
+    /// Decode the OPERATION_MODE field of TOP_CTL1, if it is a known mode.
+    pub fn from_register(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(OperationMode::INACTIVE),
+            1 => Some(OperationMode::DRO_MODE),
+            2 => Some(OperationMode::PWM_MODE),
+            3 => Some(OperationMode::RTWM_MODE),
+            4 => Some(OperationMode::ETWM_MODE),
+            _ => None,
+        }
+    }
+}
+
+/// State the device automatically returns to when playback has finished or
+/// the operation mode is INACTIVE, according to 5.2.1 System States (STANDBY_EN).
+///
+/// Note: in DRO and PWM mode the output stage is additionally switched off
+/// automatically while the drive level stays at 0%.
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleState {
+    /// Lowest power consumption (typ. 0.36 uA), ~0.75 ms until output starts switching
+    IDLE,
+    /// Low latency-to-drive (~0.15 ms), typ. 0.8 mA
+    STANDBY,
+}
+
 /// According to 5.7 Advanced Operation
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(nonstandard_style)]
@@ -44,3 +121,239 @@ pub enum DrivingMode {
     CUSTOM_WAVEFORM
 }
 
+impl DrivingMode {
+    /// Supported drive frequencies in Hz.
+    ///
+    /// Frequency tracking only locks onto resonances of 50-300 Hz, while wideband
+    /// and custom waveform mode drive anywhere from 25 to 1023 Hz.
+    pub fn frequency_range(&self) -> core::ops::Range<u16> {
+        match self {
+            DrivingMode::FREQUENCY_TRACK => 50..300,
+            DrivingMode::WIDEBAND | DrivingMode::CUSTOM_WAVEFORM => 25..1024,
+        }
+    }
+}
+
+/// What `configure()` and `upload_waveform_memory()` do while the device is active,
+/// see `DA728x::set_busy_policy()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BusyPolicy {
+    /// Return `DeviceBusy`.
+    #[default]
+    Reject,
+    /// Stop playback and set the operation mode to INACTIVE first.
+    Stop,
+}
+
+/// How `DA728x::set_frequency_with()` writes the drive period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyUpdate {
+    /// Write FRQ_LRA_PER_H and FRQ_LRA_PER_L as two separate register writes.
+    Immediate,
+    /// Write both period registers in one auto-increment transaction, so no other
+    /// bus traffic can come between the two bytes.
+    Burst,
+}
+
+/// Events that are prevented from pulling the nIRQ pin low (IRQ_MASK1 / IRQ_MASK2).
+///
+/// nIRQ is an active-low open-drain output that stays asserted until all unmasked
+/// events have been cleared, so it can be used with level- or falling-edge-triggered
+/// MCU interrupts. Masked events are still latched and reported by `get_events()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IrqMask {
+    pub seq_continue: bool,
+    pub uvlo: bool,
+    pub seq_done: bool,
+    pub overtemp_crit: bool,
+    pub seq_fault: bool,
+    pub warning: bool,
+    pub actuator_fault: bool,
+    pub oc_fault: bool,
+    pub adc_saturation: bool,
+}
+
+impl IrqMask {
+    /// Every event asserts nIRQ (reset default).
+    pub const NONE: Self = Self {
+        seq_continue: false,
+        uvlo: false,
+        seq_done: false,
+        overtemp_crit: false,
+        seq_fault: false,
+        warning: false,
+        actuator_fault: false,
+        oc_fault: false,
+        adc_saturation: false,
+    };
+
+    /// Only faults assert nIRQ, notifications (sequence done / continue) and warnings are masked.
+    pub const FAULTS_ONLY: Self = Self {
+        seq_continue: true,
+        seq_done: true,
+        warning: true,
+        ..Self::NONE
+    };
+
+    /// Faults and warnings assert nIRQ, notifications (sequence done / continue) are masked.
+    pub const NO_NOTIFICATIONS: Self = Self {
+        seq_continue: true,
+        seq_done: true,
+        ..Self::NONE
+    };
+}
+
+/// Current regulation loop filter (5.7.9, TRIM3 / TRIM4).
+///
+/// Active Acceleration has no tuning registers of its own: it overdrives up to
+/// ACTUATOR_ABSMAX on level changes, and how sharply the output current follows
+/// that overdrive is set by this loop filter. The defaults cover most actuators.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopFilter {
+    /// LOOP_FILT_CAP_TRIM (0..=3), see `cap_trim_for_resistance()`.
+    pub cap_trim: u8,
+    /// LOOP_FILT_RES_TRIM (0..=3), depends on the series resistance and inductance (Table 16).
+    pub res_trim: u8,
+    /// LOOP_FILT_LOW_BW, set if the actuator inductance exceeds 1 mH.
+    pub low_bandwidth: bool,
+}
+
+impl LoopFilter {
+    /// Reset values.
+    pub const DEFAULT: Self = Self {
+        cap_trim: 3,
+        res_trim: 0,
+        low_bandwidth: false,
+    };
+
+    /// LOOP_FILT_CAP_TRIM for the actuator series resistance according to Table 15.
+    #[allow(non_snake_case)]
+    pub fn cap_trim_for_resistance(resistance_mOhm: u16) -> u8 {
+        match resistance_mOhm {
+            0..18_000 => 3,
+            18_000..28_000 => 2,
+            28_000..=41_000 => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for LoopFilter {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Frequency tracking limits (5.7.1 Frequency Tracking).
+///
+/// The tracked range itself is not configurable: the loop converges from up to 25 %
+/// offset to the programmed `frequency_Hz` and raises an actuator fault outside of
+/// 50 Hz to 300 Hz, unless `force_on` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyTracking {
+    /// FRQ_LOCKED_LIM (0..=63), the loop counts as locked once the error is below
+    /// `locked_limit * 4`. Only relevant with `auto_adjust`.
+    pub locked_limit: u8,
+    /// FRQ_TRACK_BEMF_LIM (0..=15), tracking pauses while the BEMF is below this threshold.
+    /// Should always be lower than the Rapid Stop threshold (RAPID_STOP_LIM).
+    pub bemf_limit: u8,
+    /// FREQ_TRACKING_AUTO_ADJ, scale down the proportional coefficient once locked.
+    pub auto_adjust: bool,
+    /// FREQ_TRACKING_FORCE_ON, keep tracking even if the error exceeds 25 % of the
+    /// programmed frequency and skip the 50 Hz to 300 Hz fault checks.
+    pub force_on: bool,
+}
+
+impl FrequencyTracking {
+    /// Reset values.
+    pub const DEFAULT: Self = Self {
+        locked_limit: 0x20,
+        bemf_limit: 0x3,
+        auto_adjust: true,
+        force_on: false,
+    };
+}
+
+impl Default for FrequencyTracking {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Braking settings that make short effects stop dead instead of ringing out.
+///
+/// With Rapid Stop the drive is reversed against the BEMF when the level drops to
+/// zero, until the BEMF falls below `rapid_stop_limit` (5.7.2 Rapid Stop). Active
+/// Acceleration (`DeviceConfig::acceleration`) additionally brakes on every level
+/// decrease, and coin ERMs are braked manually with `ErmOverdrive`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrakingConfig {
+    /// RAPID_STOP_EN, requires frequency track mode and BEMF sensing (not with coin ERMs).
+    pub rapid_stop: bool,
+    /// RAPID_STOP_LIM (0..=7), decrease it if the actuator doesn't stop completely,
+    /// increase it if it overshoots. Must stay above `FrequencyTracking::bemf_limit`.
+    pub rapid_stop_limit: u8,
+    /// FULL_BRAKE_THR (0..=15), PWM duty cycles this close to zero drive brake fully.
+    pub full_brake_threshold: u8,
+}
+
+impl BrakingConfig {
+    /// Reset values, no Rapid Stop.
+    pub const DEFAULT: Self = Self {
+        rapid_stop: false,
+        rapid_stop_limit: 4,
+        full_brake_threshold: 1,
+    };
+
+    /// Rapid Stop with the default threshold, for crisp clicks.
+    pub const CRISP: Self = Self {
+        rapid_stop: true,
+        ..Self::DEFAULT
+    };
+}
+
+impl Default for BrakingConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Drive phase delay relative to the BEMF (FRQ_PHASE_H / FRQ_PHASE_L, 5.7.5).
+///
+/// The datasheet only specifies the two presets, all other settings are reserved.
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseDelay {
+    /// DELAY_H = 0x25, DELAY_SHIFT_L = 5, DELAY_FREEZE = 0 (reset value)
+    FREQUENCY_TRACK,
+    /// DELAY_H = 0, DELAY_SHIFT_L = 0, DELAY_FREEZE = 1
+    WIDEBAND,
+    /// Raw values, use at your own risk.
+    CUSTOM {
+        delay_h: u8,
+        /// 0..=7
+        delay_shift_l: u8,
+        /// Only set with frequency tracking disabled.
+        freeze: bool,
+    },
+}
+
+impl PhaseDelay {
+    /// Get the raw (DELAY_H, DELAY_SHIFT_L, DELAY_FREEZE) values.
+    pub fn raw(&self) -> (u8, u8, bool) {
+        match *self {
+            PhaseDelay::FREQUENCY_TRACK => (0x25, 5, false),
+            PhaseDelay::WIDEBAND => (0, 0, true),
+            PhaseDelay::CUSTOM { delay_h, delay_shift_l, freeze } => (delay_h, delay_shift_l, freeze),
+        }
+    }
+
+    /// Decode raw values, returning a preset if they match one.
+    pub fn from_raw(delay_h: u8, delay_shift_l: u8, freeze: bool) -> Self {
+        match (delay_h, delay_shift_l, freeze) {
+            (0x25, 5, false) => PhaseDelay::FREQUENCY_TRACK,
+            (0, 0, true) => PhaseDelay::WIDEBAND,
+            _ => PhaseDelay::CUSTOM { delay_h, delay_shift_l, freeze },
+        }
+    }
+}
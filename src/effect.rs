@@ -0,0 +1,308 @@
+//! Haptic effects and chaining them.
+//!
+//! `HapticEffect` is implemented by the built-in effects (sequences, patterns,
+//! melodies, textures and rumble) and can be implemented by applications for
+//! their own effects, so all of them can be played and chained the same way.
+//!
+//! Jumping straight from the last level of one effect to the first level of the
+//! next can produce an audible click. A `Crossfade` ramps the override value
+//! between the two in small steps instead.
+//!
+//! RTWM/ETWM sequences are played by the device one at a time and cannot overlap,
+//! so crossfading only applies to DRO playback.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::melody::Note;
+use crate::rumble::{Rumble, RumbleConfig};
+use crate::texture::Texture;
+use crate::DA728x;
+
+/// An effect that can be played on a DA728x.
+///
+/// # Example
+///
+/// ```ignore
+/// struct DoubleClick;
+///
+/// impl HapticEffect for DoubleClick {
+///     async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+///         haptic.play_pattern(". .", 20, 127, delay).await
+///     }
+/// }
+///
+/// DoubleClick.then(PlayMelody(&melody)).play(&mut haptic, &mut delay).await?;
+/// ```
+#[allow(async_fn_in_trait)]
+pub trait HapticEffect {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error>;
+
+    /// Play `next` after this effect.
+    fn then<E: HapticEffect>(self, next: E) -> Then<Self, E>
+    where
+        Self: Sized,
+    {
+        Then { first: self, second: next }
+    }
+}
+
+impl<T: HapticEffect + ?Sized> HapticEffect for &T {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        (**self).play(haptic, delay).await
+    }
+}
+
+/// Two effects played one after the other, see `HapticEffect::then()`.
+#[derive(Debug, Clone, Copy)]
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: HapticEffect, B: HapticEffect> HapticEffect for Then<A, B> {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        self.first.play(haptic, delay).await?;
+        self.second.play(haptic, delay).await
+    }
+}
+
+/// Select and start a sequence from the waveform memory (RTWM mode).
+///
+/// The sequence plays in the background, this does not wait for it to finish.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaySequence {
+    pub sequence_id: u8,
+    pub loops: u8,
+}
+
+impl HapticEffect for PlaySequence {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, _delay: &mut D) -> Result<(), Error> {
+        haptic.play_sequence(self.sequence_id, self.loops).await
+    }
+}
+
+/// A dot/dash pattern, see `DA728x::play_pattern()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayPattern<'a> {
+    pub pattern: &'a str,
+    pub unit_ms: u32,
+    pub level: i8,
+}
+
+impl HapticEffect for PlayPattern<'_> {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.play_pattern(self.pattern, self.unit_ms, self.level, delay).await
+    }
+}
+
+/// Text as Morse code, see `DA728x::play_morse()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayMorse<'a> {
+    pub text: &'a str,
+    pub unit_ms: u32,
+    pub level: i8,
+}
+
+impl HapticEffect for PlayMorse<'_> {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.play_morse(self.text, self.unit_ms, self.level, delay).await
+    }
+}
+
+/// A melody, see `DA728x::play_melody()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayMelody<'a>(pub &'a [(Note, u32)]);
+
+impl HapticEffect for PlayMelody<'_> {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.play_melody(self.0, delay).await
+    }
+}
+
+/// A texture for a fixed duration, see `DA728x::play_texture()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayTexture {
+    pub texture: Texture,
+    pub duration_ms: u32,
+}
+
+impl HapticEffect for PlayTexture {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.play_texture(&self.texture, self.duration_ms, delay).await
+    }
+}
+
+/// A rumble for a fixed duration, see `DA728x::rumble()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayRumble {
+    pub rumble: Rumble,
+    pub config: RumbleConfig,
+    pub duration_ms: u32,
+}
+
+impl HapticEffect for PlayRumble {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        haptic.rumble(self.rumble, &self.config, self.duration_ms, delay).await
+    }
+}
+
+/// A fixed override value for a fixed duration, ramped in and out with a `Crossfade`.
+///
+/// The ramps count towards the on-time for the protection profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayLevel {
+    pub level: i8,
+    pub duration_ms: u32,
+    pub crossfade: Crossfade,
+}
+
+impl HapticEffect for PlayLevel {
+    async fn play<I2C: I2c, D: DelayNs>(&self, haptic: &mut DA728x<I2C>, delay: &mut D) -> Result<(), Error> {
+        let on_ms = self.duration_ms.saturating_add(self.crossfade.fade_ms.saturating_mul(2));
+        haptic.check_protection(self.level, on_ms)?;
+        haptic.crossfade_to(self.level, &self.crossfade, delay).await?;
+        delay.delay_ms(self.duration_ms).await;
+        haptic.crossfade_to(0, &self.crossfade, delay).await?;
+        haptic.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+/// Linear ramp of the override value between two effects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossfade {
+    /// Total duration of the ramp.
+    pub fade_ms: u32,
+    /// Interval between override value updates.
+    pub step_ms: u32,
+}
+
+impl Crossfade {
+    pub const DEFAULT: Crossfade = Crossfade {
+        fade_ms: 20,
+        step_ms: 2,
+    };
+
+    /// Number of intermediate updates.
+    pub fn steps(&self) -> u32 {
+        self.fade_ms.checked_div(self.step_ms).unwrap_or(0)
+    }
+
+    /// Level after `step` of `steps()` updates, ending exactly on `to`.
+    pub fn level_at(&self, from: i8, to: i8, step: u32) -> i8 {
+        let steps = self.steps();
+        if step >= steps {
+            return to;
+        }
+        let delta = (to as i32 - from as i32) * step as i32 / steps as i32;
+        (from as i32 + delta) as i8
+    }
+}
+
+impl Default for Crossfade {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Ramp from the current override value to `level`.
+    ///
+    /// Call this between two DRO effects with the first level of the next effect,
+    /// which then starts without a jump. The stronger of both levels is checked
+    /// against the protection profile for the duration of the fade.
+    pub async fn crossfade_to<D: DelayNs>(
+        &mut self,
+        level: i8,
+        crossfade: &Crossfade,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let from = self.override_value;
+        let strongest = if from.unsigned_abs() > level.unsigned_abs() { from } else { level };
+        self.check_protection(strongest, crossfade.fade_ms)?;
+
+        for step in 1..crossfade.steps() {
+            self.set_override_value(crossfade.level_at(from, level, step)).await?;
+            delay.delay_ms(crossfade.step_ms).await;
+        }
+
+        self.set_override_value(level).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossfade_levels() {
+        let crossfade = Crossfade { fade_ms: 20, step_ms: 5 };
+        assert_eq!(crossfade.steps(), 4);
+        assert_eq!(crossfade.level_at(100, 0, 0), 100);
+        assert_eq!(crossfade.level_at(100, 0, 1), 75);
+        assert_eq!(crossfade.level_at(100, 0, 3), 25);
+        assert_eq!(crossfade.level_at(100, 0, 4), 0);
+        assert_eq!(crossfade.level_at(-20, 20, 2), 0);
+    }
+
+    #[test]
+    fn test_crossfade_checks_protection() {
+        use crate::config::{DrivingMode, OperationMode};
+        use crate::mock::{block_on, device_config, lra, MockDevice, NoDelay};
+        use crate::protection::{AmplitudeLimit, ProtectionProfile};
+        use crate::Variant;
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let mut profile = ProtectionProfile::NONE;
+            profile.amplitude_limits[0] = Some(AmplitudeLimit { min_duration_ms: 50, max_level: 64 });
+            haptic.set_protection_profile(Some(profile));
+
+            let crossfade = Crossfade { fade_ms: 100, step_ms: 10 };
+            assert!(matches!(
+                haptic.crossfade_to(-127, &crossfade, &mut NoDelay).await,
+                Err(Error::ProtectionLimit)
+            ));
+            haptic.crossfade_to(-64, &crossfade, &mut NoDelay).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_play_level_long_duration() {
+        use crate::config::{DrivingMode, OperationMode};
+        use crate::mock::{block_on, device_config, lra, MockDevice, NoDelay};
+        use crate::protection::ProtectionProfile;
+        use crate::Variant;
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let mut profile = ProtectionProfile::NONE;
+            profile.max_on_ms = Some(u32::MAX - 1);
+            haptic.set_protection_profile(Some(profile));
+
+            let effect = PlayLevel { level: 64, duration_ms: u32::MAX - 10, crossfade: Crossfade::DEFAULT };
+            assert!(matches!(effect.play(&mut haptic, &mut NoDelay).await, Err(Error::ProtectionLimit)));
+        });
+    }
+
+    #[test]
+    fn test_crossfade_disabled() {
+        let crossfade = Crossfade { fade_ms: 20, step_ms: 0 };
+        assert_eq!(crossfade.steps(), 0);
+        assert_eq!(crossfade.level_at(100, 10, 0), 10);
+    }
+}
@@ -2,6 +2,31 @@ use core::fmt::{Debug, Display, Formatter, Result};
 use embedded_hal::digital::ErrorKind as DigitalErrorKind;
 use embedded_hal::i2c::ErrorKind as I2cErrorKind;
 
+/// The element of the waveform memory that did not fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveformElement {
+    /// Snippet with the given ID.
+    Snippet(u8),
+    /// Sequence with the given ID.
+    Sequence(u8),
+    /// PWL point at the given index within its snippet.
+    Point(u8),
+    /// Frame at the given index within its sequence.
+    Frame(u8),
+}
+
+impl Display for WaveformElement
+{
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            WaveformElement::Snippet(id) => write!(f, "snippet {}", id),
+            WaveformElement::Sequence(id) => write!(f, "sequence {}", id),
+            WaveformElement::Point(index) => write!(f, "point {}", index),
+            WaveformElement::Frame(index) => write!(f, "frame {}", index),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     I2c(I2cErrorKind),
@@ -10,10 +35,14 @@ pub enum Error {
     InvalidValue,
     NotConfigured,
     WrongMode,
+    DeviceBusy,
+    DutyCycleExceeded { retry_in_ms: u32 },
+    ProtectionLimit,
     // Waveform memory errors
-    WaveformMemoryFull,
-    TooManySnippets,
-    TooManySequences,
+    WaveformMemoryFull { element: WaveformElement, excess_bytes: usize },
+    TooManySnippets { element: WaveformElement },
+    TooManyPoints { element: WaveformElement },
+    TooManySequences { element: WaveformElement },
     InvalidSnippetId,
     InvalidTimebase,
     InvalidAmplitude,
@@ -21,6 +50,9 @@ pub enum Error {
     InvalidLoopCount,
     EmptySnippet,
     EmptySequence,
+    SequenceTooLong,
+    // Register script errors
+    InvalidScript { line: usize },
 }
 
 impl Display for Error
@@ -33,9 +65,17 @@ impl Display for Error
             Error::InvalidValue => write!(f,  "Invalid value, most likely out of range."),
             Error::NotConfigured => write!(f, "Configuration has not beed set yet."),
             Error::WrongMode => write!(f, "Driver is not in the right mode to support this operation"),
-            Error::WaveformMemoryFull => write!(f, "Waveform memory exceeds 100 bytes"),
-            Error::TooManySnippets => write!(f, "Too many snippets (max 15)"),
-            Error::TooManySequences => write!(f, "Too many sequences (max 16)"),
+            Error::DeviceBusy => write!(f, "Device is active, stop playback first"),
+            Error::DutyCycleExceeded { retry_in_ms } => {
+                write!(f, "Duty-cycle budget exceeded, retry in {} ms", retry_in_ms)
+            }
+            Error::ProtectionLimit => write!(f, "Effect exceeds the actuator protection profile"),
+            Error::WaveformMemoryFull { element, excess_bytes } => {
+                write!(f, "Waveform memory full at {}, {} bytes over budget", element, excess_bytes)
+            }
+            Error::TooManySnippets { element } => write!(f, "Too many snippets, {} does not fit", element),
+            Error::TooManyPoints { element } => write!(f, "Too many points, {} does not fit", element),
+            Error::TooManySequences { element } => write!(f, "Too many sequences or frames, {} does not fit", element),
             Error::InvalidSnippetId => write!(f, "Invalid snippet ID"),
             Error::InvalidTimebase => write!(f, "Invalid timebase value"),
             Error::InvalidAmplitude => write!(f, "Invalid amplitude value"),
@@ -43,6 +83,8 @@ impl Display for Error
             Error::InvalidLoopCount => write!(f, "Invalid loop count"),
             Error::EmptySnippet => write!(f, "Snippet must contain at least one point"),
             Error::EmptySequence => write!(f, "Sequence must contain at least one frame"),
+            Error::SequenceTooLong => write!(f, "Sequence exceeds the given frame or duration limit"),
+            Error::InvalidScript { line } => write!(f, "Invalid register script entry on line {}", line),
         }
     }
 }
@@ -0,0 +1,534 @@
+//! Decoded system events.
+//!
+//! The raw IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG and IRQ_EVENT_SEQ_DIAG registers
+//! are combined into an [`Events`] set that yields typed [`HapticEvent`] values.
+//! An [`EventHandler`] can be passed to [`DA728x::process_events`] or
+//! [`DA728x::wait_for_event`] to centralize fault handling and notifications.
+
+use core::future::poll_fn;
+use core::pin::pin;
+use core::task::Poll;
+
+use embedded_hal::digital::Error as _;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::registers::{Register, IRQ_EVENT1, IRQ_EVENT_SEQ_DIAG, IRQ_EVENT_WARNING_DIAG, SEQ_CTL2};
+use crate::{DA728x, Health};
+
+/// Class of a warning reported through E_WARNING / IRQ_EVENT_WARNING_DIAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// Playback is limited because the supply is lower than the sequence target.
+    LimDrive,
+    /// Acceleration is limited because the supply is lower than the overdrive level.
+    LimDriveAcc,
+    /// MEM_DATA_SIGNED does not match ACCELERATION_EN.
+    MemType,
+    /// Chip temperature exceeded the 105 °C warning limit.
+    OvertempWarn,
+}
+
+impl WarningKind {
+    /// All warnings, in the order they are yielded by [`Events::warnings`].
+    pub const ALL: [WarningKind; 4] = [
+        WarningKind::LimDrive,
+        WarningKind::LimDriveAcc,
+        WarningKind::MemType,
+        WarningKind::OvertempWarn,
+    ];
+
+    /// Check the diagnostic bit of this warning.
+    pub fn is_set(&self, warning_diag: IRQ_EVENT_WARNING_DIAG) -> bool {
+        match self {
+            WarningKind::LimDrive => warning_diag.E_LIM_DRIVE(),
+            WarningKind::LimDriveAcc => warning_diag.E_LIM_DRIVE_ACC(),
+            WarningKind::MemType => warning_diag.E_MEM_TYPE(),
+            WarningKind::OvertempWarn => warning_diag.E_OVERTEMP_WARN(),
+        }
+    }
+
+    /// Decode all warnings set in IRQ_EVENT_WARNING_DIAG.
+    ///
+    /// The diagnostic bits are only meaningful while E_WARNING is latched, use
+    /// [`Events::warnings`] to check both.
+    pub fn decode(warning_diag: IRQ_EVENT_WARNING_DIAG) -> impl Iterator<Item = WarningKind> {
+        Self::ALL.into_iter().filter(move |kind| kind.is_set(warning_diag))
+    }
+}
+
+/// Class of a sequence fault reported through E_SEQ_FAULT / IRQ_EVENT_SEQ_DIAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqFaultKind {
+    /// The requested sequence ID (PS_SEQ_ID) does not exist.
+    SeqId,
+    /// The waveform memory is corrupted (empty, invalid snippet ID, invalid frame).
+    Mem,
+    /// The PWM input signal timed out.
+    Pwm,
+}
+
+/// Details of a latched sequence fault, see [`DA728x::get_seq_fault`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqFault {
+    /// The sequence that faulted (PS_SEQ_ID, which also reads back the GPI triggered sequence).
+    pub sequence_id: u8,
+    /// Loop count configured for the sequence (PS_SEQ_LOOP).
+    pub loops: u8,
+    /// Failure class. If several diagnostic bits are set, the first of ID, memory and PWM fault.
+    pub kind: SeqFaultKind,
+}
+
+impl SeqFault {
+    /// Decode a sequence fault from the latched events and SEQ_CTL2.
+    ///
+    /// Returns `None` if E_SEQ_FAULT is not set or no diagnostic bit identifies the class.
+    pub fn decode(events: &Events, seq_ctl2: SEQ_CTL2) -> Option<Self> {
+        let kind = [SeqFaultKind::SeqId, SeqFaultKind::Mem, SeqFaultKind::Pwm]
+            .into_iter()
+            .find(|kind| events.contains(HapticEvent::SeqFault(*kind)))?;
+
+        Some(Self {
+            sequence_id: seq_ctl2.PS_SEQ_ID(),
+            loops: seq_ctl2.PS_SEQ_LOOP(),
+            kind,
+        })
+    }
+}
+
+/// A single decoded system event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HapticEvent {
+    /// Over-current / short-circuit on OUTP or OUTN.
+    OcFault,
+    /// Actuator fault (impedance, BEMF amplitude or resonant frequency).
+    ActuatorFault,
+    /// Sequence fault.
+    SeqFault(SeqFaultKind),
+    /// System warning.
+    Warning(WarningKind),
+    /// Critical chip temperature (125 °C) exceeded.
+    OverTemp,
+    /// Supply dropped below the UVLO threshold.
+    Uvlo,
+    /// Waveform memory sequence playback completed.
+    SeqDone,
+    /// A new sequence started because SEQ_CONTINUE was set.
+    SeqContinue,
+    /// The device lost its configuration and the driver re-applied it, see
+    /// `DA728x::set_auto_reconfigure()`. Reported by `process_events()` only.
+    Reconfigured,
+}
+
+impl HapticEvent {
+    /// Whether this event is a fault (the device returns to IDLE until it is cleared).
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self,
+            HapticEvent::OcFault
+                | HapticEvent::ActuatorFault
+                | HapticEvent::SeqFault(_)
+                | HapticEvent::OverTemp
+                | HapticEvent::Uvlo
+        )
+    }
+}
+
+/// All events in the order they are yielded by [`Events::iter`] (most severe first).
+const ALL_EVENTS: [HapticEvent; 13] = [
+    HapticEvent::OcFault,
+    HapticEvent::ActuatorFault,
+    HapticEvent::OverTemp,
+    HapticEvent::Uvlo,
+    HapticEvent::SeqFault(SeqFaultKind::SeqId),
+    HapticEvent::SeqFault(SeqFaultKind::Mem),
+    HapticEvent::SeqFault(SeqFaultKind::Pwm),
+    HapticEvent::Warning(WarningKind::LimDrive),
+    HapticEvent::Warning(WarningKind::LimDriveAcc),
+    HapticEvent::Warning(WarningKind::MemType),
+    HapticEvent::Warning(WarningKind::OvertempWarn),
+    HapticEvent::SeqDone,
+    HapticEvent::SeqContinue,
+];
+
+/// A decoded set of latched system events.
+///
+/// # Example
+///
+/// ```
+/// use da728x::events::{Events, HapticEvent};
+/// use da728x::registers::{IRQ_EVENT1, IRQ_EVENT_SEQ_DIAG, IRQ_EVENT_WARNING_DIAG};
+///
+/// let events = Events::new(
+///     IRQ_EVENT1::new().with_E_SEQ_DONE(true),
+///     IRQ_EVENT_WARNING_DIAG::new(),
+///     IRQ_EVENT_SEQ_DIAG::new(),
+/// );
+///
+/// for event in events.iter() {
+///     match event {
+///         HapticEvent::SeqDone => { /* start the next effect */ }
+///         e if e.is_fault() => { /* recover */ }
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Events {
+    irq_event1: IRQ_EVENT1,
+    warning_diag: IRQ_EVENT_WARNING_DIAG,
+    seq_diag: IRQ_EVENT_SEQ_DIAG,
+}
+
+impl Events {
+    /// Combine the raw event registers.
+    pub fn new(
+        irq_event1: IRQ_EVENT1,
+        warning_diag: IRQ_EVENT_WARNING_DIAG,
+        seq_diag: IRQ_EVENT_SEQ_DIAG,
+    ) -> Self {
+        Self {
+            irq_event1,
+            warning_diag,
+            seq_diag,
+        }
+    }
+
+    /// Get the raw event registers.
+    pub fn raw(&self) -> (IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG) {
+        (self.irq_event1, self.warning_diag, self.seq_diag)
+    }
+
+    /// Check if a specific event is present.
+    pub fn contains(&self, event: HapticEvent) -> bool {
+        let e = self.irq_event1;
+        match event {
+            HapticEvent::OcFault => e.E_OC_FAULT(),
+            HapticEvent::ActuatorFault => e.E_ACTUATOR_FAULT(),
+            HapticEvent::OverTemp => e.E_OVERTEMP_CRIT(),
+            HapticEvent::Uvlo => e.E_UVLO(),
+            HapticEvent::SeqDone => e.E_SEQ_DONE(),
+            HapticEvent::SeqContinue => e.E_SEQ_CONTINUE(),
+            HapticEvent::SeqFault(kind) => {
+                e.E_SEQ_FAULT()
+                    && match kind {
+                        SeqFaultKind::SeqId => self.seq_diag.E_SEQ_ID_FAULT(),
+                        SeqFaultKind::Mem => self.seq_diag.E_MEM_FAULT(),
+                        SeqFaultKind::Pwm => self.seq_diag.E_PWM_FAULT(),
+                    }
+            }
+            HapticEvent::Warning(kind) => e.E_WARNING() && kind.is_set(self.warning_diag),
+            HapticEvent::Reconfigured => false,
+        }
+    }
+
+    /// Check if no event is latched.
+    pub fn is_empty(&self) -> bool {
+        u8::from(self.irq_event1) == 0
+    }
+
+    /// Check if any fault is latched.
+    pub fn has_fault(&self) -> bool {
+        self.iter().any(|e| e.is_fault())
+    }
+
+    /// Iterate over the latched warnings.
+    pub fn warnings(&self) -> impl Iterator<Item = WarningKind> {
+        let warning_diag = if self.irq_event1.E_WARNING() {
+            self.warning_diag
+        } else {
+            IRQ_EVENT_WARNING_DIAG::new()
+        };
+        WarningKind::decode(warning_diag)
+    }
+
+    /// Iterate over the latched events, most severe first.
+    pub fn iter(&self) -> EventsIter {
+        EventsIter {
+            events: *self,
+            index: 0,
+        }
+    }
+}
+
+impl From<(IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG)> for Events {
+    fn from(raw: (IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG)) -> Self {
+        Self::new(raw.0, raw.1, raw.2)
+    }
+}
+
+impl IntoIterator for &Events {
+    type Item = HapticEvent;
+    type IntoIter = EventsIter;
+
+    fn into_iter(self) -> EventsIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the events in an [`Events`] set.
+#[derive(Debug, Clone)]
+pub struct EventsIter {
+    events: Events,
+    index: usize,
+}
+
+impl Iterator for EventsIter {
+    type Item = HapticEvent;
+
+    fn next(&mut self) -> Option<HapticEvent> {
+        while self.index < ALL_EVENTS.len() {
+            let event = ALL_EVENTS[self.index];
+            self.index += 1;
+            if self.events.contains(event) {
+                return Some(event);
+            }
+        }
+        None
+    }
+}
+
+/// Receives decoded events from [`DA728x::process_events`] and [`DA728x::wait_for_event`].
+///
+/// Implemented for any `FnMut(HapticEvent)` closure.
+pub trait EventHandler {
+    /// Called once for every latched event, most severe first.
+    fn on_event(&mut self, event: HapticEvent);
+}
+
+impl<F> EventHandler for F
+where
+    F: FnMut(HapticEvent),
+{
+    fn on_event(&mut self, event: HapticEvent) {
+        self(event)
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Read and clear all latched events, and pass each of them to `handler`.
+    ///
+    /// The decoded events are returned as well, e.g. to check `has_fault()`.
+    /// A sequence started with `play_sequence_forever()` is re-triggered on E_SEQ_DONE,
+    /// and SEQ_CONTINUE is cleared on E_SEQ_CONTINUE, so a sequence chained with
+    /// `continue_sequence()` plays only once.
+    /// With `set_auto_reconfigure()`, a lost configuration is re-applied on E_UVLO.
+    pub async fn process_events<H: EventHandler>(&mut self, handler: &mut H) -> Result<Events, Error> {
+        let events = self.get_decoded_events().await?;
+
+        for event in events.iter() {
+            handler.on_event(event);
+        }
+
+        if self.auto_reconfigure
+            && events.contains(HapticEvent::Uvlo)
+            && self.health_check(true).await? == Health::Reconfigured
+        {
+            handler.on_event(HapticEvent::Reconfigured);
+        }
+
+        if self.sequence_continue && events.contains(HapticEvent::SeqContinue) {
+            self.set_sequence_continue(false).await?;
+        }
+
+        if let Some(sequence_id) = self.looping_sequence
+            && events.contains(HapticEvent::SeqDone)
+        {
+            self.play_sequence(sequence_id, 15).await?;
+        }
+
+        Ok(events)
+    }
+
+    /// Identify a latched sequence fault, without clearing it.
+    ///
+    /// Call this before the events are cleared, as clearing E_SEQ_FAULT also clears
+    /// IRQ_EVENT_SEQ_DIAG. Returns `None` if no sequence fault is latched.
+    pub async fn get_seq_fault(&mut self) -> Result<Option<SeqFault>, Error> {
+        let events = Events::from(self.peek_events().await?);
+        if !events.raw().0.E_SEQ_FAULT() {
+            return Ok(None);
+        }
+
+        let seq_ctl2 = SEQ_CTL2::from(self.read_register(Register::SEQ_CTL2).await?);
+        Ok(SeqFault::decode(&events, seq_ctl2))
+    }
+
+    /// Wait for the (active low) nIRQ pin to assert, then process the events with `handler`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// loop {
+    ///     haptic.wait_for_event(&mut nirq, &mut |event| match event {
+    ///         HapticEvent::SeqDone => info!("sequence finished"),
+    ///         e if e.is_fault() => warn!("fault: {:?}", e),
+    ///         _ => {}
+    ///     }).await?;
+    /// }
+    /// ```
+    pub async fn wait_for_event<P: Wait, H: EventHandler>(
+        &mut self,
+        irq: &mut P,
+        handler: &mut H,
+    ) -> Result<Events, Error> {
+        irq.wait_for_low().await.map_err(|e| Error::Gpio(e.kind()))?;
+        self.process_events(handler).await
+    }
+
+    /// Like `wait_for_event()`, but gives up after `timeout_ms`.
+    ///
+    /// Returns `Ok(None)` if nIRQ did not assert in time, e.g. because it is not wired
+    /// up or the events are masked.
+    pub async fn wait_for_event_timeout<P: Wait, H: EventHandler, D: DelayNs>(
+        &mut self,
+        irq: &mut P,
+        handler: &mut H,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<Option<Events>, Error> {
+        let asserted = {
+            let mut irq_low = pin!(irq.wait_for_low());
+            let mut timeout = pin!(delay.delay_ms(timeout_ms));
+
+            poll_fn(|cx| {
+                if let Poll::Ready(result) = irq_low.as_mut().poll(cx) {
+                    return Poll::Ready(Some(result));
+                }
+                if timeout.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+                Poll::Pending
+            })
+            .await
+        };
+
+        match asserted {
+            Some(result) => {
+                result.map_err(|e| Error::Gpio(e.kind()))?;
+                self.process_events(handler).await.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_empty() {
+        let events = Events::new(
+            IRQ_EVENT1::new(),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new(),
+        );
+        assert!(events.is_empty());
+        assert!(!events.has_fault());
+        assert_eq!(events.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_events_order_and_decode() {
+        let events = Events::new(
+            IRQ_EVENT1::new()
+                .with_E_SEQ_DONE(true)
+                .with_E_WARNING(true)
+                .with_E_OC_FAULT(true),
+            IRQ_EVENT_WARNING_DIAG::new().with_E_LIM_DRIVE(true),
+            IRQ_EVENT_SEQ_DIAG::new(),
+        );
+
+        let mut iter = events.iter();
+        assert_eq!(iter.next(), Some(HapticEvent::OcFault));
+        assert_eq!(iter.next(), Some(HapticEvent::Warning(WarningKind::LimDrive)));
+        assert_eq!(iter.next(), Some(HapticEvent::SeqDone));
+        assert_eq!(iter.next(), None);
+        assert!(events.has_fault());
+    }
+
+    #[test]
+    fn test_diag_requires_summary_bit() {
+        // Diagnostic bits without E_SEQ_FAULT are not reported
+        let events = Events::new(
+            IRQ_EVENT1::new(),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new().with_E_MEM_FAULT(true),
+        );
+        assert!(!events.contains(HapticEvent::SeqFault(SeqFaultKind::Mem)));
+
+        let events = Events::new(
+            IRQ_EVENT1::new().with_E_SEQ_FAULT(true),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new().with_E_MEM_FAULT(true).with_E_SEQ_ID_FAULT(true),
+        );
+        let mut iter = events.iter();
+        assert_eq!(iter.next(), Some(HapticEvent::SeqFault(SeqFaultKind::SeqId)));
+        assert_eq!(iter.next(), Some(HapticEvent::SeqFault(SeqFaultKind::Mem)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_closure_handler() {
+        let events = Events::new(
+            IRQ_EVENT1::new().with_E_UVLO(true).with_E_SEQ_DONE(true),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new(),
+        );
+
+        let mut count = 0;
+        let mut handler = |_event: HapticEvent| count += 1;
+        for event in events.iter() {
+            handler.on_event(event);
+        }
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_seq_fault_decode() {
+        let seq_ctl2 = SEQ_CTL2::new().with_PS_SEQ_ID(5).with_PS_SEQ_LOOP(2);
+
+        let events = Events::new(
+            IRQ_EVENT1::new().with_E_SEQ_DONE(true),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new(),
+        );
+        assert_eq!(SeqFault::decode(&events, seq_ctl2), None);
+
+        let events = Events::new(
+            IRQ_EVENT1::new().with_E_SEQ_FAULT(true),
+            IRQ_EVENT_WARNING_DIAG::new(),
+            IRQ_EVENT_SEQ_DIAG::new().with_E_MEM_FAULT(true).with_E_PWM_FAULT(true),
+        );
+        assert_eq!(
+            SeqFault::decode(&events, seq_ctl2),
+            Some(SeqFault {
+                sequence_id: 5,
+                loops: 2,
+                kind: SeqFaultKind::Mem,
+            })
+        );
+    }
+
+    #[test]
+    fn test_warnings_decode() {
+        let warning_diag = IRQ_EVENT_WARNING_DIAG::new().with_E_LIM_DRIVE(true).with_E_OVERTEMP_WARN(true);
+        let mut decoded = WarningKind::decode(warning_diag);
+        assert_eq!(decoded.next(), Some(WarningKind::LimDrive));
+        assert_eq!(decoded.next(), Some(WarningKind::OvertempWarn));
+        assert_eq!(decoded.next(), None);
+
+        // Diagnostic bits without E_WARNING are ignored
+        let events = Events::new(IRQ_EVENT1::new(), warning_diag, IRQ_EVENT_SEQ_DIAG::new());
+        assert_eq!(events.warnings().count(), 0);
+
+        let events = Events::new(IRQ_EVENT1::new().with_E_WARNING(true), warning_diag, IRQ_EVENT_SEQ_DIAG::new());
+        assert_eq!(events.warnings().count(), 2);
+    }
+}
@@ -0,0 +1,71 @@
+//! End-of-line test for manufacturing fixtures.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::registers::Register;
+use crate::self_test::SelfTestReport;
+use crate::{v2i_register, DA728x, Health, Variant};
+
+/// Result of [`DA728x::factory_test`].
+#[derive(Debug, Clone, Copy)]
+pub struct FactoryReport {
+    /// Variant found at the address by `probe()`, `None` if nothing responded.
+    pub variant: Option<Variant>,
+    /// CALIB_V2I holds the value derived from the actuator impedance and IMAX.
+    pub v2i_calibrated: bool,
+    /// Register check, drive burst, telemetry and events, `None` if an earlier step failed.
+    pub self_test: Option<SelfTestReport>,
+}
+
+impl FactoryReport {
+    /// Whether every step passed.
+    pub fn passed(&self, variant: Variant) -> bool {
+        self.variant == Some(variant)
+            && self.v2i_calibrated
+            && self.self_test.is_some_and(|report| report.passed())
+    }
+
+    /// The register check result, if it ran.
+    pub fn health(&self) -> Option<Health> {
+        self.self_test.map(|report| report.health)
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Run the end-of-line test in a single pass: probe, V2I calibration check, then
+    /// `self_test()` with a `pulse_ms` drive burst.
+    ///
+    /// Requires `configure()` to have been called. Steps are skipped once one fails,
+    /// so the report shows where a unit dropped out. Bus errors other than a missing
+    /// acknowledge are returned as errors, not as a failed report.
+    pub async fn factory_test<D: DelayNs>(&mut self, delay: &mut D, pulse_ms: u32) -> Result<FactoryReport, Error> {
+        let actuator_config = self.actuator_config.ok_or(Error::NotConfigured)?;
+        if self.device_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+
+        let mut report = FactoryReport {
+            variant: Self::probe(&mut self.i2c, self.address).await?,
+            v2i_calibrated: false,
+            self_test: None,
+        };
+        if report.variant != Some(self.variant) {
+            return Ok(report);
+        }
+
+        let mut calib_v2i = [0u8; 2];
+        self.read_registers(Register::CALIB_V2I_H, &mut calib_v2i).await?;
+        report.v2i_calibrated = u16::from_be_bytes(calib_v2i) == v2i_register(&actuator_config);
+        if !report.v2i_calibrated {
+            return Ok(report);
+        }
+
+        report.self_test = Some(self.self_test(delay, pulse_ms).await?);
+        Ok(report)
+    }
+}
@@ -0,0 +1,306 @@
+//! GPI configuration for ETWM mode (5.2.7 Edge-Triggered Waveform Memory Mode).
+//!
+//! Each GPI plays either a single sequence, or alternates between two consecutive
+//! sequences (odd edges play `SEQUENCE_ID`, even edges `SEQUENCE_ID + 1`), so at most
+//! six sequences can be reached through the three GPIs. The DA7281 only has GPI_0,
+//! see `Variant::gpi_count()`.
+
+use embedded_hal::digital::Error as _;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::{Error, WaveformElement};
+use crate::registers::{Register, GPI_CTL, SEQ_CTL2};
+use crate::{Variant, DA728x};
+
+/// One of the three GPI pins.
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gpi {
+    GPI_0,
+    GPI_1,
+    GPI_2,
+}
+
+impl Gpi {
+    /// All GPIs, in order.
+    pub const ALL: [Gpi; 3] = [Gpi::GPI_0, Gpi::GPI_1, Gpi::GPI_2];
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Whether `variant` has this pin.
+    pub fn is_available(&self, variant: Variant) -> bool {
+        self.index() < variant.gpi_count()
+    }
+
+    fn register(&self) -> Register {
+        match self {
+            Gpi::GPI_0 => Register::GPI_0_CTL,
+            Gpi::GPI_1 => Register::GPI_1_CTL,
+            Gpi::GPI_2 => Register::GPI_2_CTL,
+        }
+    }
+}
+
+/// Which GPI edge triggers an event.
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GpiPolarity {
+    RISING_EDGE = 0,
+    FALLING_EDGE = 1,
+    BOTH_EDGES = 2,
+}
+
+/// Sequence(s) played on a GPI event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GpiTrigger {
+    /// Every event plays this sequence (0..=15).
+    Single(u8),
+    /// Odd events play this sequence, even events the next one (0..=14).
+    Alternating(u8),
+}
+
+/// Configuration of a single GPI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpiConfig {
+    pub polarity: GpiPolarity,
+    pub trigger: GpiTrigger,
+}
+
+impl GpiConfig {
+    /// Distribute up to six sequences over the GPIs of `variant`.
+    ///
+    /// Consecutive sequence IDs (e.g. `[3, 4]`) share one GPI in multi-sequence mode,
+    /// all others get a GPI of their own. The result is indexed like [`Gpi::ALL`].
+    ///
+    /// # Errors
+    /// Returns `TooManySequences` if more GPIs would be needed than the variant has,
+    /// `InvalidValue` if a sequence ID is above 15 and `EmptySequence` if `sequences` is empty.
+    pub fn plan(variant: Variant, sequences: &[u8], polarity: GpiPolarity) -> Result<[Option<GpiConfig>; 3], Error> {
+        if sequences.is_empty() {
+            return Err(Error::EmptySequence);
+        }
+        if sequences.iter().any(|&id| id > 15) {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut plan = [None; 3];
+        let mut gpi = 0;
+        let mut i = 0;
+
+        while i < sequences.len() {
+            if gpi == variant.gpi_count() {
+                return Err(Error::TooManySequences {
+                    element: WaveformElement::Sequence(sequences[i]),
+                });
+            }
+
+            let id = sequences[i];
+            let trigger = if sequences.get(i + 1) == Some(&(id + 1)) {
+                i += 2;
+                GpiTrigger::Alternating(id)
+            } else {
+                i += 1;
+                GpiTrigger::Single(id)
+            };
+
+            plan[gpi] = Some(GpiConfig { polarity, trigger });
+            gpi += 1;
+        }
+
+        Ok(plan)
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Configure a GPI for ETWM mode.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` for an invalid sequence ID or a GPI the variant doesn't have.
+    pub async fn configure_gpi(&mut self, gpi: Gpi, config: GpiConfig) -> Result<(), Error> {
+        if !gpi.is_available(self.variant) {
+            return Err(Error::InvalidValue);
+        }
+
+        let (mode, sequence_id) = match config.trigger {
+            GpiTrigger::Single(id) if id <= 15 => (false, id),
+            GpiTrigger::Alternating(id) if id <= 14 => (true, id),
+            _ => return Err(Error::InvalidValue),
+        };
+
+        let gpi_ctl = GPI_CTL::new()
+            .with_POLARITY(config.polarity as u8)
+            .with_MODE(mode)
+            .with_SEQUENCE_ID(sequence_id);
+        self.write_register(gpi.register(), gpi_ctl.into()).await?;
+        self.gpi_even_event[gpi.index()] = false;
+
+        Ok(())
+    }
+
+    /// Play the sequence an edge on `gpi` would trigger, using only I2C writes.
+    ///
+    /// Useful to exercise an ETWM setup before the trigger wiring exists. The GPI
+    /// configuration is read back from the device; in multi-sequence mode the calls
+    /// alternate between `SEQUENCE_ID` and `SEQUENCE_ID + 1` like odd and even edges
+    /// do (tracked separately from real edges). PS_SEQ_LOOP is kept.
+    ///
+    /// Returns the sequence ID that was started, or `InvalidValue` for a GPI the
+    /// variant doesn't have.
+    pub async fn simulate_gpi_trigger(&mut self, gpi: Gpi) -> Result<u8, Error> {
+        if !gpi.is_available(self.variant) {
+            return Err(Error::InvalidValue);
+        }
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::ETWM_MODE || !self.enabled {
+            return Err(Error::WrongMode);
+        }
+
+        let gpi_ctl = GPI_CTL::from(self.read_register(gpi.register()).await?);
+        let even = self.gpi_even_event[gpi.index()];
+        let sequence_id = if gpi_ctl.MODE() && even {
+            (gpi_ctl.SEQUENCE_ID() + 1).min(15)
+        } else {
+            gpi_ctl.SEQUENCE_ID()
+        };
+
+        let seq_ctl2 = SEQ_CTL2::from(self.read_register(Register::SEQ_CTL2).await?)
+            .with_PS_SEQ_ID(sequence_id);
+        self.write_register(Register::SEQ_CTL2, seq_ctl2.into()).await?;
+        self.selected_sequence = Some((sequence_id, seq_ctl2.PS_SEQ_LOOP()));
+        self.start_sequence().await?;
+
+        if gpi_ctl.MODE() {
+            self.gpi_even_event[gpi.index()] = !even;
+        }
+
+        Ok(sequence_id)
+    }
+
+    /// Configure all GPIs from a plan created by [`GpiConfig::plan`].
+    ///
+    /// GPIs without a configuration are left unchanged. A plan made for another
+    /// variant fails with `InvalidValue` if it uses a GPI this variant doesn't have.
+    pub async fn configure_gpis(&mut self, plan: [Option<GpiConfig>; 3]) -> Result<(), Error> {
+        for (gpi, config) in Gpi::ALL.into_iter().zip(plan) {
+            if let Some(config) = config {
+                self.configure_gpi(gpi, config).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a sequence on every edge of a button connected to the MCU instead of a GPI.
+    ///
+    /// Edges within `debounce_ms` after a trigger are ignored. Runs until an I2C or
+    /// GPIO error occurs, so it is meant to be run in its own task.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// haptic.enable().await?;
+    /// haptic.play_on_button(&mut button, &mut Delay, GpiPolarity::FALLING_EDGE, 0, 0, 30).await?;
+    /// ```
+    pub async fn play_on_button<P: Wait, D: DelayNs>(
+        &mut self,
+        button: &mut P,
+        delay: &mut D,
+        edge: GpiPolarity,
+        sequence_id: u8,
+        loops: u8,
+        debounce_ms: u32,
+    ) -> Result<(), Error> {
+        self.select_sequence(sequence_id, loops).await?;
+
+        loop {
+            match edge {
+                GpiPolarity::RISING_EDGE => button.wait_for_rising_edge().await,
+                GpiPolarity::FALLING_EDGE => button.wait_for_falling_edge().await,
+                GpiPolarity::BOTH_EDGES => button.wait_for_any_edge().await,
+            }
+            .map_err(|e| Error::Gpio(e.kind()))?;
+
+            self.start_sequence().await?;
+            delay.delay_ms(debounce_ms).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_pairs_consecutive() {
+        let plan = GpiConfig::plan(Variant::DA7280, &[0, 1, 5, 7, 8], GpiPolarity::RISING_EDGE).unwrap();
+        assert_eq!(plan[0].unwrap().trigger, GpiTrigger::Alternating(0));
+        assert_eq!(plan[1].unwrap().trigger, GpiTrigger::Single(5));
+        assert_eq!(plan[2].unwrap().trigger, GpiTrigger::Alternating(7));
+    }
+
+    #[test]
+    fn test_plan_partial() {
+        let plan = GpiConfig::plan(Variant::DA7280, &[2], GpiPolarity::BOTH_EDGES).unwrap();
+        assert_eq!(plan[0].unwrap().trigger, GpiTrigger::Single(2));
+        assert_eq!(plan[0].unwrap().polarity, GpiPolarity::BOTH_EDGES);
+        assert!(plan[1].is_none());
+        assert!(plan[2].is_none());
+    }
+
+    #[test]
+    fn test_plan_too_many() {
+        let result = GpiConfig::plan(Variant::DA7280, &[0, 2, 4, 6], GpiPolarity::RISING_EDGE);
+        assert!(matches!(result, Err(Error::TooManySequences { .. })));
+
+        let result = GpiConfig::plan(Variant::DA7280, &[0, 1, 2, 3, 4, 5, 6], GpiPolarity::RISING_EDGE);
+        assert!(matches!(result, Err(Error::TooManySequences { .. })));
+    }
+
+    #[test]
+    fn test_plan_da7281_single_gpi() {
+        let plan = GpiConfig::plan(Variant::DA7281, &[3, 4], GpiPolarity::RISING_EDGE).unwrap();
+        assert_eq!(plan[0].unwrap().trigger, GpiTrigger::Alternating(3));
+        assert!(plan[1].is_none());
+
+        let result = GpiConfig::plan(Variant::DA7281, &[3, 5], GpiPolarity::RISING_EDGE);
+        assert!(matches!(result, Err(Error::TooManySequences { element: WaveformElement::Sequence(5) })));
+    }
+
+    #[test]
+    fn test_configure_gpi_da7281() {
+        use crate::mock::{block_on, MockDevice};
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7281);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7281).await.unwrap();
+            let config = GpiConfig {
+                polarity: GpiPolarity::RISING_EDGE,
+                trigger: GpiTrigger::Single(2),
+            };
+
+            haptic.configure_gpi(Gpi::GPI_0, config).await.unwrap();
+            assert!(matches!(haptic.configure_gpi(Gpi::GPI_1, config).await, Err(Error::InvalidValue)));
+            assert!(matches!(haptic.configure_gpi(Gpi::GPI_2, config).await, Err(Error::InvalidValue)));
+            assert!(matches!(haptic.simulate_gpi_trigger(Gpi::GPI_2).await, Err(Error::InvalidValue)));
+            assert_eq!(haptic.i2c.registers[Register::GPI_1_CTL as usize], 0);
+        });
+    }
+
+    #[test]
+    fn test_plan_invalid() {
+        assert!(matches!(GpiConfig::plan(Variant::DA7280, &[], GpiPolarity::RISING_EDGE), Err(Error::EmptySequence)));
+        assert!(matches!(GpiConfig::plan(Variant::DA7280, &[16], GpiPolarity::RISING_EDGE), Err(Error::InvalidValue)));
+    }
+}
@@ -3,21 +3,65 @@
 #![no_std]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "advanced")]
+pub mod advanced;
+pub mod array;
+#[cfg(feature = "alloc")]
+pub mod audio;
+pub mod audit;
+pub mod banks;
+pub mod budget;
+pub mod command;
+pub mod compensation;
 pub mod config;
+pub mod effect;
 pub mod errors;
+pub mod events;
+pub mod factory;
+pub mod gpi;
+#[cfg(feature = "embassy")]
+pub mod monitor;
+pub mod melody;
+#[cfg(test)]
+mod mock;
+pub mod pattern;
+pub mod profile;
+pub mod protection;
+pub mod pwm;
 pub mod registers;
+pub mod rumble;
+pub mod scheduler;
+pub mod script;
+pub mod self_test;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod telemetry;
+pub mod texture;
 pub mod waveform;
 
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
 use embedded_hal_async::i2c::Error as I2cError;
+use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
 
 #[cfg(feature = "debug")]
 use defmt::{debug, info};
 
-use config::{ActuatorConfig, DeviceConfig, DrivingMode, OperationMode};
-use errors::Error;
+use config::{ActuatorConfig, ActuatorType, BrakingConfig, BusyPolicy, DeviceConfig, DrivingMode, ErmOverdrive, FrequencyTracking, FrequencyUpdate, IdleState, IrqMask, LoopFilter, OperationMode, PhaseDelay};
+use command::Command;
+use compensation::{DroopCompensation, ThermalDerating};
+use errors::{Error, WaveformElement};
+use events::Events;
+use protection::ProtectionProfile;
 use registers::Register;
-use registers::{CHIP_REV, ACTUATOR1, ACTUATOR2, ACTUATOR3, TOP_CTL1, TOP_CFG1, CALIB_V2I_H, CALIB_V2I_L, FRQ_LRA_PER_H, FRQ_LRA_PER_L, IRQ_STATUS1, IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG, FRQ_PHASE_H, FRQ_PHASE_L};
+use registers::{CHIP_REV, CIF_I2C1, IRQ_MASK1, IRQ_MASK2, ACTUATOR1, ACTUATOR2, ACTUATOR3, TOP_CTL1, TOP_CFG1, CALIB_V2I_H, CALIB_V2I_L, FRQ_LRA_PER_H, FRQ_LRA_PER_L, IRQ_STATUS1, IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG, FRQ_PHASE_H, FRQ_PHASE_L};
 
 use crate::registers::MEM_CTL2;
 use crate::registers::SEQ_CTL1;
@@ -25,36 +69,153 @@ use crate::registers::SEQ_CTL2;
 use crate::registers::TOP_CFG2;
 use crate::registers::TOP_CFG4;
 use crate::registers::TOP_CTL2;
+use crate::registers::TOP_INT_CFG1;
+use crate::registers::TOP_INT_CFG8;
+use crate::registers::FRQ_CTL;
+use crate::registers::TRIM3;
+use crate::registers::TRIM4;
 use crate::waveform::WaveformMemory;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Variant {
     DA7280 = 0xBA,
     DA7281 = 0xCA,
     DA7282 = 0xDA,
 }
 
+impl Variant {
+    /// Identify the variant from a CHIP_REV readout, if it is a known one.
+    pub fn from_chip_rev(chip_rev: CHIP_REV) -> Option<Self> {
+        if chip_rev.CHIP_REV_MAJOR() != 0xA {
+            return None;
+        }
+        match chip_rev.CHIP_REV_MINOR() {
+            0xB => Some(Variant::DA7280),
+            0xC => Some(Variant::DA7281),
+            0xD => Some(Variant::DA7282),
+            _ => None,
+        }
+    }
+
+    /// Size of the waveform memory (SNP_MEM) in bytes.
+    ///
+    /// All variants currently have the same 100 byte memory at 0x84..=0xE7.
+    pub fn waveform_memory_size(&self) -> usize {
+        match self {
+            Variant::DA7280 | Variant::DA7281 | Variant::DA7282 => waveform::MAX_MEMORY_SIZE,
+        }
+    }
+
+    /// Number of GPI trigger pins, starting at GPI_0 (Table 1: 3/1/3).
+    pub fn gpi_count(&self) -> usize {
+        match self {
+            Variant::DA7280 | Variant::DA7282 => 3,
+            Variant::DA7281 => 1,
+        }
+    }
+}
+
 pub struct DA728x<I2C> {
     i2c: I2C,
     address: u8,
     variant: Variant,
     actuator_config: Option<ActuatorConfig>,
     device_config: Option<DeviceConfig>,
+    enabled: bool,
+    standby_state: Option<TOP_CTL1>,
+    override_value: i8,
+    frequency_tracking_frozen: bool,
+    master_gain: u8,
+    global_strength: u8,
+    unit_scale_permille: u16,
+    max_level: u8,
+    gpi_even_event: [bool; 3],
+    looping_sequence: Option<u8>,
+    sequence_continue: bool,
+    busy_policy: BusyPolicy,
+    protection_profile: Option<ProtectionProfile>,
+    droop_compensation: Option<DroopCompensation>,
+    droop_scale_permille: u16,
+    thermal_derating: Option<ThermalDerating>,
+    derating_percent: u8,
+    selected_sequence: Option<(u8, u8)>,
+    top_ctl1_cache: Option<TOP_CTL1>,
+    max_transaction_size: Option<usize>,
+    uploaded_memory: Option<(WaveformMemory, bool)>,
+    auto_reconfigure: bool,
+    #[cfg(feature = "stats")]
+    i2c_stats: stats::I2cStats,
+}
+
+impl<I2C> DA728x<I2C> {
+    /// The I2C address of the device.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// The variant this driver was created for.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The actuator configuration applied by the last successful `configure()`.
+    pub fn actuator_config(&self) -> Option<&ActuatorConfig> {
+        self.actuator_config.as_ref()
+    }
+
+    /// The device configuration applied by the last successful `configure()`.
+    pub fn device_config(&self) -> Option<&DeviceConfig> {
+        self.device_config.as_ref()
+    }
+
+    /// Whether the configured operation mode is currently enabled through `enable()`.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Give back the I2C bus (or shared bus device), e.g. to hand it to another driver.
+    ///
+    /// The device keeps running in its current state.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
 }
 
 impl<I2C> DA728x<I2C>
 where
     I2C: I2c,
 {
-    pub async fn new(i2c: I2C, address: u8, variant: Variant) -> Result<Self, Error>
-    where
-        I2C: I2c,
-    {
+    pub async fn new(i2c: I2C, address: u8, variant: Variant) -> Result<Self, Error> {
         let mut da728x = DA728x {
             i2c,
             address,
             variant,
             actuator_config: None,
             device_config: None,
+            enabled: false,
+            standby_state: None,
+            override_value: 0,
+            frequency_tracking_frozen: false,
+            master_gain: 100,
+            global_strength: 100,
+            unit_scale_permille: 1000,
+            max_level: 127,
+            gpi_even_event: [false; 3],
+            looping_sequence: None,
+            sequence_continue: false,
+            busy_policy: BusyPolicy::Reject,
+            protection_profile: None,
+            droop_compensation: None,
+            droop_scale_permille: 1000,
+            thermal_derating: None,
+            derating_percent: 100,
+            selected_sequence: None,
+            top_ctl1_cache: None,
+            max_transaction_size: None,
+            uploaded_memory: None,
+            auto_reconfigure: false,
+            #[cfg(feature = "stats")]
+            i2c_stats: stats::I2cStats::default(),
         };
 
         // Check that CHIP_REV matches with selected Variant
@@ -67,37 +228,74 @@ where
             chip_rev.CHIP_REV_MAJOR()
         );
 
-        match da728x.variant {
-            Variant::DA7280 => {
-                if chip_rev.CHIP_REV_MINOR() != 0xB || chip_rev.CHIP_REV_MAJOR() != 0xA {
-                    return Err(Error::VariantMismatch);
-                }
-            }
-            Variant::DA7281 => {
-                if chip_rev.CHIP_REV_MINOR() != 0xC || chip_rev.CHIP_REV_MAJOR() != 0xA {
-                    return Err(Error::VariantMismatch);
-                }
-            }
-            Variant::DA7282 => {
-                if chip_rev.CHIP_REV_MINOR() != 0xD || chip_rev.CHIP_REV_MAJOR() != 0xA {
-                    return Err(Error::VariantMismatch);
-                }
-            }
+        if Variant::from_chip_rev(chip_rev) != Some(da728x.variant) {
+            return Err(Error::VariantMismatch);
         }
 
         Ok(da728x)
     }
 
+    /// Check whether a DA728x is present at the given address without constructing the driver.
+    ///
+    /// Reads CHIP_REV and returns the detected variant. Returns `Ok(None)` if nothing
+    /// acknowledges the address or the CHIP_REV value is not a known DA728x revision,
+    /// which makes this suitable for bus scans and factory fixtures checking board assembly.
+    pub async fn probe(i2c: &mut I2C, address: u8) -> Result<Option<Variant>, Error> {
+        let mut buffer = [0u8; 1];
+
+        match i2c.write_read(address, &[Register::CHIP_REV as u8], &mut buffer).await {
+            Ok(()) => Ok(Variant::from_chip_rev(CHIP_REV::from(buffer[0]))),
+            Err(e) => match e.kind() {
+                embedded_hal::i2c::ErrorKind::NoAcknowledge(_) => Ok(None),
+                kind => Err(Error::I2c(kind)),
+            },
+        }
+    }
+
     /// Configure the device with the supplied ActuatorConfig and DeviceConfig.
     ///
     /// There are a lot of inter-dependencies between the actuator config and the device config,
     /// so they need to be set together so that we can figure out if everything can work like configured
     /// And to deal with different value ranges for the registers depending on the driving modes
+    ///
+    /// Rewriting the configuration while the device is active would corrupt playback,
+    /// so this returns `DeviceBusy` unless the device is INACTIVE, see `set_busy_policy()`.
+    ///
+    /// With `DeviceConfig::low_latency` the operation mode is enabled afterwards, in
+    /// RTWM and ETWM mode only if a locked waveform memory was uploaded already.
     pub async fn configure(
         &mut self,
         actuator_config: ActuatorConfig,
         device_config: DeviceConfig,
     ) -> Result<(), Error> {
+        self.ensure_idle().await?;
+        self.apply_configuration(actuator_config, device_config).await?;
+        self.arm_low_latency().await
+    }
+
+    /// Enable the operation mode for `DeviceConfig::low_latency`.
+    ///
+    /// The waveform memory can only be written while the device is INACTIVE, so RTWM
+    /// and ETWM mode are only enabled once the memory is uploaded and locked.
+    async fn arm_low_latency(&mut self) -> Result<(), Error> {
+        let Some(device_config) = self.device_config else {
+            return Ok(());
+        };
+        let plays_memory = matches!(device_config.operation_mode, OperationMode::RTWM_MODE | OperationMode::ETWM_MODE);
+        if !device_config.low_latency || self.enabled || (plays_memory && !matches!(self.uploaded_memory, Some((_, true)))) {
+            return Ok(());
+        }
+
+        self.enable().await
+    }
+
+    /// Write the configuration, without checking whether the device is busy.
+    async fn apply_configuration(
+        &mut self,
+        actuator_config: ActuatorConfig,
+        device_config: DeviceConfig,
+    ) -> Result<(), Error> {
+        self.top_ctl1_cache = None;
 
         // Check for invalid combinations
         if device_config.driving_mode != DrivingMode::FREQUENCY_TRACK &&
@@ -105,6 +303,26 @@ where
                 return Err(Error::WrongMode);
             }
 
+        // Coin ERMs have no usable BEMF
+        if actuator_config.actuator_type == ActuatorType::ERM_COIN &&
+            (device_config.acceleration || device_config.rapid_stop) {
+                return Err(Error::WrongMode);
+            }
+
+        // Amplitude PID is only meant for bar ERMs (not needed for LRAs, not recommended for coin ERMs)
+        if device_config.amplitude_pid && actuator_config.actuator_type != ActuatorType::ERM_BAR {
+            return Err(Error::WrongMode);
+        }
+
+        if let Some(erm_overdrive) = device_config.erm_overdrive {
+            if actuator_config.actuator_type != ActuatorType::ERM_COIN {
+                return Err(Error::WrongMode);
+            }
+            if erm_overdrive.overdrive_ms > ErmOverdrive::MAX_MS || erm_overdrive.brake_ms > ErmOverdrive::MAX_MS {
+                return Err(Error::InvalidValue);
+            }
+        }
+
         // Check ranges of values before we set any registers
         if actuator_config.nominal_max_mV > 6000 {
             return Err(Error::InvalidValue);
@@ -118,47 +336,17 @@ where
         if !(4000..50_000).contains(&actuator_config.impedance_mOhm) {
             return Err(Error::InvalidValue);
         }
-        match device_config.driving_mode {
-            DrivingMode::FREQUENCY_TRACK => {
-                if !(50..300).contains(&actuator_config.frequency_Hz) {
-                    return Err(Error::InvalidValue);
-                }
-            }
-            DrivingMode::WIDEBAND | DrivingMode::CUSTOM_WAVEFORM => {
-                if !(50..300).contains(&actuator_config.frequency_Hz) {
-                    return Err(Error::InvalidValue);
-                }
-            }
+        if !device_config.driving_mode.frequency_range().contains(&actuator_config.frequency_Hz) {
+            return Err(Error::InvalidValue);
         }
 
-        // Figure out feature flags depending on actuator type and driving mode
-        let (bemf_sense_en,frequency_track_en, acceleration_en, rapid_stop_en);
-
-        match device_config.driving_mode {
-            DrivingMode::FREQUENCY_TRACK => {
-                bemf_sense_en = true;
-                frequency_track_en = true;
-                acceleration_en = device_config.acceleration;
-                rapid_stop_en = device_config.rapid_stop;
-            }
-            DrivingMode::WIDEBAND | DrivingMode::CUSTOM_WAVEFORM => {
-                bemf_sense_en = false;
-                frequency_track_en = false;
-                acceleration_en = false;
-                rapid_stop_en = false;
-            }
-        }
+        // CIF_I2C1 (I2C timeout), keep auto-increment writes for the waveform memory upload
+        let cif_i2c1 = CIF_I2C1::new().with_I2C_TO_ENABLE(device_config.i2c_timeout);
+        self.write_register(Register::CIF_I2C1, cif_i2c1.into()).await?;
 
         // TOP_CFG1 register (type and features)
-        // EMBEDDED_MODE enables automatic fault clearing when entering IDLE state
-        let top_cfg1 = TOP_CFG1::new()
-        .with_ACTUATOR_TYPE(actuator_config.actuator_type as u8)
-        .with_BEMF_SENSE_EN(bemf_sense_en)
-        .with_FREQ_TRACK_EN(frequency_track_en)
-        .with_ACCELERATION_EN(acceleration_en)
-        .with_RAPID_STOP_EN(rapid_stop_en)
-        .with_AMP_PID_EN(false) // Only supported with ERMs, disable for now.
-        .with_EMBEDDED_MODE(true); // Auto-clear faults when entering IDLE
+        let top_cfg1 = Self::top_cfg1(&actuator_config, &device_config);
+        let acceleration_en = top_cfg1.ACCELERATION_EN();
         self.write_register(Register::TOP_CFG1, top_cfg1.into()).await?;
 
         // TOP_CFG2 - MEM_DATA_SIGNED must match acceleration config:
@@ -172,26 +360,18 @@ where
         debug!("TOP_CFG2: accel_en={}, MEM_DATA_SIGNED={}, val={:02X}", acceleration_en, !acceleration_en, u8::from(top_cfg2));
         self.write_register(Register::TOP_CFG2, top_cfg2.into()).await?;
 
-        // ACTUATOR1 (nom max volt)
-        let volt_converted = ((actuator_config.nominal_max_mV as u32 * 1000) / 23400) as u8;
-        let actuator1 = ACTUATOR1::from(volt_converted);
-        self.write_register(Register::ACTUATOR1, actuator1.into()).await?;
-
-
-        // ACTUATOR2 (as max volt)
-        let volt_converted = ((actuator_config.absolute_max_mV as u32 * 1000) / 23400) as u8; // +1?
-        let actuator2 = ACTUATOR2::from(volt_converted);
-        self.write_register(Register::ACTUATOR2, actuator2.into()).await?;
+        // ACTUATOR1 (nom max volt) / ACTUATOR2 (abs max volt), the drive reference is scaled by the master gain
+        let (nommax, absmax) = self.drive_limit_registers(&actuator_config, acceleration_en);
+        self.write_register(Register::ACTUATOR1, ACTUATOR1::from(nommax).into()).await?;
+        self.write_register(Register::ACTUATOR2, ACTUATOR2::from(absmax).into()).await?;
 
         // ACTUATOR3 (imax)
-        let current_converted = ((actuator_config.max_current_mA as u32 * 1000 - 28600) / 7200) as u8; // +1?
-        let current_converted_clone = current_converted as u32;
+        let current_converted = milliamps_to_imax(actuator_config.max_current_mA);
         let actuator3 = ACTUATOR3::new().with_IMAX(current_converted);
         self.write_register(Register::ACTUATOR3, actuator3.into()).await?;
 
         // CALIB_V2I_L / CALIB_V2I_H (impedance)
-        let impedance_converted = ((actuator_config.impedance_mOhm as u32 * 1000 * (current_converted_clone + 4)) / 1610400) as u16;
-        let bytes: [u8; 2] = impedance_converted.to_be_bytes();
+        let bytes: [u8; 2] = v2i_register(&actuator_config).to_be_bytes();
         let calib_v2i_h = CALIB_V2I_H::from(bytes[0]);
         let calib_v2i_l = CALIB_V2I_L::from(bytes[1]);
         self.write_register(Register::CALIB_V2I_H, calib_v2i_h.into()).await?;
@@ -218,48 +398,282 @@ where
         // WAVEGEN_MODE = 1, V2I_FACTOR_FREEZE = 1
 
         if device_config.driving_mode == DrivingMode::WIDEBAND || device_config.driving_mode == DrivingMode::CUSTOM_WAVEFORM {
-            let frq_phase_h = FRQ_PHASE_H::from(0x00);
-            let frq_phase_l = FRQ_PHASE_L::new().with_DELAY_SHIFT_L(0x00).with_DELAY_FREEZE(true);
-            self.write_register(Register::FRQ_PHASE_H, frq_phase_h.into());
-            self.write_register(Register::FRQ_PHASE_L, frq_phase_l.into());
+            self.set_phase_delay(PhaseDelay::WIDEBAND).await?;
+        } else {
+            // Restore the reset value in case the device was in wideband mode before
+            self.set_phase_delay(PhaseDelay::FREQUENCY_TRACK).await?;
         }
 
         if device_config.driving_mode == DrivingMode::CUSTOM_WAVEFORM {
             let seq_ctl1 = SEQ_CTL1::new().with_WAVEGEN_MODE(true);
             let top_cfg4 = TOP_CFG4::new().with_V2I_FACTOR_FREEZE(true); // Unclear if TST_CALIB_IMPEDANCE_DIS should be true/false.
-            self.write_register(Register::SEQ_CTL1, seq_ctl1.into());
-            self.write_register(Register::TOP_CFG4, top_cfg4.into());
+            self.write_register(Register::SEQ_CTL1, seq_ctl1.into()).await?;
+            self.write_register(Register::TOP_CFG4, top_cfg4.into()).await?;
+        }
+
+        // COIN ERM
+        // V2I_FACTOR_FREEZE = 1, CALIB_IMPEDANCE_DIS = 1, BEMF_FAULT_LIM = 0
+        if actuator_config.actuator_type == ActuatorType::ERM_COIN {
+            let top_cfg4 = TOP_CFG4::from(self.read_register(Register::TOP_CFG4).await?)
+                .with_V2I_FACTOR_FREEZE(true)
+                .with_TST_CALIB_IMPEDANCE_DIS(true);
+            self.write_register(Register::TOP_CFG4, top_cfg4.into()).await?;
+
+            let top_int_cfg1 = TOP_INT_CFG1::from(self.read_register(Register::TOP_INT_CFG1).await?)
+                .with_BEMF_FAULT_LIM(0);
+            self.write_register(Register::TOP_INT_CFG1, top_int_cfg1.into()).await?;
         }
 
         self.actuator_config = Some(actuator_config);
         self.device_config = Some(device_config);
+        self.frequency_tracking_frozen = false;
         Ok(())
     }
 
+    /// Build TOP_CFG1 (actuator type and loop features) for the given configuration.
+    fn top_cfg1(actuator_config: &ActuatorConfig, device_config: &DeviceConfig) -> TOP_CFG1 {
+        // Figure out feature flags depending on actuator type and driving mode
+        let (bemf_sense_en,frequency_track_en, acceleration_en, rapid_stop_en);
+
+        match device_config.driving_mode {
+            DrivingMode::FREQUENCY_TRACK => {
+                // ERMs have no resonance to track, LRA_PER only sets the BEMF sensing rate
+                bemf_sense_en = true;
+                frequency_track_en = !actuator_config.actuator_type.is_erm();
+                acceleration_en = device_config.acceleration;
+                rapid_stop_en = device_config.rapid_stop;
+            }
+            DrivingMode::WIDEBAND | DrivingMode::CUSTOM_WAVEFORM => {
+                bemf_sense_en = false;
+                frequency_track_en = false;
+                acceleration_en = false;
+                rapid_stop_en = false;
+            }
+        }
+
+        // EMBEDDED_MODE enables automatic fault clearing when entering IDLE state
+        TOP_CFG1::new()
+        .with_ACTUATOR_TYPE(actuator_config.actuator_type.is_erm() as u8)
+        .with_BEMF_SENSE_EN(bemf_sense_en)
+        .with_FREQ_TRACK_EN(frequency_track_en)
+        .with_ACCELERATION_EN(acceleration_en)
+        .with_RAPID_STOP_EN(rapid_stop_en)
+        .with_AMP_PID_EN(device_config.amplitude_pid) // Validated in configure()
+        .with_EMBEDDED_MODE(true) // Auto-clear faults when entering IDLE
+    }
+
+    /// ACTUATOR_NOMMAX and ACTUATOR_ABSMAX, with the drive reference scaled by the master
+    /// gain, the global strength and the unit calibration.
+    ///
+    /// Drive levels are relative to ACTUATOR_NOMMAX with Active Acceleration enabled
+    /// and relative to ACTUATOR_ABSMAX without (5.2.4 / 5.2.5).
+    fn drive_limit_registers(&self, actuator_config: &ActuatorConfig, acceleration_en: bool) -> (u8, u8) {
+        let gain = self.master_gain as u64 * self.global_strength as u64 * self.unit_scale_permille as u64;
+        let scale = |millivolts: u16| (millivolts as u64 * gain / 10_000_000) as u16;
+
+        if acceleration_en {
+            (
+                millivolts_to_register(scale(actuator_config.nominal_max_mV)),
+                millivolts_to_register(actuator_config.absolute_max_mV),
+            )
+        } else {
+            (
+                millivolts_to_register(actuator_config.nominal_max_mV),
+                millivolts_to_register(scale(actuator_config.absolute_max_mV)),
+            )
+        }
+    }
+
+    /// TOP_CFG1 as it should currently be on the device, including a frozen frequency tracking.
+    fn expected_top_cfg1(&self, actuator_config: &ActuatorConfig, device_config: &DeviceConfig) -> TOP_CFG1 {
+        let top_cfg1 = Self::top_cfg1(actuator_config, device_config);
+        top_cfg1.with_FREQ_TRACK_EN(top_cfg1.FREQ_TRACK_EN() && !self.frequency_tracking_frozen)
+    }
+
+    /// TOP_CFG1 and ACTUATOR1..3 as they should currently be on the device.
+    pub(crate) fn expected_config_registers(
+        &self,
+        actuator_config: &ActuatorConfig,
+        device_config: &DeviceConfig,
+    ) -> [(Register, u8); 4] {
+        let top_cfg1 = self.expected_top_cfg1(actuator_config, device_config);
+        let (nommax, absmax) = self.drive_limit_registers(actuator_config, top_cfg1.ACCELERATION_EN());
+        [
+            (Register::TOP_CFG1, u8::from(top_cfg1)),
+            (Register::ACTUATOR1, nommax),
+            (Register::ACTUATOR2, absmax),
+            (Register::ACTUATOR3, u8::from(ACTUATOR3::new().with_IMAX(milliamps_to_imax(actuator_config.max_current_mA)))),
+        ]
+    }
+
+    /// Check that the device still responds and holds the configuration applied by `configure()`.
+    ///
+    /// Compares CHIP_REV, TOP_CFG1 and ACTUATOR1..3 against the expected values.
+    /// If the configuration was lost (e.g. after a brown-out reset) and `reapply` is set,
+    /// the stored configuration and the last uploaded waveform memory are written again
+    /// and the operation mode is re-enabled if it was enabled before.
+    pub async fn health_check(&mut self, reapply: bool) -> Result<Health, Error> {
+        let chip_rev = match self.get_chip_rev().await {
+            Ok(chip_rev) => chip_rev,
+            Err(Error::I2c(embedded_hal::i2c::ErrorKind::NoAcknowledge(_))) => {
+                return Ok(Health::NotResponding);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if Variant::from_chip_rev(chip_rev) != Some(self.variant) {
+            return Ok(Health::VariantMismatch);
+        }
+
+        let (Some(actuator_config), Some(device_config)) = (self.actuator_config, self.device_config) else {
+            return Ok(Health::Healthy);
+        };
+
+        let expected = self.expected_config_registers(&actuator_config, &device_config);
+
+        let mut intact = true;
+        for (register, value) in expected {
+            if self.read_register(register).await? != value {
+                intact = false;
+                break;
+            }
+        }
+
+        if intact {
+            return Ok(Health::Healthy);
+        }
+
+        #[cfg(feature = "debug")]
+        debug!("Configuration registers do not match, device was likely reset");
+
+        if !reapply {
+            return Ok(Health::ConfigurationLost);
+        }
+
+        let was_enabled = self.enabled;
+        self.apply_configuration(actuator_config, device_config).await?;
+        if let Some((memory, locked)) = self.uploaded_memory {
+            self.upload_waveform_memory(&memory, locked).await?;
+        }
+        if was_enabled {
+            self.enable().await?;
+        }
+
+        Ok(Health::Reconfigured)
+    }
+
+    /// Run `health_check(true)` from `process_events()` whenever E_UVLO is latched.
+    ///
+    /// A brown-out may reset the device, so the configuration and the last uploaded
+    /// waveform memory are re-applied if they were lost, and the handler receives
+    /// `HapticEvent::Reconfigured`. Disabled by default.
+    pub fn set_auto_reconfigure(&mut self, enabled: bool) {
+        self.auto_reconfigure = enabled;
+    }
+
+    /// Whether `set_auto_reconfigure()` is enabled.
+    pub fn auto_reconfigure(&self) -> bool {
+        self.auto_reconfigure
+    }
+
     pub async fn get_chip_rev(&mut self) -> Result<registers::CHIP_REV, Error> {
         let reg = self.read_register(Register::CHIP_REV).await?;
         Ok(CHIP_REV::from(reg))
     }
 
     /// This gets all system events (and also clears them...)
+    ///
+    /// Use `peek_events()` and `clear_events()` if several consumers need to see the events.
     pub async fn get_events(&mut self) -> Result<(IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG), Error> {
+        let events = self.peek_events().await?;
+
+        // Clear events (only IRQ_EVENT1, this also clears the diagnostics)
+        self.clear_events(IRQ_EVENT1::from(0xFF)).await?;
+
+        Ok(events)
+    }
+
+    /// Like `get_events()`, but decoded into typed [`events::HapticEvent`]s.
+    pub async fn get_decoded_events(&mut self) -> Result<Events, Error> {
+        self.get_events().await.map(Events::from)
+    }
+
+    /// Read all latched system events without clearing them.
+    pub async fn peek_events(&mut self) -> Result<(IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG), Error> {
         let irq_event1 = IRQ_EVENT1::from(self.read_register(Register::IRQ_EVENT1).await?);
         let irq_event_warning_diag = IRQ_EVENT_WARNING_DIAG::from(self.read_register(Register::IRQ_EVENT_WARNING_DIAG).await?);
         let irq_event_seq_diag = IRQ_EVENT_SEQ_DIAG::from(self.read_register(Register::IRQ_EVENT_SEQ_DIAG).await?);
 
-        // Clear events (only IRQ_EVENT1)
-        self.write_register(Register::IRQ_EVENT1, 0xFF);
+        // Faults may stop the device on its own, read TOP_CTL1 again next time
+        if irq_event1.E_UVLO() || irq_event1.E_OVERTEMP_CRIT() || irq_event1.E_SEQ_FAULT()
+            || irq_event1.E_ACTUATOR_FAULT() || irq_event1.E_OC_FAULT() {
+            self.top_ctl1_cache = None;
+        }
 
         Ok((irq_event1, irq_event_warning_diag, irq_event_seq_diag))
     }
 
+    /// Acknowledge the latched events set in `mask` (write 1 to clear).
+    ///
+    /// Clearing E_WARNING also clears IRQ_EVENT_WARNING_DIAG, and clearing E_SEQ_FAULT
+    /// also clears IRQ_EVENT_SEQ_DIAG.
+    pub async fn clear_events(&mut self, mask: IRQ_EVENT1) -> Result<(), Error> {
+        self.write_register(Register::IRQ_EVENT1, mask.into()).await
+    }
+
+    /// Select which events may assert the nIRQ pin.
+    pub async fn set_irq_mask(&mut self, mask: IrqMask) -> Result<(), Error> {
+        let irq_mask1 = IRQ_MASK1::new()
+            .with_SEQ_CONTINUE_M(mask.seq_continue)
+            .with_E_UVLO_M(mask.uvlo)
+            .with_SEQ_DONE_M(mask.seq_done)
+            .with_OVERTEMP_CRIT_M(mask.overtemp_crit)
+            .with_SEQ_FAULT_M(mask.seq_fault)
+            .with_WARNING_M(mask.warning)
+            .with_ACTUATOR_M(mask.actuator_fault)
+            .with_OC_M(mask.oc_fault);
+        let irq_mask2 = IRQ_MASK2::new().with_ADC_SAT_M(mask.adc_saturation);
+
+        self.write_register(Register::IRQ_MASK1, irq_mask1.into()).await?;
+        self.write_register(Register::IRQ_MASK2, irq_mask2.into()).await
+    }
+
+    /// Read back which events are currently masked from the nIRQ pin.
+    pub async fn get_irq_mask(&mut self) -> Result<IrqMask, Error> {
+        let irq_mask1 = IRQ_MASK1::from(self.read_register(Register::IRQ_MASK1).await?);
+        let irq_mask2 = IRQ_MASK2::from(self.read_register(Register::IRQ_MASK2).await?);
+
+        Ok(IrqMask {
+            seq_continue: irq_mask1.SEQ_CONTINUE_M(),
+            uvlo: irq_mask1.E_UVLO_M(),
+            seq_done: irq_mask1.SEQ_DONE_M(),
+            overtemp_crit: irq_mask1.OVERTEMP_CRIT_M(),
+            seq_fault: irq_mask1.SEQ_FAULT_M(),
+            warning: irq_mask1.WARNING_M(),
+            actuator_fault: irq_mask1.ACTUATOR_M(),
+            oc_fault: irq_mask1.OC_M(),
+            adc_saturation: irq_mask2.ADC_SAT_M(),
+        })
+    }
+
     pub async fn get_status(&mut self) -> Result<IRQ_STATUS1, Error> {
         let status = self.read_register(Register::IRQ_STATUS1).await?;
         Ok(IRQ_STATUS1::from(status))
     }
 
 
+    /// Set the drive frequency, see `set_frequency_with()`.
     pub async fn set_frequency(&mut self, frequency_hz: u16) -> Result<(), Error> {
+        self.set_frequency_with(frequency_hz, FrequencyUpdate::Immediate).await
+    }
+
+    /// Set the drive frequency (FRQ_LRA_PER_H / FRQ_LRA_PER_L).
+    ///
+    /// FRQ_LRA_PER_H is written first. With `FrequencyUpdate::Burst` both bytes go out
+    /// in a single auto-increment transaction, which keeps the time between them as
+    /// short as possible when changing the frequency on the fly in wideband mode.
+    /// The burst is split if `set_max_transaction_size()` doesn't allow 3 bytes.
+    pub async fn set_frequency_with(&mut self, frequency_hz: u16, update: FrequencyUpdate) -> Result<(), Error> {
         if self.actuator_config.is_none() || self.device_config.is_none() {
             return Err(Error::NotConfigured);
         }
@@ -267,17 +681,8 @@ where
         let device_config = self.device_config.as_ref().unwrap();
 
         // Different frequency ranges with normal mode and wide-band/custom waveform mode
-        match device_config.driving_mode {
-            DrivingMode::FREQUENCY_TRACK => {
-                if !(50..300).contains(&frequency_hz) {
-                    return Err(Error::InvalidValue);
-                }
-            }
-            DrivingMode::WIDEBAND | DrivingMode::CUSTOM_WAVEFORM => {
-                if !(25..1024).contains(&frequency_hz) {
-                    return Err(Error::InvalidValue);
-                }
-            }
+        if !device_config.driving_mode.frequency_range().contains(&frequency_hz) {
+            return Err(Error::InvalidValue);
         }
 
         let frequency_converted =  (1000000000 / (frequency_hz as u32 * 1333)) as u16;
@@ -285,138 +690,708 @@ where
         let frequency_converted_l: u8 = (frequency_converted & 0x7F) as u8;
         let frq_lra_per_h = FRQ_LRA_PER_H::from(frequency_converted_h);
         let frq_lra_per_l = FRQ_LRA_PER_L::new().with_LRA_PER_L(frequency_converted_l);
-        self.write_register(Register::FRQ_LRA_PER_H, frq_lra_per_h.into()).await?;
-        self.write_register(Register::FRQ_LRA_PER_L, frq_lra_per_l.into()).await?;
-
-        Ok(())
-    }
 
-    /// Direct register override
-    /// 
-    /// This sets the amplitude in the DRO_MODE
-    /// With acceleration enabled, this has a range of 0..127
-    /// With acceleration disabled, this has a range of -127..127
-    pub async fn set_override_value(&mut self, value: i8) -> Result<(), Error> {
-        if self.actuator_config.is_none() || self.device_config.is_none() {
-            return Err(Error::NotConfigured);
-        }
-
-        if self.device_config.unwrap().acceleration && value < 0 {
-            return Err(Error::InvalidValue);
+        match update {
+            FrequencyUpdate::Immediate => {
+                self.write_register(Register::FRQ_LRA_PER_H, frq_lra_per_h.into()).await?;
+                self.write_register(Register::FRQ_LRA_PER_L, frq_lra_per_l.into()).await?;
+            }
+            FrequencyUpdate::Burst => {
+                self.write_registers(Register::FRQ_LRA_PER_H, &[frq_lra_per_h.into(), frq_lra_per_l.into()]).await?;
+            }
         }
 
-        let device_config = self.device_config.unwrap();
+        Ok(())
+    }
 
-        if device_config.operation_mode != OperationMode::DRO_MODE {
-            return Err(Error::WrongMode);
+    /// Enable or disable the I2C interface timeout at runtime.
+    ///
+    /// When enabled, the chip resets its I2C interface if SCL has no falling edge for ~35 ms,
+    /// so a host that hangs mid-transaction cannot lock up the bus.
+    /// The timeout duration is fixed by the chip.
+    pub async fn set_i2c_timeout(&mut self, enabled: bool) -> Result<(), Error> {
+        let cif_i2c1 = CIF_I2C1::new().with_I2C_TO_ENABLE(enabled);
+        self.write_register(Register::CIF_I2C1, cif_i2c1.into()).await?;
+
+        if let Some(device_config) = self.device_config.as_mut() {
+            device_config.i2c_timeout = enabled;
         }
 
-        let top_ctl_2 = TOP_CTL2::from(value as u8);        
-        self.write_register(Register::TOP_CTL2, top_ctl_2.into()).await?;
-
         Ok(())
     }
 
-    /// Enable the configured operation mode
+    /// Freeze or release the frequency tracking.
     ///
-    /// Note: For RTWM/ETWM modes, this enables the mode but does NOT start sequence playback.
-    /// Call `play_sequence()` or `start_sequence()` separately after enabling.
-    pub async fn enable(&mut self) -> Result<(), Error> {
-        if self.actuator_config.is_none() || self.device_config.is_none() {
+    /// Freezing writes the tracked resonant period (average over the last four
+    /// half-periods) to LRA_PER and disables FREQ_TRACK_EN, so the drive frequency
+    /// stays fixed. Releasing enables the tracking again, starting from that period.
+    /// Active Acceleration and Rapid Stop rely on the tracking and are less effective
+    /// while it is frozen.
+    pub async fn freeze_frequency_tracking(&mut self, freeze: bool) -> Result<(), Error> {
+        let (Some(actuator_config), Some(device_config)) = (self.actuator_config, self.device_config) else {
             return Err(Error::NotConfigured);
+        };
+
+        if device_config.driving_mode != DrivingMode::FREQUENCY_TRACK || actuator_config.actuator_type.is_erm() {
+            return Err(Error::WrongMode);
         }
-        let device_config = self.device_config.unwrap();
 
-        // Build new TOP_CTL1 value - explicitly clear SEQ_START to avoid
-        // accidentally starting a sequence before memory is ready
-        let top_ctl1 = TOP_CTL1::new()
-            .with_OPERATION_MODE(device_config.operation_mode as u8)
-            .with_STANDBY_EN(false)
-            .with_SEQ_START(false);
+        if freeze {
+            let mut period = [0u8; 2];
+            self.read_registers(Register::LRA_AVR_H, &mut period).await?;
 
-        #[cfg(feature = "debug")]
-        debug!("TOP_CTL1: {:?}", top_ctl1);
+            // Nothing tracked yet, keep the programmed period
+            if period != [0, 0] {
+                let frq_lra_per_h = FRQ_LRA_PER_H::from(period[0]);
+                let frq_lra_per_l = FRQ_LRA_PER_L::new().with_LRA_PER_L(period[1] & 0x7F);
+                self.write_register(Register::FRQ_LRA_PER_H, frq_lra_per_h.into()).await?;
+                self.write_register(Register::FRQ_LRA_PER_L, frq_lra_per_l.into()).await?;
+            }
+        }
 
-        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.frequency_tracking_frozen = freeze;
+        let top_cfg1 = self.expected_top_cfg1(&actuator_config, &device_config);
+        self.write_register(Register::TOP_CFG1, top_cfg1.into()).await
+    }
 
-        Ok(())
+    /// Whether the frequency tracking is currently frozen.
+    pub fn is_frequency_tracking_frozen(&self) -> bool {
+        self.frequency_tracking_frozen
     }
 
-    /// Disable the configured Operation Mode (also stopping haptic feedback)
-    pub async fn disable(&mut self) -> Result<(), Error> {
-        if self.actuator_config.is_none() || self.device_config.is_none() {
-            return Err(Error::NotConfigured);
+    /// Set the frequency tracking limits (TOP_INT_CFG1, TOP_INT_CFG8 and FRQ_CTL).
+    pub async fn set_frequency_tracking(&mut self, frequency_tracking: FrequencyTracking) -> Result<(), Error> {
+        if frequency_tracking.locked_limit > 63 || frequency_tracking.bemf_limit > 15 {
+            return Err(Error::InvalidValue);
         }
-        let device_config = self.device_config.unwrap();
 
-        let mut top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
-        top_ctl1 = top_ctl1.with_OPERATION_MODE(OperationMode::INACTIVE as u8);
-        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        let top_int_cfg1 = TOP_INT_CFG1::from(self.read_register(Register::TOP_INT_CFG1).await?)
+            .with_FRQ_LOCKED_LIM(frequency_tracking.locked_limit);
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?)
+            .with_FRQ_TRACK_BEMF_LIM(frequency_tracking.bemf_limit);
+        let frq_ctl = FRQ_CTL::from(self.read_register(Register::FRQ_CTL).await?)
+            .with_FREQ_TRACKING_AUTO_ADJ(frequency_tracking.auto_adjust)
+            .with_FREQ_TRACKING_FORCE_ON(frequency_tracking.force_on);
+        self.write_register(Register::TOP_INT_CFG1, top_int_cfg1.into()).await?;
+        self.write_register(Register::TOP_INT_CFG8, top_int_cfg8.into()).await?;
+        self.write_register(Register::FRQ_CTL, frq_ctl.into()).await
+    }
 
-        Ok(())
+    /// Read the frequency tracking limits.
+    pub async fn get_frequency_tracking(&mut self) -> Result<FrequencyTracking, Error> {
+        let top_int_cfg1 = TOP_INT_CFG1::from(self.read_register(Register::TOP_INT_CFG1).await?);
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?);
+        let frq_ctl = FRQ_CTL::from(self.read_register(Register::FRQ_CTL).await?);
+
+        Ok(FrequencyTracking {
+            locked_limit: top_int_cfg1.FRQ_LOCKED_LIM(),
+            bemf_limit: top_int_cfg8.FRQ_TRACK_BEMF_LIM(),
+            auto_adjust: frq_ctl.FREQ_TRACKING_AUTO_ADJ(),
+            force_on: frq_ctl.FREQ_TRACKING_FORCE_ON(),
+        })
     }
 
-    /// Sets a custom drive waveform, see 5.7.6 Custom Waveform Operation
-    /// Device needs to be in the CUSTOM_WAVEFORM mode.
-    pub async fn set_custom_drive_waveform(&mut self, points: [u8; 3]) -> Result<(), Error> {
-        if self.actuator_config.is_none() || self.device_config.is_none() {
-            return Err(Error::NotConfigured);
+    /// Set the drive phase delay relative to the BEMF (FRQ_PHASE_H / FRQ_PHASE_L).
+    ///
+    /// `configure()` selects `PhaseDelay::WIDEBAND` for wideband and custom waveform mode.
+    pub async fn set_phase_delay(&mut self, phase_delay: PhaseDelay) -> Result<(), Error> {
+        let (delay_h, delay_shift_l, freeze) = phase_delay.raw();
+        if delay_shift_l > 7 {
+            return Err(Error::InvalidValue);
         }
-        let device_config = self.device_config.as_ref().unwrap();
 
-        if device_config.driving_mode != DrivingMode::CUSTOM_WAVEFORM {
-            return Err(Error::WrongMode);
-        }
+        let frq_phase_h = FRQ_PHASE_H::new().with_DELAY_H(delay_h);
+        let frq_phase_l = FRQ_PHASE_L::new().with_DELAY_SHIFT_L(delay_shift_l).with_DELAY_FREEZE(freeze);
+        self.write_register(Register::FRQ_PHASE_H, frq_phase_h.into()).await?;
+        self.write_register(Register::FRQ_PHASE_L, frq_phase_l.into()).await
+    }
 
-        // TODO
+    /// Read the drive phase delay.
+    pub async fn get_phase_delay(&mut self) -> Result<PhaseDelay, Error> {
+        let frq_phase_h = FRQ_PHASE_H::from(self.read_register(Register::FRQ_PHASE_H).await?);
+        let frq_phase_l = FRQ_PHASE_L::from(self.read_register(Register::FRQ_PHASE_L).await?);
 
-        Ok(())
+        Ok(PhaseDelay::from_raw(
+            frq_phase_h.DELAY_H(),
+            frq_phase_l.DELAY_SHIFT_L(),
+            frq_phase_l.DELAY_FREEZE(),
+        ))
     }
 
-    async fn read_register(&mut self, register: Register) -> Result<u8, Error> {
-        let mut buffer = [0u8; 1];
-
-        self.i2c
-            .write_read(self.address, &[register as u8], &mut buffer)
-            .await
+    /// Apply the braking settings (TOP_CFG1.RAPID_STOP_EN, TOP_INT_CFG8.RAPID_STOP_LIM
+    /// and TOP_CFG2.FULL_BRAKE_THR).
+    ///
+    /// `rapid_stop` replaces `DeviceConfig::rapid_stop` and is validated the same way.
+    pub async fn set_braking(&mut self, braking: BrakingConfig) -> Result<(), Error> {
+        let (Some(actuator_config), Some(mut device_config)) = (self.actuator_config, self.device_config) else {
+            return Err(Error::NotConfigured);
+        };
+        if braking.rapid_stop
+            && (device_config.driving_mode != DrivingMode::FREQUENCY_TRACK
+                || actuator_config.actuator_type == ActuatorType::ERM_COIN)
+        {
+            return Err(Error::WrongMode);
+        }
+        if braking.rapid_stop_limit > 7 || braking.full_brake_threshold > 15 {
+            return Err(Error::InvalidValue);
+        }
+
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?);
+        if top_int_cfg8.FRQ_TRACK_BEMF_LIM() >= braking.rapid_stop_limit {
+            return Err(Error::InvalidValue);
+        }
+        self.write_register(Register::TOP_INT_CFG8, top_int_cfg8.with_RAPID_STOP_LIM(braking.rapid_stop_limit).into()).await?;
+        self.set_full_brake_threshold(braking.full_brake_threshold).await?;
+
+        device_config.rapid_stop = braking.rapid_stop;
+        let top_cfg1 = self.expected_top_cfg1(&actuator_config, &device_config);
+        self.write_register(Register::TOP_CFG1, top_cfg1.into()).await?;
+        self.device_config = Some(device_config);
+        Ok(())
+    }
+
+    /// Read the braking settings.
+    pub async fn get_braking(&mut self) -> Result<BrakingConfig, Error> {
+        let top_cfg1 = TOP_CFG1::from(self.read_register(Register::TOP_CFG1).await?);
+        let top_int_cfg8 = TOP_INT_CFG8::from(self.read_register(Register::TOP_INT_CFG8).await?);
+        let top_cfg2 = self.get_top_cfg2().await?;
+
+        Ok(BrakingConfig {
+            rapid_stop: top_cfg1.RAPID_STOP_EN(),
+            rapid_stop_limit: top_int_cfg8.RAPID_STOP_LIM(),
+            full_brake_threshold: top_cfg2.FULL_BRAKE_THR(),
+        })
+    }
+
+    /// Set the PWM mode full-brake threshold (TOP_CFG2.FULL_BRAKE_THR, 0..=15 in 6.66 % steps).
+    ///
+    /// PWM duty cycles within the threshold of the zero drive duty cycle are interpreted
+    /// as zero drive (5.2.5 PWM Mode). 0 disables the threshold, the reset value is 1.
+    pub async fn set_full_brake_threshold(&mut self, threshold: u8) -> Result<(), Error> {
+        if threshold > 15 {
+            return Err(Error::InvalidValue);
+        }
+
+        let top_cfg2 = self.get_top_cfg2().await?.with_FULL_BRAKE_THR(threshold);
+        self.write_register(Register::TOP_CFG2, top_cfg2.into()).await
+    }
+
+    /// Read TOP_CFG2 (full-brake threshold and memory data format).
+    ///
+    /// MEM_DATA_SIGNED is managed by `configure()`, as it has to match ACCELERATION_EN.
+    pub async fn get_top_cfg2(&mut self) -> Result<TOP_CFG2, Error> {
+        Ok(TOP_CFG2::from(self.read_register(Register::TOP_CFG2).await?))
+    }
+
+    /// Set the current regulation loop filter (TRIM3 / TRIM4).
+    ///
+    /// Should be changed while no playback is active.
+    pub async fn set_loop_filter(&mut self, loop_filter: LoopFilter) -> Result<(), Error> {
+        if loop_filter.cap_trim > 3 || loop_filter.res_trim > 3 {
+            return Err(Error::InvalidValue);
+        }
+
+        let trim3 = TRIM3::from(self.read_register(Register::TRIM3).await?)
+            .with_LOOP_FILT_LOW_BW(loop_filter.low_bandwidth);
+        let trim4 = TRIM4::from(self.read_register(Register::TRIM4).await?)
+            .with_LOOP_FILT_CAP_TRIM(loop_filter.cap_trim)
+            .with_LOOP_FILT_RES_TRIM(loop_filter.res_trim);
+        self.write_register(Register::TRIM3, trim3.into()).await?;
+        self.write_register(Register::TRIM4, trim4.into()).await
+    }
+
+    /// Read the current regulation loop filter.
+    pub async fn get_loop_filter(&mut self) -> Result<LoopFilter, Error> {
+        let trim3 = TRIM3::from(self.read_register(Register::TRIM3).await?);
+        let trim4 = TRIM4::from(self.read_register(Register::TRIM4).await?);
+
+        Ok(LoopFilter {
+            cap_trim: trim4.LOOP_FILT_CAP_TRIM(),
+            res_trim: trim4.LOOP_FILT_RES_TRIM(),
+            low_bandwidth: trim3.LOOP_FILT_LOW_BW(),
+        })
+    }
+
+    /// Set a device-wide gain in percent (0..=100) of the drive reference voltage.
+    ///
+    /// There is no dedicated gain register: all drive levels (DRO, PWM, waveform memory)
+    /// are relative to ACTUATOR_NOMMAX with Active Acceleration enabled, and to
+    /// ACTUATOR_ABSMAX without. Scaling that reference attenuates everything with a
+    /// single write; the Active Acceleration overdrive limit (ACTUATOR_ABSMAX) is not changed.
+    /// The gain is kept when the device is reconfigured.
+    pub async fn set_master_gain(&mut self, percent: u8) -> Result<(), Error> {
+        if percent > 100 {
+            return Err(Error::InvalidValue);
+        }
+
+        let (Some(actuator_config), Some(device_config)) = (self.actuator_config, self.device_config) else {
+            return Err(Error::NotConfigured);
+        };
+
+        self.master_gain = percent;
+        self.write_drive_reference(&actuator_config, &device_config).await
+    }
+
+    /// The device-wide gain in percent.
+    pub fn master_gain(&self) -> u8 {
+        self.master_gain
+    }
+
+    /// Set the user's vibration intensity preference in percent (0..=100).
+    ///
+    /// Scales all subsequent playback like `set_master_gain()`, on top of it, so a
+    /// settings slider maps to this call while the master gain stays under control of
+    /// the application. Can be set before `configure()` and is kept when the device
+    /// is reconfigured.
+    pub async fn set_global_strength(&mut self, percent: u8) -> Result<(), Error> {
+        if percent > 100 {
+            return Err(Error::InvalidValue);
+        }
+
+        self.global_strength = percent;
+        match (self.actuator_config, self.device_config) {
+            (Some(actuator_config), Some(device_config)) => {
+                self.write_drive_reference(&actuator_config, &device_config).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The vibration intensity set with `set_global_strength()`, in percent.
+    pub fn global_strength(&self) -> u8 {
+        self.global_strength
+    }
+
+    /// Write the scaled drive reference, ACTUATOR_NOMMAX or ACTUATOR_ABSMAX.
+    async fn write_drive_reference(
+        &mut self,
+        actuator_config: &ActuatorConfig,
+        device_config: &DeviceConfig,
+    ) -> Result<(), Error> {
+        let acceleration_en = Self::top_cfg1(actuator_config, device_config).ACCELERATION_EN();
+        let (nommax, absmax) = self.drive_limit_registers(actuator_config, acceleration_en);
+        if acceleration_en {
+            self.write_register(Register::ACTUATOR1, ACTUATOR1::from(nommax).into()).await
+        } else {
+            self.write_register(Register::ACTUATOR2, ACTUATOR2::from(absmax).into()).await
+        }
+    }
+
+    /// Limit the magnitude of DRO override values to `max_level` (0..=127).
+    ///
+    /// Enforced by the driver, independent of the ACTUATOR registers: every override
+    /// value, including those from effects and `override_command()`, is clamped to
+    /// -max_level..=max_level, so an application bug cannot exceed a product-defined
+    /// comfort or safety limit. Waveform memory playback is not affected, use
+    /// `set_master_gain()` for that. The limit is kept when the device is reconfigured.
+    pub fn set_max_level(&mut self, max_level: u8) -> Result<(), Error> {
+        if max_level > 127 {
+            return Err(Error::InvalidValue);
+        }
+
+        self.max_level = max_level;
+        Ok(())
+    }
+
+    /// The DRO override value ceiling, see `set_max_level()`.
+    pub fn max_level(&self) -> u8 {
+        self.max_level
+    }
+
+    /// Change the actuator current limit (ACTUATOR3.IMAX) on the fly, e.g. to tighten it
+    /// when the battery is low or the enclosure is hot.
+    ///
+    /// CALIB_V2I is derived from IMAX and is reprogrammed with it. The new limit replaces
+    /// `max_current_mA` of the actuator configuration, so it is kept by `health_check()`.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if `milliamps` is outside of the IMAX range (29..=252 mA).
+    pub async fn set_max_current_ma(&mut self, milliamps: u16) -> Result<(), Error> {
+        if !(29..=252).contains(&milliamps) {
+            return Err(Error::InvalidValue);
+        }
+        let mut actuator_config = self.actuator_config.ok_or(Error::NotConfigured)?;
+        actuator_config.max_current_mA = milliamps;
+
+        let actuator3 = ACTUATOR3::new().with_IMAX(milliamps_to_imax(milliamps));
+        self.write_register(Register::ACTUATOR3, actuator3.into()).await?;
+        let bytes: [u8; 2] = v2i_register(&actuator_config).to_be_bytes();
+        self.write_register(Register::CALIB_V2I_H, CALIB_V2I_H::from(bytes[0]).into()).await?;
+        self.write_register(Register::CALIB_V2I_L, CALIB_V2I_L::from(bytes[1]).into()).await?;
+
+        self.actuator_config = Some(actuator_config);
+        Ok(())
+    }
+
+    /// Direct register override
+    /// 
+    /// This sets the amplitude in the DRO_MODE
+    /// With acceleration enabled, this has a range of 0..=127
+    /// With acceleration disabled, this has a range of -127..=127
+    ///
+    /// Returns the raw TOP_CTL2 value programmed, see `command::override_register_value()`.
+    /// This differs from `value` when it was scaled by the droop compensation or
+    /// clamped by `set_max_level()`.
+    pub async fn set_override_value(&mut self, value: i8) -> Result<u8, Error> {
+        let command = self.override_command(value)?;
+        self.send(command).await?;
+        self.override_value = value;
+
+        Ok(command.value())
+    }
+
+    /// Set the DRO level of a coin ERM, applying the configured `ErmOverdrive`.
+    ///
+    /// When the level increases, full drive is applied for `overdrive_ms` first.
+    /// When stopping, reversed full drive is applied for `brake_ms` first.
+    /// Without `erm_overdrive` configured, this is the same as `set_override_value()`.
+    ///
+    /// The level and the full drive pulses are checked against the protection profile,
+    /// the level with an on-time of 0 as it is held until the next call.
+    pub async fn drive_erm<D: DelayNs>(&mut self, level: i8, delay: &mut D) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        let previous = self.override_value;
+        self.check_protection(level, 0)?;
+
+        if let Some(erm_overdrive) = device_config.erm_overdrive {
+            if level == 0 && previous != 0 {
+                if erm_overdrive.brake_ms > 0 {
+                    let brake = if previous > 0 { -127 } else { 127 };
+                    self.check_protection(brake, erm_overdrive.brake_ms as u32)?;
+                    self.set_override_value(brake).await?;
+                    delay.delay_ms(erm_overdrive.brake_ms as u32).await;
+                }
+            } else if level.unsigned_abs() > previous.unsigned_abs() && erm_overdrive.overdrive_ms > 0 {
+                let kick = if level > 0 { 127 } else { -127 };
+                self.check_protection(kick, erm_overdrive.overdrive_ms as u32)?;
+                self.set_override_value(kick).await?;
+                delay.delay_ms(erm_overdrive.overdrive_ms as u32).await;
+            }
+        }
+
+        self.set_override_value(level).await?;
+        Ok(())
+    }
+
+    /// Enable the configured operation mode
+    ///
+    /// Note: For RTWM/ETWM modes, this enables the mode but does NOT start sequence playback.
+    /// Call `play_sequence()` or `start_sequence()` separately after enabling.
+    pub async fn enable(&mut self) -> Result<(), Error> {
+        if self.actuator_config.is_none() || self.device_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+        let device_config = self.device_config.unwrap();
+
+        // Build new TOP_CTL1 value - explicitly clear SEQ_START to avoid
+        // accidentally starting a sequence before memory is ready
+        let top_ctl1 = Self::top_ctl1(&device_config, false);
+
+        #[cfg(feature = "debug")]
+        debug!("TOP_CTL1: {:?}", top_ctl1);
+
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.enabled = true;
+        self.standby_state = None;
+
+        Ok(())
+    }
+
+    /// TOP_CTL1 value for the configured operation mode.
+    fn top_ctl1(device_config: &DeviceConfig, seq_start: bool) -> TOP_CTL1 {
+        TOP_CTL1::new()
+            .with_OPERATION_MODE(device_config.operation_mode as u8)
+            .with_STANDBY_EN(device_config.idle_state == IdleState::STANDBY || device_config.low_latency)
+            .with_SEQ_START(seq_start)
+    }
+
+    /// Disable the configured Operation Mode (also stopping haptic feedback)
+    pub async fn disable(&mut self) -> Result<(), Error> {
+        if self.actuator_config.is_none() || self.device_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+        let device_config = self.device_config.unwrap();
+
+        let top_ctl1 = self
+            .cached_top_ctl1()
+            .await?
+            .with_OPERATION_MODE(OperationMode::INACTIVE as u8)
+            .with_SEQ_START(false);
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.enabled = false;
+        self.standby_state = None;
+
+        Ok(())
+    }
+
+    /// TOP_CTL1 as last written by the driver, read from the device if not known.
+    ///
+    /// SEQ_START clears itself when a sequence ends, so callers must set or clear it
+    /// explicitly. The cache is dropped by `configure()` and when `peek_events()` sees
+    /// a fault.
+    async fn cached_top_ctl1(&mut self) -> Result<TOP_CTL1, Error> {
+        if let Some(top_ctl1) = self.top_ctl1_cache {
+            return Ok(top_ctl1);
+        }
+
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
+        self.top_ctl1_cache = Some(top_ctl1);
+        Ok(top_ctl1)
+    }
+
+    /// Choose whether `configure()` and `upload_waveform_memory()` fail with `DeviceBusy`
+    /// (the default) or stop the device first when it is not INACTIVE.
+    pub fn set_busy_policy(&mut self, busy_policy: BusyPolicy) {
+        self.busy_policy = busy_policy;
+    }
+
+    /// The policy set with `set_busy_policy()`.
+    pub fn busy_policy(&self) -> BusyPolicy {
+        self.busy_policy
+    }
+
+    /// Whether TOP_CTL1 has the operation mode INACTIVE and no playback ongoing, the
+    /// precondition for writing the configuration and the waveform memory.
+    fn is_idle(top_ctl1: TOP_CTL1) -> bool {
+        top_ctl1.OPERATION_MODE() == OperationMode::INACTIVE as u8 && !top_ctl1.SEQ_START()
+    }
+
+    /// Make sure the device is INACTIVE according to the busy policy.
+    async fn ensure_idle(&mut self) -> Result<(), Error> {
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
+        if Self::is_idle(top_ctl1) {
+            return Ok(());
+        }
+
+        match self.busy_policy {
+            BusyPolicy::Reject => Err(Error::DeviceBusy),
+            BusyPolicy::Stop => {
+                let top_ctl1 = top_ctl1
+                    .with_OPERATION_MODE(OperationMode::INACTIVE as u8)
+                    .with_SEQ_START(false);
+                self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+                self.enabled = false;
+                self.looping_sequence = None;
+                self.standby_state = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Put the device into its lowest power state between haptic events.
+    ///
+    /// Stops any playback and sets OPERATION_MODE = INACTIVE with STANDBY_EN = 0, so the
+    /// chip drops into the IDLE state (typ. 0.36 uA). The previous TOP_CTL1 value is
+    /// remembered and restored by `wake()`.
+    pub async fn standby(&mut self) -> Result<(), Error> {
+        if self.standby_state.is_some() {
+            return Ok(());
+        }
+
+        let top_ctl1 = self.cached_top_ctl1().await?.with_SEQ_START(false);
+        let idle = top_ctl1
+            .with_OPERATION_MODE(OperationMode::INACTIVE as u8)
+            .with_STANDBY_EN(false);
+        self.write_register(Register::TOP_CTL1, idle.into()).await?;
+
+        self.standby_state = Some(top_ctl1);
+        self.enabled = false;
+
+        Ok(())
+    }
+
+    /// Restore the state saved by `standby()`.
+    ///
+    /// Re-enables the operation mode (and STANDBY_EN setting) that was active before
+    /// entering standby. Does nothing if the device is not in standby.
+    pub async fn wake(&mut self) -> Result<(), Error> {
+        let Some(top_ctl1) = self.standby_state.take() else {
+            return Ok(());
+        };
+
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.enabled = top_ctl1.OPERATION_MODE() != OperationMode::INACTIVE as u8;
+
+        Ok(())
+    }
+
+    /// Whether the device was put into standby by `standby()` and not woken up yet.
+    pub fn is_in_standby(&self) -> bool {
+        self.standby_state.is_some()
+    }
+
+    /// Sets a custom drive waveform, see 5.7.6 Custom Waveform Operation
+    ///
+    /// `points` are the levels of the interpolation points 1 to 3 of every quarter
+    /// period in 1/256 of IMAX (CUSTOM_WAVE_GEN_COEFF1..3, a sine after reset:
+    /// `[0x61, 0xB4, 0xEC]`). Point 0 is always 0 % and point 4 always 100 %.
+    /// The three registers are written in a single transaction.
+    /// Device needs to be in the CUSTOM_WAVEFORM mode.
+    pub async fn set_custom_drive_waveform(&mut self, points: [u8; 3]) -> Result<(), Error> {
+        if self.actuator_config.is_none() || self.device_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+        let device_config = self.device_config.as_ref().unwrap();
+
+        if device_config.driving_mode != DrivingMode::CUSTOM_WAVEFORM {
+            return Err(Error::WrongMode);
+        }
+
+        self.write_registers(Register::SWG_C1, &points).await
+    }
+
+    /// Stream `count` custom drive waveforms produced by `generator`, one every `interval_us`.
+    ///
+    /// The generator is called with the index of the update, so procedural waveforms
+    /// (noise, decaying sine, ...) don't need to be precomputed into a buffer.
+    /// Device needs to be in the CUSTOM_WAVEFORM mode.
+    pub async fn stream_custom_drive_waveform<F, D>(
+        &mut self,
+        count: usize,
+        interval_us: u32,
+        mut generator: F,
+        delay: &mut D,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(usize) -> [u8; 3],
+        D: DelayNs,
+    {
+        for index in 0..count {
+            self.set_custom_drive_waveform(generator(index)).await?;
+            delay.delay_us(interval_us).await;
+        }
+
+        Ok(())
+    }
+
+    async fn read_register(&mut self, register: Register) -> Result<u8, Error> {
+        let mut buffer = [0u8; 1];
+
+        self.count_transfer(1, 1);
+        self.i2c
+            .write_read(self.address, &[register as u8], &mut buffer)
+            .await
             .map_err(|e| Error::I2c(e.kind()))?;
 
         Ok(buffer[0])
     }
 
+    /// Read consecutive registers starting at `start` in a single transaction.
+    async fn read_registers(&mut self, start: Register, buffer: &mut [u8]) -> Result<(), Error> {
+        let chunk_size = self.max_transaction_size.unwrap_or(usize::MAX);
+        for (i, chunk) in buffer.chunks_mut(chunk_size).enumerate() {
+            let address = start as u8 + (i * chunk_size) as u8;
+            self.count_transfer(1, chunk.len());
+            self.i2c
+                .write_read(self.address, &[address], chunk)
+                .await
+                .map_err(|e| Error::I2c(e.kind()))?;
+        }
+        Ok(())
+    }
+
+    /// Write up to 3 consecutive registers starting at `start` in a single transaction,
+    /// split if it exceeds `max_transaction_size()`.
+    async fn write_registers(&mut self, start: Register, data: &[u8]) -> Result<(), Error> {
+        let chunk_size = self.max_transaction_size.map_or(data.len(), |max| max - 1);
+        for (i, chunk) in data.chunks(chunk_size).enumerate() {
+            let mut buffer = [0u8; 4];
+            buffer[0] = start as u8 + (i * chunk_size) as u8;
+            buffer[1..=chunk.len()].copy_from_slice(chunk);
+
+            self.count_transfer(1 + chunk.len(), 0);
+            self.i2c
+                .write(self.address, &buffer[..=chunk.len()])
+                .await
+                .map_err(|e| Error::I2c(e.kind()))?;
+        }
+        Ok(())
+    }
+
+    /// Limit the number of bytes per I2C transfer, `None` for no limit.
+    ///
+    /// For peripherals that cap the transfer length, e.g. the EasyDMA buffer of some
+    /// nRF TWI instances. Burst reads and waveform memory uploads are split
+    /// automatically, writes count the register address byte.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` for limits below 3 bytes, the longest write that
+    /// can't be split.
+    pub fn set_max_transaction_size(&mut self, bytes: Option<usize>) -> Result<(), Error> {
+        if bytes.is_some_and(|bytes| bytes < 3) {
+            return Err(Error::InvalidValue);
+        }
+        self.max_transaction_size = bytes;
+        Ok(())
+    }
+
+    /// The limit set with `set_max_transaction_size()`.
+    pub fn max_transaction_size(&self) -> Option<usize> {
+        self.max_transaction_size
+    }
+
+    /// Count a transfer for `i2c_stats()`.
+    #[cfg(feature = "stats")]
+    fn count_transfer(&mut self, written: usize, read: usize) {
+        self.i2c_stats.record(written, read);
+    }
+
+    #[cfg(not(feature = "stats"))]
+    fn count_transfer(&mut self, _written: usize, _read: usize) {}
+
     async fn write_register(&mut self, register: Register, data: u8) -> Result<(), Error> {
-        self.i2c
-            .write(self.address, &[register as u8, data])
-            .await
-            .map_err(|e| Error::I2c(e.kind()))
+        self.send(Command::write(register, data)).await
     }
 
-    /// Write multiple bytes to consecutive memory addresses starting at SNP_MEM_0.
+    /// Write multiple bytes to consecutive memory addresses starting at SNP_MEM_0 + `start`.
     ///
     /// This is used for uploading waveform memory data.
-    async fn write_memory_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+    async fn write_memory_bytes(&mut self, start: usize, data: &[u8]) -> Result<(), Error> {
+        let chunk_size = self.memory_chunk_size();
+        self.write_memory_chunks(start, data, chunk_size, false).await
+    }
+
+    /// Payload bytes per write of `write_memory_bytes()`.
+    fn memory_chunk_size(&self) -> usize {
         // Write in chunks to avoid buffer overflow
         // Most I2C implementations have limited buffer sizes
         const CHUNK_SIZE: usize = 32;
 
+        CHUNK_SIZE.min(self.max_transaction_size.unwrap_or(usize::MAX) - 1)
+    }
+
+    /// Write `data` in chunks of `chunk_size` (at most `MAX_MEMORY_SIZE`) bytes,
+    /// optionally yielding to the executor after every chunk.
+    async fn write_memory_chunks(
+        &mut self,
+        start: usize,
+        data: &[u8],
+        chunk_size: usize,
+        yield_between: bool,
+    ) -> Result<(), Error> {
+        let chunk_size = chunk_size.min(self.max_transaction_size.unwrap_or(usize::MAX) - 1);
         let mut offset = 0;
         while offset < data.len() {
-            let chunk_end = (offset + CHUNK_SIZE).min(data.len());
+            let chunk_end = (offset + chunk_size).min(data.len());
             let chunk = &data[offset..chunk_end];
 
             // Create buffer with register address followed by data
             // We use a fixed-size buffer since we're no_std
             // Waveform memory starts at SNP_MEM_0 (0x84)
-            let mut buffer = [0u8; CHUNK_SIZE + 1];
-            buffer[0] = Register::SNP_MEM_0 as u8 + offset as u8;
+            let mut buffer = [0u8; waveform::MAX_MEMORY_SIZE + 1];
+            buffer[0] = Register::SNP_MEM_0 as u8 + (start + offset) as u8;
             buffer[1..1 + chunk.len()].copy_from_slice(chunk);
 
+            self.count_transfer(1 + chunk.len(), 0);
             self.i2c
                 .write(self.address, &buffer[..1 + chunk.len()])
                 .await
                 .map_err(|e| Error::I2c(e.kind()))?;
 
             offset = chunk_end;
+            if yield_between && offset < data.len() {
+                YieldNow(false).await;
+            }
         }
 
         Ok(())
@@ -426,11 +1401,27 @@ where
     ///
     /// Must be called before uploading waveform memory.
     /// Per datasheet: WAV_MEM_LOCK = 1 means unlocked, 0 means locked.
+    ///
+    /// The memory may only be written while the operation mode is INACTIVE and no
+    /// playback is ongoing (e.g. after `disable()`), otherwise `WrongMode` is returned.
+    /// Unlocking again is also how to recover from a failed upload after
+    /// `lock_waveform_memory()`, no power cycle is needed.
     pub async fn unlock_waveform_memory(&mut self) -> Result<(), Error> {
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
+        if !Self::is_idle(top_ctl1) {
+            return Err(Error::WrongMode);
+        }
+
         let mem_ctl2 = MEM_CTL2::new().with_WAV_MEM_LOCK(true); // 1 = unlocked
         self.write_register(Register::MEM_CTL2, mem_ctl2.into()).await
     }
 
+    /// Check whether the waveform memory is currently locked against writes.
+    pub async fn is_waveform_memory_locked(&mut self) -> Result<bool, Error> {
+        let mem_ctl2 = MEM_CTL2::from(self.read_register(Register::MEM_CTL2).await?);
+        Ok(!mem_ctl2.WAV_MEM_LOCK()) // 0 = locked
+    }
+
     /// Lock waveform memory to prevent accidental writes.
     ///
     /// Should be called after uploading waveform memory.
@@ -440,33 +1431,195 @@ where
         self.write_register(Register::MEM_CTL2, mem_ctl2.into()).await
     }
 
+    /// Check that `memory` fits into the waveform memory of the variant.
+    fn check_memory_size(&self, memory: &WaveformMemory) -> Result<(), Error> {
+        let size = self.variant.waveform_memory_size();
+        if memory.len() > size {
+            return Err(Error::WaveformMemoryFull {
+                element: WaveformElement::Sequence(memory.num_sequences().saturating_sub(1)),
+                excess_bytes: memory.len() - size,
+            });
+        }
+        Ok(())
+    }
+
     /// Upload waveform memory to the device.
     ///
+    /// With `DeviceConfig::low_latency`, the operation mode is enabled after a locked
+    /// upload, ready for `trigger_armed_sequence()`.
+    ///
     /// # Arguments
     /// * `memory` - The waveform memory to upload
     /// * `lock_after` - Whether to lock memory after upload
     ///
     /// # Errors
-    /// Returns an I2C error if communication fails.
+    /// Returns an I2C error if communication fails, `WaveformMemoryFull` if the image
+    /// is larger than the memory of the variant, and `DeviceBusy` unless the device
+    /// is INACTIVE, see `set_busy_policy()`.
     pub async fn upload_waveform_memory(
         &mut self,
         memory: &WaveformMemory,
         lock_after: bool,
     ) -> Result<(), Error> {
+        self.check_memory_size(memory)?;
+        self.ensure_idle().await?;
+
         // Unlock memory first
         self.unlock_waveform_memory().await?;
 
         // Write the waveform data
-        self.write_memory_bytes(memory.as_bytes()).await?;
+        self.write_memory_bytes(0, memory.as_bytes()).await?;
+        self.uploaded_memory = Some((*memory, lock_after));
 
         // Optionally lock memory
         if lock_after {
             self.lock_waveform_memory().await?;
         }
 
+        self.arm_low_latency().await
+    }
+
+    /// Upload waveform memory in a single I2C write, using `buffer` as scratch space.
+    ///
+    /// The register address and the whole image are assembled in `buffer` and sent as
+    /// one contiguous write, for HALs whose DMA transfers need a single flat buffer.
+    /// With `set_max_transaction_size()`, the image is sent in several writes from
+    /// the same buffer.
+    /// `buffer` must hold at least `memory.len() + 1` bytes,
+    /// `variant().waveform_memory_size() + 1` fits every image.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if `buffer` is too small, otherwise like
+    /// `upload_waveform_memory()`.
+    pub async fn upload_waveform_memory_with_buffer(
+        &mut self,
+        memory: &WaveformMemory,
+        buffer: &mut [u8],
+        lock_after: bool,
+    ) -> Result<(), Error> {
+        let data = memory.as_bytes();
+        if buffer.len() < data.len() + 1 {
+            return Err(Error::InvalidValue);
+        }
+        self.check_memory_size(memory)?;
+
+        self.ensure_idle().await?;
+        self.unlock_waveform_memory().await?;
+
+        // With a transaction size limit, every chunk is preceded by its address,
+        // overwriting the last byte of the chunk before, which was sent already
+        buffer[1..=data.len()].copy_from_slice(data);
+        let chunk_size = self.max_transaction_size.map_or(data.len(), |max| max - 1);
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_end = (offset + chunk_size).min(data.len());
+            buffer[offset] = Register::SNP_MEM_0 as u8 + offset as u8;
+            self.count_transfer(1 + chunk_end - offset, 0);
+            self.i2c
+                .write(self.address, &buffer[offset..=chunk_end])
+                .await
+                .map_err(|e| Error::I2c(e.kind()))?;
+            offset = chunk_end;
+        }
+        self.uploaded_memory = Some((*memory, lock_after));
+
+        if lock_after {
+            self.lock_waveform_memory().await?;
+        }
+
+        self.arm_low_latency().await
+    }
+
+    /// Upload waveform memory in chunks of `chunk_size` bytes, yielding between chunks.
+    ///
+    /// A full image takes around 10 ms at 100 kHz. Yielding to the executor after every
+    /// chunk lets other tasks run, or use a shared bus, in between. The register address
+    /// is sent again with every chunk, so smaller chunks take longer overall.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if `chunk_size` is 0 or larger than the memory,
+    /// otherwise like `upload_waveform_memory()`.
+    pub async fn upload_waveform_memory_chunked(
+        &mut self,
+        memory: &WaveformMemory,
+        chunk_size: usize,
+        lock_after: bool,
+    ) -> Result<(), Error> {
+        if chunk_size == 0 || chunk_size > self.variant.waveform_memory_size() {
+            return Err(Error::InvalidValue);
+        }
+        self.check_memory_size(memory)?;
+
+        self.ensure_idle().await?;
+        self.unlock_waveform_memory().await?;
+
+        self.write_memory_chunks(0, memory.as_bytes(), chunk_size, true).await?;
+        self.uploaded_memory = Some((*memory, lock_after));
+
+        if lock_after {
+            self.lock_waveform_memory().await?;
+        }
+
+        self.arm_low_latency().await
+    }
+
+    /// Overwrite part of the waveform memory, starting `offset` bytes into SNP_MEM.
+    ///
+    /// Updates a single snippet or sequence in place without re-sending the whole image,
+    /// `WaveformMemory::range_of()` gives its offset.
+    /// The memory must have been unlocked with `unlock_waveform_memory()`, and the patch
+    /// must keep the header and end pointers consistent with the data: the patched copy
+    /// of the image uploaded last, restored by `health_check()` and compared by
+    /// `switch_bank()`, has to stay a valid `WaveformMemory`.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if the patch runs past the end of the memory, the error
+    /// of `WaveformMemory::from_bytes()` if it breaks the uploaded image, `WrongMode` if
+    /// the memory is locked and `DeviceBusy` unless the device is INACTIVE, see
+    /// `set_busy_policy()`.
+    pub async fn patch_waveform_memory(&mut self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        let end = offset.checked_add(bytes.len()).ok_or(Error::InvalidValue)?;
+        if end > self.variant.waveform_memory_size() {
+            return Err(Error::InvalidValue);
+        }
+
+        // Check the patched copy first, so a rejected patch leaves the chip untouched
+        let patched = match self.uploaded_memory {
+            Some((memory, _)) => {
+                let mut image = [0u8; waveform::MAX_MEMORY_SIZE];
+                image[..memory.len()].copy_from_slice(memory.as_bytes());
+                image[offset..end].copy_from_slice(bytes);
+                Some(WaveformMemory::from_bytes(&image[..memory.len().max(end)])?)
+            }
+            None => None,
+        };
+
+        self.ensure_idle().await?;
+        if self.is_waveform_memory_locked().await? {
+            return Err(Error::WrongMode);
+        }
+
+        self.write_memory_bytes(offset, bytes).await?;
+
+        // The memory is unlocked while patching, whatever it was after the upload
+        if let Some(memory) = patched {
+            self.uploaded_memory = Some((memory, false));
+        }
+
         Ok(())
     }
 
+    /// Check whether the device holds `memory`, by comparing fingerprints.
+    ///
+    /// Reads `memory.len()` bytes from SNP_MEM in a single burst, so boot code
+    /// can skip `upload_waveform_memory()` when the correct image is already present.
+    pub async fn verify_waveform_memory(&mut self, memory: &WaveformMemory) -> Result<bool, Error> {
+        let mut buffer = [0u8; waveform::MAX_MEMORY_SIZE];
+        let on_chip = &mut buffer[..memory.len()];
+        self.read_registers(Register::SNP_MEM_0, on_chip).await?;
+        Ok(waveform::crc32(on_chip) == memory.fingerprint())
+    }
+
     /// Read back waveform memory contents.
     ///
     /// # Arguments
@@ -480,6 +1633,7 @@ where
         for i in 0..read_len {
             let addr = Register::SNP_MEM_0 as u8 + i as u8;
             let mut data = [0u8; 1];
+            self.count_transfer(1, 1);
             self.i2c
                 .write_read(self.address, &[addr], &mut data)
                 .await
@@ -489,6 +1643,18 @@ where
         Ok(read_len)
     }
 
+    /// Read the waveform memory image from the device in a single transaction.
+    ///
+    /// Edit it with `WaveformMemoryBuilder::from_memory()` and upload it again.
+    ///
+    /// # Errors
+    /// Returns the errors of `WaveformMemory::from_bytes()` if the device holds no valid image.
+    pub async fn read_waveform_memory_image(&mut self) -> Result<WaveformMemory, Error> {
+        let mut buffer = [0u8; waveform::MAX_MEMORY_SIZE];
+        self.read_registers(Register::SNP_MEM_0, &mut buffer).await?;
+        WaveformMemory::from_bytes(&buffer)
+    }
+
     /// Select a sequence for playback.
     ///
     /// # Arguments
@@ -498,25 +1664,62 @@ where
     /// # Errors
     /// Returns `InvalidValue` if parameters are out of range.
     pub async fn select_sequence(&mut self, sequence_id: u8, loops: u8) -> Result<(), Error> {
-        if sequence_id > 15 || loops > 15 {
-            return Err(Error::InvalidValue);
-        }
+        let command = Self::select_sequence_command(sequence_id, loops)?;
+        self.send(command).await?;
+        self.selected_sequence = Some((sequence_id, loops));
+        Ok(())
+    }
+
+    /// The sequence and loop count last selected with `select_sequence()`, if any.
+    pub fn selected_sequence(&self) -> Option<(u8, u8)> {
+        self.selected_sequence
+    }
 
-        let seq_ctl2 = SEQ_CTL2::new()
-            .with_PS_SEQ_ID(sequence_id)
-            .with_PS_SEQ_LOOP(loops);
-        self.write_register(Register::SEQ_CTL2, seq_ctl2.into()).await
+    /// Read back the selected sequence (PS_SEQ_ID) and loop count (PS_SEQ_LOOP).
+    ///
+    /// These are programmed by `select_sequence()` and define what the device
+    /// plays when SEQ_START is set.
+    pub async fn get_selected_sequence(&mut self) -> Result<(u8, u8), Error> {
+        let seq_ctl2 = SEQ_CTL2::from(self.read_register(Register::SEQ_CTL2).await?);
+        Ok((seq_ctl2.PS_SEQ_ID(), seq_ctl2.PS_SEQ_LOOP()))
     }
 
     /// Start playback of the selected sequence.
     ///
     /// The device must be in RTWM_MODE and enabled for this to work.
     pub async fn start_sequence(&mut self) -> Result<(), Error> {
-        let mut top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
-        top_ctl1 = top_ctl1.with_SEQ_START(true);
+        let top_ctl1 = self.cached_top_ctl1().await?.with_SEQ_START(true);
         self.write_register(Register::TOP_CTL1, top_ctl1.into()).await
     }
 
+    /// Start the sequence selected with `select_sequence()` with a single register write.
+    ///
+    /// Unlike `start_sequence()`, TOP_CTL1 is not taken from the cache but derived from the
+    /// configuration, so the device must have been enabled with `enable()`.
+    pub async fn trigger(&mut self) -> Result<(), Error> {
+        let command = self.trigger_command()?;
+        self.send(command).await
+    }
+
+    /// Start the pre-selected sequence with exactly one register write, e.g. from an
+    /// input interrupt.
+    ///
+    /// Nothing is read from the device: the mode has to be enabled with `enable()`
+    /// (or `DeviceConfig::low_latency`) and the sequence selected with `select_sequence()`
+    /// beforehand.
+    ///
+    /// # Errors
+    /// Returns `WrongMode` if the device is not enabled in RTWM_MODE or no sequence
+    /// has been selected.
+    pub async fn trigger_armed_sequence(&mut self) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::RTWM_MODE || self.selected_sequence.is_none() {
+            return Err(Error::WrongMode);
+        }
+
+        self.trigger().await
+    }
+
     /// Select and immediately start playing a sequence.
     ///
     /// This is a convenience method that combines `select_sequence` and `start_sequence`.
@@ -528,4 +1731,271 @@ where
         self.select_sequence(sequence_id, loops).await?;
         self.start_sequence().await
     }
+
+    /// Play a sequence after the current one, or start it if playback already stopped.
+    ///
+    /// While a sequence is playing, this selects the next one and sets SEQ_CONTINUE,
+    /// so the device chains it onto the end of the current one without a gap
+    /// (E_SEQ_CONTINUE is raised). Whether a sequence is still playing is taken from
+    /// IRQ_STATUS1.STA_SEQ_DONE. SEQ_CONTINUE is cleared again by `process_events()`
+    /// once E_SEQ_CONTINUE was seen, or by `stop_sequence()`.
+    pub async fn continue_sequence(&mut self, sequence_id: u8, loops: u8) -> Result<SequenceResume, Error> {
+        let status = IRQ_STATUS1::from(self.read_register(Register::IRQ_STATUS1).await?);
+        if status.STA_SEQ_FAULT() {
+            return Ok(SequenceResume::Blocked);
+        }
+
+        self.select_sequence(sequence_id, loops).await?;
+
+        if status.STA_SEQ_DONE() {
+            self.start_sequence().await?;
+            return Ok(SequenceResume::Restarted);
+        }
+
+        self.set_sequence_continue(true).await?;
+        Ok(SequenceResume::Continued)
+    }
+
+    /// Set SEQ_CONTINUE: after the current sequence, play the one selected in PS_SEQ_ID.
+    ///
+    /// The device keeps chaining sequences while SEQ_CONTINUE is set.
+    pub async fn set_sequence_continue(&mut self, enabled: bool) -> Result<(), Error> {
+        let seq_ctl1 = SEQ_CTL1::from(self.read_register(Register::SEQ_CTL1).await?)
+            .with_SEQ_CONTINUE(enabled);
+        self.write_register(Register::SEQ_CTL1, seq_ctl1.into()).await?;
+        self.sequence_continue = enabled;
+        Ok(())
+    }
+
+    /// Play a sequence until `stop_sequence()` is called.
+    ///
+    /// The sequence is started with the maximum loop count and re-triggered by
+    /// `process_events()` (or `wait_for_event()`) whenever E_SEQ_DONE is seen, so
+    /// E_SEQ_DONE must not be masked and the events need to be processed.
+    pub async fn play_sequence_forever(&mut self, sequence_id: u8) -> Result<(), Error> {
+        self.play_sequence(sequence_id, 15).await?;
+        self.looping_sequence = Some(sequence_id);
+        Ok(())
+    }
+
+    /// Get the sequence started with `play_sequence_forever()`, if it is still looping.
+    pub fn looping_sequence(&self) -> Option<u8> {
+        self.looping_sequence
+    }
+
+    /// Stop the sequence playback by clearing SEQ_START.
+    ///
+    /// This also ends a sequence started with `play_sequence_forever()` and clears
+    /// SEQ_CONTINUE if it was set by `continue_sequence()`.
+    pub async fn stop_sequence(&mut self) -> Result<(), Error> {
+        self.looping_sequence = None;
+        if self.sequence_continue {
+            self.set_sequence_continue(false).await?;
+        }
+        let top_ctl1 = self.cached_top_ctl1().await?.with_SEQ_START(false);
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await
+    }
+}
+
+/// Result of [`DA728x::continue_sequence`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequenceResume {
+    /// A sequence is still playing, the new one follows it seamlessly (SEQ_CONTINUE).
+    Continued,
+    /// Playback had already stopped, the sequence was started again.
+    Restarted,
+    /// A sequence fault is latched, clear it with `clear_events()` first.
+    Blocked,
+}
+
+/// Result of [`DA728x::health_check`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Health {
+    /// The device responds and holds the expected configuration.
+    Healthy,
+    /// Nothing acknowledged the device address.
+    NotResponding,
+    /// CHIP_REV no longer matches the selected variant.
+    VariantMismatch,
+    /// The configuration registers no longer match (the device was likely reset).
+    ConfigurationLost,
+    /// The configuration was lost and has been re-applied.
+    Reconfigured,
+}
+
+/// Future that is pending once, so the executor can run other tasks.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Convert a voltage in mV to the ACTUATOR_NOMMAX / ACTUATOR_ABSMAX scale (23.4 mV steps).
+fn millivolts_to_register(millivolts: u16) -> u8 {
+    ((millivolts as u32 * 1000) / 23400) as u8 // +1?
+}
+
+/// CALIB_V2I value for the actuator impedance at the configured IMAX.
+pub(crate) fn v2i_register(actuator_config: &ActuatorConfig) -> u16 {
+    let imax = milliamps_to_imax(actuator_config.max_current_mA) as u32;
+    ((actuator_config.impedance_mOhm as u32 * 1000 * (imax + 4)) / 1610400) as u16
+}
+
+/// Convert a current in mA to the IMAX scale (7.2 mA steps, 28.6 mA offset).
+fn milliamps_to_imax(milliamps: u16) -> u8 {
+    ((milliamps as u32 * 1000 - 28600) / 7200) as u8 // +1?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, device_config, lra, MockDevice, NoDelay};
+
+    #[test]
+    fn test_stream_custom_drive_waveform() {
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::CUSTOM_WAVEFORM);
+            haptic.configure(lra(), config).await.unwrap();
+            haptic.set_max_transaction_size(Some(3)).unwrap();
+
+            haptic
+                .stream_custom_drive_waveform(2, 1000, |i| [0x10 * (i as u8 + 1), 0x80, 0xFF], &mut NoDelay)
+                .await
+                .unwrap();
+            let device = haptic.release();
+
+            let swg = Register::SWG_C1 as usize;
+            assert_eq!(device.registers[swg..swg + 3], [0x20, 0x80, 0xFF]);
+        });
+    }
+
+    #[test]
+    fn test_sequence_continue_is_cleared() {
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::RTWM_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+            haptic.enable().await.unwrap();
+            let seq_continue = |haptic: &DA728x<&mut MockDevice>| {
+                SEQ_CTL1::from(haptic.i2c.registers[Register::SEQ_CTL1 as usize]).SEQ_CONTINUE()
+            };
+
+            // STA_SEQ_DONE = 0, a sequence is playing
+            assert_eq!(haptic.continue_sequence(1, 0).await.unwrap(), SequenceResume::Continued);
+            assert!(seq_continue(&haptic));
+
+            haptic.i2c.registers[Register::IRQ_EVENT1 as usize] = IRQ_EVENT1::new().with_E_SEQ_CONTINUE(true).into();
+            haptic.process_events(&mut |_| {}).await.unwrap();
+            assert!(!seq_continue(&haptic));
+
+            haptic.continue_sequence(2, 0).await.unwrap();
+            haptic.stop_sequence().await.unwrap();
+            assert!(!seq_continue(&haptic));
+        });
+    }
+
+    #[test]
+    fn test_unlock_requires_inactive_mode() {
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            haptic.enable().await.unwrap();
+            assert!(matches!(haptic.unlock_waveform_memory().await, Err(Error::WrongMode)));
+
+            haptic.disable().await.unwrap();
+            haptic.unlock_waveform_memory().await.unwrap();
+            assert!(!haptic.is_waveform_memory_locked().await.unwrap());
+        });
+    }
+
+    #[test]
+    fn test_low_latency_arms_after_upload() {
+        use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let mut config = device_config(OperationMode::RTWM_MODE, DrivingMode::FREQUENCY_TRACK);
+            config.low_latency = true;
+
+            haptic.configure(lra(), config).await.unwrap();
+            assert!(!haptic.is_enabled());
+
+            let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+            let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+            let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+            let memory = WaveformMemoryBuilder::new(true)
+                .add_snippet(snippet).unwrap()
+                .add_sequence(sequence).unwrap()
+                .build()
+                .unwrap();
+            haptic.upload_waveform_memory(&memory, true).await.unwrap();
+            assert!(haptic.is_enabled());
+
+            haptic.select_sequence(0, 0).await.unwrap();
+            let writes = haptic.i2c.writes;
+            haptic.trigger_armed_sequence().await.unwrap();
+            assert_eq!(haptic.i2c.writes, writes + 1);
+
+            let top_ctl1 = TOP_CTL1::from(haptic.i2c.registers[Register::TOP_CTL1 as usize]);
+            assert_eq!(top_ctl1.OPERATION_MODE(), OperationMode::RTWM_MODE as u8);
+            assert!(top_ctl1.STANDBY_EN());
+            assert!(top_ctl1.SEQ_START());
+        });
+    }
+
+    #[test]
+    fn test_patch_then_switch_bank() {
+        use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::RTWM_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+            let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+            let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+            let memory = WaveformMemoryBuilder::new(true)
+                .add_snippet(snippet).unwrap()
+                .add_sequence(sequence).unwrap()
+                .build()
+                .unwrap();
+            haptic.upload_waveform_memory(&memory, true).await.unwrap();
+            haptic.unlock_waveform_memory().await.unwrap();
+
+            assert!(matches!(haptic.patch_waveform_memory(usize::MAX, &[0]).await, Err(Error::InvalidValue)));
+            // Zero snippets, the patch is rejected and the uploaded image kept
+            assert!(haptic.patch_waveform_memory(0, &[0]).await.is_err());
+            assert_eq!(haptic.uploaded_memory.unwrap().0.as_bytes(), memory.as_bytes());
+
+            let range = memory.range_of(WaveformElement::Snippet(1)).unwrap();
+            haptic.patch_waveform_memory(range.start, &[0x08]).await.unwrap();
+            let (patched, locked) = haptic.uploaded_memory.unwrap();
+            assert_eq!(patched.as_bytes()[range.start], 0x08);
+            assert!(!locked);
+
+            let swap = haptic.switch_bank(&memory).await.unwrap();
+            assert_eq!(swap.bytes_written, 1);
+            assert!(haptic.is_waveform_memory_locked().await.unwrap());
+            assert_eq!(haptic.i2c.registers[Register::SNP_MEM_0 as usize + range.start], memory.as_bytes()[range.start]);
+        });
+    }
 }
@@ -0,0 +1,193 @@
+//! Playing melodies in wideband DRO mode.
+//!
+//! With WIDEBAND driving, `set_frequency()` accepts 25..1024 Hz, which covers the
+//! notes C2 to B5. Each note is played at the given frequency with a short pause
+//! afterwards, so repeated notes remain distinguishable.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::{DrivingMode, OperationMode};
+use crate::errors::Error;
+use crate::DA728x;
+
+/// Silence after each note in `play_melody()`.
+pub const NOTE_GAP_MS: u32 = 50;
+
+/// A pitch in Hz, `Note::REST` is silence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note(pub u16);
+
+#[allow(nonstandard_style)]
+impl Note {
+    pub const REST: Note = Note(0);
+
+    pub const C2: Note = Note(65);
+    pub const CS2: Note = Note(69);
+    pub const D2: Note = Note(73);
+    pub const DS2: Note = Note(78);
+    pub const E2: Note = Note(82);
+    pub const F2: Note = Note(87);
+    pub const FS2: Note = Note(92);
+    pub const G2: Note = Note(98);
+    pub const GS2: Note = Note(104);
+    pub const A2: Note = Note(110);
+    pub const AS2: Note = Note(117);
+    pub const B2: Note = Note(123);
+    pub const C3: Note = Note(131);
+    pub const CS3: Note = Note(139);
+    pub const D3: Note = Note(147);
+    pub const DS3: Note = Note(156);
+    pub const E3: Note = Note(165);
+    pub const F3: Note = Note(175);
+    pub const FS3: Note = Note(185);
+    pub const G3: Note = Note(196);
+    pub const GS3: Note = Note(208);
+    pub const A3: Note = Note(220);
+    pub const AS3: Note = Note(233);
+    pub const B3: Note = Note(247);
+    pub const C4: Note = Note(262);
+    pub const CS4: Note = Note(277);
+    pub const D4: Note = Note(294);
+    pub const DS4: Note = Note(311);
+    pub const E4: Note = Note(330);
+    pub const F4: Note = Note(349);
+    pub const FS4: Note = Note(370);
+    pub const G4: Note = Note(392);
+    pub const GS4: Note = Note(415);
+    pub const A4: Note = Note(440);
+    pub const AS4: Note = Note(466);
+    pub const B4: Note = Note(494);
+    pub const C5: Note = Note(523);
+    pub const CS5: Note = Note(554);
+    pub const D5: Note = Note(587);
+    pub const DS5: Note = Note(622);
+    pub const E5: Note = Note(659);
+    pub const F5: Note = Note(698);
+    pub const FS5: Note = Note(740);
+    pub const G5: Note = Note(784);
+    pub const GS5: Note = Note(831);
+    pub const A5: Note = Note(880);
+    pub const AS5: Note = Note(932);
+    pub const B5: Note = Note(988);
+
+    /// Frequency of the note in Hz.
+    pub fn frequency_hz(&self) -> u16 {
+        self.0
+    }
+
+    pub fn is_rest(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Tempo in beats per minute, with one beat being a quarter note.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tempo {
+    pub bpm: u16,
+}
+
+impl Tempo {
+    pub fn new(bpm: u16) -> Self {
+        Self { bpm }
+    }
+
+    /// Duration of a whole note in ms.
+    pub fn whole(&self) -> u32 {
+        240_000 / self.bpm.max(1) as u32
+    }
+
+    pub fn half(&self) -> u32 {
+        self.whole() / 2
+    }
+
+    pub fn quarter(&self) -> u32 {
+        self.whole() / 4
+    }
+
+    pub fn eighth(&self) -> u32 {
+        self.whole() / 8
+    }
+
+    pub fn sixteenth(&self) -> u32 {
+        self.whole() / 16
+    }
+
+    /// Extend a duration by half, e.g. `tempo.dotted(tempo.quarter())`.
+    pub fn dotted(&self, duration_ms: u32) -> u32 {
+        duration_ms + duration_ms / 2
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a melody of `(note, duration_ms)` pairs at full level.
+    ///
+    /// Requires DRO_MODE with WIDEBAND driving. Every note is followed by
+    /// `NOTE_GAP_MS` of silence. All notes are checked before playback starts,
+    /// including against the protection profile.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let tempo = Tempo::new(140);
+    /// haptic.play_melody(&[(Note::E5, tempo.quarter()), (Note::B4, tempo.eighth())], &mut delay).await?;
+    /// ```
+    pub async fn play_melody<D: DelayNs>(
+        &mut self,
+        melody: &[(Note, u32)],
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::DRO_MODE
+            || device_config.driving_mode != DrivingMode::WIDEBAND
+        {
+            return Err(Error::WrongMode);
+        }
+        if melody
+            .iter()
+            .any(|(note, _)| !note.is_rest() && !(25..1024).contains(&note.frequency_hz()))
+        {
+            return Err(Error::InvalidFrequency);
+        }
+        for &(note, duration_ms) in melody.iter().filter(|(note, _)| !note.is_rest()) {
+            self.check_protection(127, duration_ms)?;
+        }
+
+        for &(note, duration_ms) in melody {
+            if !note.is_rest() {
+                self.set_frequency(note.frequency_hz()).await?;
+                self.set_override_value(127).await?;
+            }
+            delay.delay_ms(duration_ms).await;
+            self.set_override_value(0).await?;
+            delay.delay_ms(NOTE_GAP_MS).await;
+        }
+
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_table() {
+        assert_eq!(Note::A4.frequency_hz(), 440);
+        assert_eq!(Note::A3.frequency_hz(), 220);
+        assert_eq!(Note::E5.frequency_hz(), 659);
+        assert!(Note::REST.is_rest());
+    }
+
+    #[test]
+    fn test_tempo() {
+        let tempo = Tempo::new(120);
+        assert_eq!(tempo.quarter(), 500);
+        assert_eq!(tempo.eighth(), 250);
+        assert_eq!(tempo.dotted(tempo.quarter()), 750);
+    }
+}
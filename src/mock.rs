@@ -0,0 +1,105 @@
+//! Register file standing in for the device in driver tests.
+
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+use crate::config::{ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, IdleState, OperationMode};
+use crate::Variant;
+
+/// A DA728x that stores every write and auto-increments the register address.
+pub(crate) struct MockDevice {
+    pub registers: [u8; 256],
+    /// Write-only transfers.
+    pub writes: usize,
+}
+
+impl MockDevice {
+    pub fn new(variant: Variant) -> Self {
+        let mut registers = [0u8; 256];
+        registers[0] = variant as u8;
+        Self { registers, writes: 0 }
+    }
+}
+
+impl ErrorType for MockDevice {
+    type Error = Infallible;
+}
+
+impl I2c for MockDevice {
+    async fn transaction(&mut self, _address: u8, operations: &mut [Operation<'_>]) -> Result<(), Infallible> {
+        let mut pointer = 0;
+        let mut read = false;
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(bytes) => {
+                    pointer = bytes[0] as usize;
+                    for &byte in &bytes[1..] {
+                        self.registers[pointer] = byte;
+                        pointer += 1;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    read = true;
+                    for byte in buffer.iter_mut() {
+                        *byte = self.registers[pointer];
+                        pointer += 1;
+                    }
+                }
+            }
+        }
+        if !read {
+            self.writes += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A delay that returns right away.
+pub(crate) struct NoDelay;
+
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Poll `future` to completion, the driver never waits on anything but the mock.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// A typical 170 Hz LRA.
+pub(crate) fn lra() -> ActuatorConfig {
+    ActuatorConfig {
+        actuator_type: ActuatorType::LRA,
+        nominal_max_mV: 1800,
+        absolute_max_mV: 2400,
+        max_current_mA: 160,
+        impedance_mOhm: 25_000,
+        frequency_Hz: 170,
+    }
+}
+
+/// Device configuration without acceleration and the optional features.
+pub(crate) fn device_config(operation_mode: OperationMode, driving_mode: DrivingMode) -> DeviceConfig {
+    DeviceConfig {
+        operation_mode,
+        driving_mode,
+        acceleration: false,
+        rapid_stop: false,
+        idle_state: IdleState::IDLE,
+        i2c_timeout: true,
+        amplitude_pid: false,
+        erm_overdrive: None,
+        low_latency: false,
+    }
+}
@@ -0,0 +1,71 @@
+//! Ready-made event monitoring task for embassy (`embassy` feature).
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Sender;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::events::HapticEvent;
+use crate::DA728x;
+
+/// What the monitor does when a fault has been latched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecoveryPolicy {
+    /// Only clear and forward the events.
+    ClearOnly,
+    /// Run `health_check(true)`, which re-applies the configuration and re-enables
+    /// playback if the device lost it.
+    Reconfigure,
+    /// Disable the device until the application enables it again.
+    Disable,
+}
+
+/// Monitor the nIRQ pin, decode and clear the events, apply `policy` on faults
+/// and forward every event to `sender`.
+///
+/// Runs until an I2C or GPIO error occurs. Events are dropped if the channel is full,
+/// so a slow consumer never stalls the interrupt handling.
+///
+/// # Example
+///
+/// ```ignore
+/// static EVENTS: Channel<CriticalSectionRawMutex, HapticEvent, 8> = Channel::new();
+///
+/// #[embassy_executor::task]
+/// async fn haptic_monitor(mut haptic: DA728x<I2c<'static, I2C0, Async>>, mut nirq: Input<'static>) {
+///     let result = monitor(&mut haptic, &mut nirq, RecoveryPolicy::Reconfigure, EVENTS.sender()).await;
+///     error!("haptic monitor stopped: {:?}", result);
+/// }
+/// ```
+pub async fn monitor<I2C, P, M, const N: usize>(
+    haptic: &mut DA728x<I2C>,
+    irq: &mut P,
+    policy: RecoveryPolicy,
+    sender: Sender<'_, M, HapticEvent, N>,
+) -> Result<(), Error>
+where
+    I2C: I2c,
+    P: Wait,
+    M: RawMutex,
+{
+    loop {
+        let events = haptic
+            .wait_for_event(irq, &mut |event| {
+                let _ = sender.try_send(event);
+            })
+            .await?;
+
+        if events.has_fault() {
+            match policy {
+                RecoveryPolicy::ClearOnly => {}
+                RecoveryPolicy::Reconfigure => {
+                    haptic.health_check(true).await?;
+                }
+                RecoveryPolicy::Disable => {
+                    haptic.disable().await?;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Playing on/off patterns and Morse code in DRO mode.
+//!
+//! A pattern is a string of dots (`.`, one unit on), dashes (`-`, three units on),
+//! letter gaps (` `, three units off) and word gaps (`/`, seven units off).
+//! Consecutive dots and dashes are separated by one unit off, following Morse timing.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::DA728x;
+
+/// A single element of a pattern string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternSymbol {
+    Dot,
+    Dash,
+    LetterGap,
+    WordGap,
+}
+
+impl PatternSymbol {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(PatternSymbol::Dot),
+            '-' => Some(PatternSymbol::Dash),
+            ' ' => Some(PatternSymbol::LetterGap),
+            '/' => Some(PatternSymbol::WordGap),
+            _ => None,
+        }
+    }
+
+    /// Length of this symbol in units.
+    pub fn units(&self) -> u32 {
+        match self {
+            PatternSymbol::Dot => 1,
+            PatternSymbol::Dash => 3,
+            PatternSymbol::LetterGap => 3,
+            PatternSymbol::WordGap => 7,
+        }
+    }
+
+    /// Whether the actuator is driven during this symbol.
+    pub fn is_on(&self) -> bool {
+        matches!(self, PatternSymbol::Dot | PatternSymbol::Dash)
+    }
+}
+
+/// Get the Morse code of a letter or digit as a pattern string.
+pub fn morse_code(c: char) -> Option<&'static str> {
+    let code = match c.to_ascii_uppercase() {
+        'A' => ".-",
+        'B' => "-...",
+        'C' => "-.-.",
+        'D' => "-..",
+        'E' => ".",
+        'F' => "..-.",
+        'G' => "--.",
+        'H' => "....",
+        'I' => "..",
+        'J' => ".---",
+        'K' => "-.-",
+        'L' => ".-..",
+        'M' => "--",
+        'N' => "-.",
+        'O' => "---",
+        'P' => ".--.",
+        'Q' => "--.-",
+        'R' => ".-.",
+        'S' => "...",
+        'T' => "-",
+        'U' => "..-",
+        'V' => "...-",
+        'W' => ".--",
+        'X' => "-..-",
+        'Y' => "-.--",
+        'Z' => "--..",
+        '0' => "-----",
+        '1' => ".----",
+        '2' => "..---",
+        '3' => "...--",
+        '4' => "....-",
+        '5' => ".....",
+        '6' => "-....",
+        '7' => "--...",
+        '8' => "---..",
+        '9' => "----.",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Total length of a pattern in units, including the gaps between dots and dashes.
+pub fn pattern_units(pattern: &str) -> Result<u32, Error> {
+    let mut units = 0;
+    let mut previous_on = false;
+
+    for c in pattern.chars() {
+        let symbol = PatternSymbol::from_char(c).ok_or(Error::InvalidValue)?;
+        if symbol.is_on() && previous_on {
+            units += 1;
+        }
+        units += symbol.units();
+        previous_on = symbol.is_on();
+    }
+
+    Ok(units)
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a pattern string (e.g. `"... --- ..."`) in DRO mode.
+    ///
+    /// `level` is the override value used for dots and dashes. The pattern is
+    /// validated against the protection profile before anything is played.
+    pub async fn play_pattern<D: DelayNs>(
+        &mut self,
+        pattern: &str,
+        unit_ms: u32,
+        level: i8,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        self.check_pattern(pattern, unit_ms, level)?;
+        self.play_symbols(pattern, unit_ms, level, delay).await?;
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+
+    /// Validate a pattern and check every dot and dash against the protection profile.
+    fn check_pattern(&self, pattern: &str, unit_ms: u32, level: i8) -> Result<(), Error> {
+        pattern_units(pattern)?;
+        pattern
+            .chars()
+            .filter_map(PatternSymbol::from_char)
+            .filter(PatternSymbol::is_on)
+            .try_for_each(|symbol| self.check_protection(level, symbol.units().saturating_mul(unit_ms)))
+    }
+
+    /// Play a validated pattern.
+    async fn play_symbols<D: DelayNs>(
+        &mut self,
+        pattern: &str,
+        unit_ms: u32,
+        level: i8,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let mut previous_on = false;
+        for c in pattern.chars() {
+            let symbol = PatternSymbol::from_char(c).ok_or(Error::InvalidValue)?;
+            if symbol.is_on() {
+                if previous_on {
+                    delay.delay_ms(unit_ms).await;
+                }
+                self.set_override_value(level).await?;
+                delay.delay_ms(symbol.units().saturating_mul(unit_ms)).await;
+                self.set_override_value(0).await?;
+            } else {
+                delay.delay_ms(symbol.units().saturating_mul(unit_ms)).await;
+            }
+            previous_on = symbol.is_on();
+        }
+
+        Ok(())
+    }
+
+    /// Play text as Morse code in DRO mode.
+    ///
+    /// Only letters, digits and whitespace are supported, anything else is rejected
+    /// with `Error::InvalidValue` before playback starts.
+    pub async fn play_morse<D: DelayNs>(
+        &mut self,
+        text: &str,
+        unit_ms: u32,
+        level: i8,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        for c in text.chars().filter(|c| !c.is_whitespace()) {
+            let code = morse_code(c).ok_or(Error::InvalidValue)?;
+            self.check_pattern(code, unit_ms, level)?;
+        }
+
+        let mut previous_letter = false;
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if previous_letter {
+                    delay.delay_ms(PatternSymbol::WordGap.units().saturating_mul(unit_ms)).await;
+                }
+                previous_letter = false;
+                continue;
+            }
+
+            if previous_letter {
+                delay.delay_ms(PatternSymbol::LetterGap.units().saturating_mul(unit_ms)).await;
+            }
+            self.play_symbols(morse_code(c).unwrap(), unit_ms, level, delay).await?;
+            previous_letter = true;
+        }
+
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_units() {
+        // dot, gap, dash, gap, dot
+        assert_eq!(pattern_units(".-.").unwrap(), 7);
+        // S, letter gap, O
+        assert_eq!(pattern_units("... ---").unwrap(), 5 + 3 + 11);
+        assert_eq!(pattern_units("/").unwrap(), 7);
+        assert!(pattern_units(".x-").is_err());
+    }
+
+    #[test]
+    fn test_check_pattern_long_unit() {
+        use crate::config::{DrivingMode, OperationMode};
+        use crate::mock::{block_on, device_config, lra, MockDevice};
+        use crate::protection::ProtectionProfile;
+        use crate::Variant;
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let mut profile = ProtectionProfile::NONE;
+            profile.max_on_ms = Some(u32::MAX - 1);
+            haptic.set_protection_profile(Some(profile));
+
+            assert!(haptic.check_pattern(".", u32::MAX / 2, 64).is_ok());
+            assert!(matches!(haptic.check_pattern("-", u32::MAX / 2, 64), Err(Error::ProtectionLimit)));
+        });
+    }
+
+    #[test]
+    fn test_morse_code() {
+        assert_eq!(morse_code('s'), Some("..."));
+        assert_eq!(morse_code('O'), Some("---"));
+        assert_eq!(morse_code('0'), Some("-----"));
+        assert_eq!(morse_code('?'), None);
+    }
+}
@@ -0,0 +1,452 @@
+//! Full-device persistence blob.
+//!
+//! `save_profile()` serializes everything needed to bring a unit back up into one
+//! compact blob: the actuator and device configuration, the configuration registers
+//! (calibrated resonant frequency and phase delay, loop tuning, IRQ masks and the
+//! GPI setup, see `SCRIPT_REGISTERS`), the drive reference scaling (master gain,
+//! global strength and unit calibration) and the last uploaded waveform memory. Store
+//! it in an external flash page and restore it at boot with `load_profile()`.
+//!
+//! Layout, multi-byte values little endian:
+//! - `"DA"`, version
+//! - ActuatorConfig (11 bytes), DeviceConfig (6 bytes)
+//! - master gain, global strength, unit calibration in ‰ (u16)
+//! - one value per `SCRIPT_REGISTERS` entry
+//! - waveform memory length (0 if none), locked flag, image
+//! - CRC-32 of everything before it
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::{
+    ActuatorConfig, ActuatorType, DeviceConfig, DrivingMode, ErmOverdrive, IdleState, OperationMode,
+};
+use crate::errors::Error;
+use crate::registers::{Register, SEQ_CTL2, TOP_CTL1};
+use crate::script::SCRIPT_REGISTERS;
+use crate::waveform::{crc32, WaveformMemory, MAX_MEMORY_SIZE};
+use crate::DA728x;
+
+/// Version of the blob layout written by `save_profile()`.
+pub const PROFILE_VERSION: u8 = 2;
+
+const MAGIC: [u8; 2] = *b"DA";
+const HEADER_SIZE: usize = 3;
+const ACTUATOR_CONFIG_SIZE: usize = 11;
+const DEVICE_CONFIG_SIZE: usize = 6;
+const DRIVE_SCALING_SIZE: usize = 4;
+const CRC_SIZE: usize = 4;
+
+/// Size of a profile with a full waveform memory, enough for every blob.
+pub const MAX_PROFILE_SIZE: usize =
+    HEADER_SIZE
+    + ACTUATOR_CONFIG_SIZE
+    + DEVICE_CONFIG_SIZE
+    + DRIVE_SCALING_SIZE
+    + SCRIPT_REGISTERS.len()
+    + 2
+    + MAX_MEMORY_SIZE
+    + CRC_SIZE;
+
+/// The complete setup of a unit, as stored by `save_profile()`.
+#[derive(Debug, Clone, Copy)]
+pub struct Profile {
+    pub actuator_config: ActuatorConfig,
+    pub device_config: DeviceConfig,
+    /// See `DA728x::set_master_gain()`.
+    pub master_gain: u8,
+    /// See `DA728x::set_global_strength()`.
+    pub global_strength: u8,
+    /// See `DA728x::set_unit_calibration()`.
+    pub unit_scale_permille: u16,
+    /// Values of the `SCRIPT_REGISTERS`, in the same order.
+    pub registers: [u8; SCRIPT_REGISTERS.len()],
+    /// Waveform memory image and whether it was locked after the upload.
+    pub waveform_memory: Option<(WaveformMemory, bool)>,
+}
+
+impl Profile {
+    /// Serialize into `buffer`, returning the length of the blob.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if `buffer` is too small, `MAX_PROFILE_SIZE` always fits.
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let memory = self.waveform_memory.as_ref().map_or(&[][..], |(memory, _)| memory.as_bytes());
+        let len = MAX_PROFILE_SIZE - MAX_MEMORY_SIZE + memory.len();
+        if buffer.len() < len {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut writer = Writer { buffer, pos: 0 };
+        writer.put(&MAGIC);
+        writer.put(&[PROFILE_VERSION]);
+        encode_actuator_config(&self.actuator_config, &mut writer);
+        encode_device_config(&self.device_config, &mut writer);
+        writer.put(&[self.master_gain, self.global_strength]);
+        writer.put_u16(self.unit_scale_permille);
+        writer.put(&self.registers);
+        writer.put(&[memory.len() as u8, self.waveform_memory.is_some_and(|(_, locked)| locked) as u8]);
+        writer.put(memory);
+
+        let crc = crc32(&writer.buffer[..writer.pos]);
+        writer.put(&crc.to_le_bytes());
+        Ok(writer.pos)
+    }
+
+    /// Parse a blob written by `encode()`.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if the blob is truncated, corrupted or of another version.
+    pub fn decode(blob: &[u8]) -> Result<Self, Error> {
+        let body_len = blob.len().checked_sub(CRC_SIZE).ok_or(Error::InvalidValue)?;
+        let (body, crc) = blob.split_at(body_len);
+        if crc32(body).to_le_bytes() != crc {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut reader = Reader { blob: body, pos: 0 };
+        if reader.take(2)? != MAGIC || reader.byte()? != PROFILE_VERSION {
+            return Err(Error::InvalidValue);
+        }
+
+        let actuator_config = decode_actuator_config(&mut reader)?;
+        let device_config = decode_device_config(&mut reader)?;
+        let master_gain = reader.byte()?;
+        let global_strength = reader.byte()?;
+        let unit_scale_permille = reader.u16()?;
+        if master_gain > 100 || global_strength > 100 || !(1..=1000).contains(&unit_scale_permille) {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut registers = [0u8; SCRIPT_REGISTERS.len()];
+        registers.copy_from_slice(reader.take(SCRIPT_REGISTERS.len())?);
+
+        let memory_len = reader.byte()? as usize;
+        let locked = reader.byte()? != 0;
+        let waveform_memory = match memory_len {
+            0 => None,
+            len => Some((WaveformMemory::from_bytes(reader.take(len)?)?, locked)),
+        };
+
+        if reader.pos != body.len() {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self {
+            actuator_config,
+            device_config,
+            master_gain,
+            global_strength,
+            unit_scale_permille,
+            registers,
+            waveform_memory,
+        })
+    }
+
+    /// The saved value of `register`, if it is one of the `SCRIPT_REGISTERS`.
+    pub fn register(&self, register: Register) -> Option<u8> {
+        SCRIPT_REGISTERS
+            .iter()
+            .position(|&r| r == register)
+            .map(|index| self.registers[index])
+    }
+}
+
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.buffer[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.put(&value.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    blob: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.blob.get(self.pos..self.pos + len).ok_or(Error::InvalidValue)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+fn encode_actuator_config(config: &ActuatorConfig, writer: &mut Writer) {
+    let actuator_type = match config.actuator_type {
+        ActuatorType::LRA => 0,
+        ActuatorType::ERM_BAR => 1,
+        ActuatorType::ERM_COIN => 2,
+    };
+    writer.put(&[actuator_type]);
+    writer.put_u16(config.nominal_max_mV);
+    writer.put_u16(config.absolute_max_mV);
+    writer.put_u16(config.max_current_mA);
+    writer.put_u16(config.impedance_mOhm);
+    writer.put_u16(config.frequency_Hz);
+}
+
+fn decode_actuator_config(reader: &mut Reader) -> Result<ActuatorConfig, Error> {
+    let actuator_type = match reader.byte()? {
+        0 => ActuatorType::LRA,
+        1 => ActuatorType::ERM_BAR,
+        2 => ActuatorType::ERM_COIN,
+        _ => return Err(Error::InvalidValue),
+    };
+
+    Ok(ActuatorConfig {
+        actuator_type,
+        nominal_max_mV: reader.u16()?,
+        absolute_max_mV: reader.u16()?,
+        max_current_mA: reader.u16()?,
+        impedance_mOhm: reader.u16()?,
+        frequency_Hz: reader.u16()?,
+    })
+}
+
+fn encode_device_config(config: &DeviceConfig, writer: &mut Writer) {
+    let driving_mode = match config.driving_mode {
+        DrivingMode::FREQUENCY_TRACK => 0,
+        DrivingMode::WIDEBAND => 1,
+        DrivingMode::CUSTOM_WAVEFORM => 2,
+    };
+    let idle_state = match config.idle_state {
+        IdleState::IDLE => 0,
+        IdleState::STANDBY => 1,
+    };
+    let flags = config.acceleration as u8
+        | (config.rapid_stop as u8) << 1
+        | (config.i2c_timeout as u8) << 2
+        | (config.amplitude_pid as u8) << 3
+        | (config.low_latency as u8) << 4
+        | (config.erm_overdrive.is_some() as u8) << 5;
+    let overdrive = config.erm_overdrive.unwrap_or(ErmOverdrive { overdrive_ms: 0, brake_ms: 0 });
+
+    writer.put(&[
+        config.operation_mode as u8,
+        driving_mode,
+        idle_state,
+        flags,
+        overdrive.overdrive_ms,
+        overdrive.brake_ms,
+    ]);
+}
+
+fn decode_device_config(reader: &mut Reader) -> Result<DeviceConfig, Error> {
+    let operation_mode = OperationMode::from_register(reader.byte()?).ok_or(Error::InvalidValue)?;
+    let driving_mode = match reader.byte()? {
+        0 => DrivingMode::FREQUENCY_TRACK,
+        1 => DrivingMode::WIDEBAND,
+        2 => DrivingMode::CUSTOM_WAVEFORM,
+        _ => return Err(Error::InvalidValue),
+    };
+    let idle_state = match reader.byte()? {
+        0 => IdleState::IDLE,
+        1 => IdleState::STANDBY,
+        _ => return Err(Error::InvalidValue),
+    };
+    let flags = reader.byte()?;
+    let overdrive = ErmOverdrive {
+        overdrive_ms: reader.byte()?,
+        brake_ms: reader.byte()?,
+    };
+
+    Ok(DeviceConfig {
+        operation_mode,
+        driving_mode,
+        acceleration: flags & 1 != 0,
+        rapid_stop: flags & 1 << 1 != 0,
+        idle_state,
+        i2c_timeout: flags & 1 << 2 != 0,
+        amplitude_pid: flags & 1 << 3 != 0,
+        erm_overdrive: (flags & 1 << 5 != 0).then_some(overdrive),
+        low_latency: flags & 1 << 4 != 0,
+    })
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Serialize the complete setup into `buffer`, returning the length of the blob.
+    ///
+    /// Includes the configuration registers as they are on the device, so calibration
+    /// results and settings made with the other setters are kept, and the waveform
+    /// memory last uploaded through this driver.
+    ///
+    /// # Errors
+    /// Returns `NotConfigured` before `configure()` and `InvalidValue` if `buffer`
+    /// is too small, `MAX_PROFILE_SIZE` always fits.
+    pub async fn save_profile(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let (Some(actuator_config), Some(device_config)) = (self.actuator_config, self.device_config) else {
+            return Err(Error::NotConfigured);
+        };
+
+        let mut registers = [0u8; SCRIPT_REGISTERS.len()];
+        for (value, (_, saved)) in registers.iter_mut().zip(self.export_register_script().await?) {
+            *value = saved;
+        }
+
+        Profile {
+            actuator_config,
+            device_config,
+            master_gain: self.master_gain,
+            global_strength: self.global_strength,
+            unit_scale_permille: self.unit_scale_permille,
+            registers,
+            waveform_memory: self.uploaded_memory,
+        }
+        .encode(buffer)
+    }
+
+    /// Restore a setup saved with `save_profile()`.
+    ///
+    /// Restores the drive reference scaling first, so the saved ACTUATOR_NOMMAX and
+    /// ACTUATOR_ABSMAX match it, then configures the device, replays the saved
+    /// registers, uploads the waveform memory and finally restores the operation mode,
+    /// without starting playback.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` for a corrupted blob and `DeviceBusy` unless the device
+    /// is INACTIVE, see `set_busy_policy()`.
+    pub async fn load_profile(&mut self, blob: &[u8]) -> Result<Profile, Error> {
+        let profile = Profile::decode(blob)?;
+
+        self.ensure_idle().await?;
+        self.master_gain = profile.master_gain;
+        self.global_strength = profile.global_strength;
+        self.unit_scale_permille = profile.unit_scale_permille;
+        self.apply_configuration(profile.actuator_config, profile.device_config).await?;
+
+        for (register, value) in SCRIPT_REGISTERS.into_iter().zip(profile.registers) {
+            if register != Register::TOP_CTL1 {
+                self.write_register(register, value).await?;
+            }
+        }
+
+        if let Some((memory, locked)) = profile.waveform_memory {
+            self.upload_waveform_memory(&memory, locked).await?;
+        }
+
+        let seq_ctl2 = SEQ_CTL2::from(profile.register(Register::SEQ_CTL2).unwrap_or_default());
+        self.selected_sequence = Some((seq_ctl2.PS_SEQ_ID(), seq_ctl2.PS_SEQ_LOOP()));
+
+        let top_ctl1 = TOP_CTL1::from(profile.register(Register::TOP_CTL1).unwrap_or_default()).with_SEQ_START(false);
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.enabled = top_ctl1.OPERATION_MODE() != OperationMode::INACTIVE as u8;
+        self.standby_state = None;
+
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waveform::{FrameBuilder, SequenceBuilder, SnippetBuilder, WaveformMemoryBuilder};
+
+    fn profile() -> Profile {
+        let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+
+        Profile {
+            actuator_config: ActuatorConfig {
+                actuator_type: ActuatorType::ERM_COIN,
+                nominal_max_mV: 2_106,
+                absolute_max_mV: 2_260,
+                max_current_mA: 165,
+                impedance_mOhm: 13_800,
+                frequency_Hz: 170,
+            },
+            device_config: DeviceConfig {
+                operation_mode: OperationMode::RTWM_MODE,
+                driving_mode: DrivingMode::WIDEBAND,
+                acceleration: false,
+                rapid_stop: false,
+                idle_state: IdleState::STANDBY,
+                i2c_timeout: true,
+                amplitude_pid: false,
+                erm_overdrive: Some(ErmOverdrive { overdrive_ms: 20, brake_ms: 30 }),
+                low_latency: true,
+            },
+            master_gain: 80,
+            global_strength: 50,
+            unit_scale_permille: 925,
+            registers: core::array::from_fn(|i| i as u8 * 3),
+            waveform_memory: Some((memory, true)),
+        }
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let mut blob = [0u8; MAX_PROFILE_SIZE];
+        let len = profile().encode(&mut blob).unwrap();
+        let decoded = Profile::decode(&blob[..len]).unwrap();
+
+        let mut again = [0u8; MAX_PROFILE_SIZE];
+        assert_eq!(decoded.encode(&mut again).unwrap(), len);
+        assert_eq!(blob[..len], again[..len]);
+        assert_eq!(decoded.device_config.erm_overdrive, Some(ErmOverdrive { overdrive_ms: 20, brake_ms: 30 }));
+        assert_eq!(decoded.register(Register::TOP_CFG1), Some(3));
+    }
+
+    #[test]
+    fn test_load_profile_restores_drive_scaling() {
+        use crate::mock::{block_on, device_config, lra, MockDevice};
+        use crate::{Health, Variant};
+
+        block_on(async {
+            let mut blob = [0u8; MAX_PROFILE_SIZE];
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+            haptic.set_master_gain(80).await.unwrap();
+            haptic.set_global_strength(50).await.unwrap();
+            haptic.set_unit_calibration(925).await.unwrap();
+            let len = haptic.save_profile(&mut blob).await.unwrap();
+
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            haptic.load_profile(&blob[..len]).await.unwrap();
+            assert_eq!(haptic.master_gain(), 80);
+            assert_eq!(haptic.global_strength(), 50);
+            assert_eq!(haptic.unit_calibration(), 925);
+            assert_eq!(haptic.health_check(false).await.unwrap(), Health::Healthy);
+        });
+    }
+
+    #[test]
+    fn test_profile_rejects_corruption() {
+        let mut blob = [0u8; MAX_PROFILE_SIZE];
+        let len = profile().encode(&mut blob).unwrap();
+
+        blob[5] ^= 1;
+        assert!(matches!(Profile::decode(&blob[..len]), Err(Error::InvalidValue)));
+        assert!(matches!(Profile::decode(&blob[..3]), Err(Error::InvalidValue)));
+        assert!(matches!(profile().encode(&mut [0u8; 16]), Err(Error::InvalidValue)));
+    }
+}
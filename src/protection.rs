@@ -0,0 +1,173 @@
+//! Per-actuator protection profiles enforced by the DRO playback APIs.
+//!
+//! Besides the electrical limits in `ActuatorConfig`, many actuators have timing
+//! ratings: how long they may be driven continuously, how strong long effects may
+//! be and how long they have to rest afterwards. A [`ProtectionProfile`] set with
+//! `DA728x::set_protection_profile()` is checked by `play_pattern()`, `play_morse()`,
+//! `play_melody()`, `play_texture()`, `rumble()`, `play_haptic_plan()` and the
+//! `PlayLevel` effect before anything is played, and they rest for `rest_ms` after
+//! the drive stops. `crossfade_to()` and `drive_erm()` check their ramps and pulses,
+//! `self_test()` its test pulse,
+//! raw `set_override_value()` calls are not checked.
+//!
+//! Sequences from the waveform memory are played by the device on its own, so their
+//! timing is not checked, see `Sequence::duration_us()` to check them up front.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::DA728x;
+
+/// Maximum number of amplitude limits per profile.
+pub const MAX_AMPLITUDE_LIMITS: usize = 4;
+
+/// Limit the level of drives lasting at least `min_duration_ms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmplitudeLimit {
+    pub min_duration_ms: u32,
+    /// Maximum absolute override value (0..=127).
+    pub max_level: u8,
+}
+
+/// Timing ratings of an actuator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtectionProfile {
+    /// Maximum continuous on-time in ms, `None` for no limit.
+    pub max_on_ms: Option<u32>,
+    /// Level limits depending on the on-time, all matching limits apply.
+    pub amplitude_limits: [Option<AmplitudeLimit>; MAX_AMPLITUDE_LIMITS],
+    /// Rest after every effect in ms.
+    pub rest_ms: u32,
+}
+
+impl ProtectionProfile {
+    /// No limits.
+    pub const NONE: Self = Self {
+        max_on_ms: None,
+        amplitude_limits: [None; MAX_AMPLITUDE_LIMITS],
+        rest_ms: 0,
+    };
+
+    /// Check a continuous drive at `level` for `on_ms`.
+    ///
+    /// # Errors
+    /// Returns `ProtectionLimit` if the drive is too long or too strong.
+    pub fn check(&self, level: i8, on_ms: u32) -> Result<(), Error> {
+        if let Some(max_on_ms) = self.max_on_ms
+            && on_ms > max_on_ms
+        {
+            return Err(Error::ProtectionLimit);
+        }
+
+        let level = level.unsigned_abs();
+        if self
+            .amplitude_limits
+            .iter()
+            .flatten()
+            .any(|limit| on_ms >= limit.min_duration_ms && level > limit.max_level)
+        {
+            return Err(Error::ProtectionLimit);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ProtectionProfile {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Set the protection profile of the actuator, `None` to disable the checks.
+    pub fn set_protection_profile(&mut self, profile: Option<ProtectionProfile>) {
+        self.protection_profile = profile;
+    }
+
+    /// The profile set with `set_protection_profile()`.
+    pub fn protection_profile(&self) -> Option<&ProtectionProfile> {
+        self.protection_profile.as_ref()
+    }
+
+    /// Check a continuous drive at `level` for `on_ms` against the protection profile.
+    ///
+    /// The level is scaled by the droop compensation and clamped to `effective_max_level()`
+    /// first, as `set_override_value()` does, so the check applies to the level driven.
+    pub(crate) fn check_protection(&self, level: i8, on_ms: u32) -> Result<(), Error> {
+        let Some(profile) = self.protection_profile else {
+            return Ok(());
+        };
+
+        profile.check(self.compensated_level(level), on_ms)
+    }
+
+    /// Wait for the rest period of the protection profile after an effect.
+    pub(crate) async fn protection_rest<D: DelayNs>(&self, delay: &mut D) {
+        if let Some(profile) = self.protection_profile
+            && profile.rest_ms > 0
+        {
+            delay.delay_ms(profile.rest_ms).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_check() {
+        let profile = ProtectionProfile {
+            max_on_ms: Some(2_000),
+            amplitude_limits: [
+                Some(AmplitudeLimit { min_duration_ms: 500, max_level: 100 }),
+                Some(AmplitudeLimit { min_duration_ms: 1_000, max_level: 64 }),
+                None,
+                None,
+            ],
+            rest_ms: 100,
+        };
+
+        assert!(profile.check(127, 499).is_ok());
+        assert!(matches!(profile.check(127, 500), Err(Error::ProtectionLimit)));
+        assert!(profile.check(-100, 999).is_ok());
+        assert!(matches!(profile.check(-100, 1_000), Err(Error::ProtectionLimit)));
+        assert!(profile.check(64, 2_000).is_ok());
+        assert!(matches!(profile.check(10, 2_001), Err(Error::ProtectionLimit)));
+        assert!(ProtectionProfile::NONE.check(127, u32::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_protection_checks_compensated_level() {
+        use crate::config::{DrivingMode, OperationMode};
+        use crate::mock::{block_on, device_config, lra, MockDevice};
+        use crate::registers::Register;
+        use crate::Variant;
+
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::DRO_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let mut profile = ProtectionProfile::NONE;
+            profile.amplitude_limits[0] = Some(AmplitudeLimit { min_duration_ms: 0, max_level: 100 });
+            haptic.set_protection_profile(Some(profile));
+
+            // Boosted by the droop compensation
+            haptic.droop_scale_permille = 1100;
+            assert!(matches!(haptic.check_protection(100, 10), Err(Error::ProtectionLimit)));
+            assert!(haptic.check_protection(90, 10).is_ok());
+
+            // The requested level is kept, the boosted one is driven
+            assert_eq!(haptic.set_override_value(90).await.unwrap(), 99);
+            assert_eq!(haptic.override_value, 90);
+            assert_eq!(haptic.i2c.registers[Register::TOP_CTL2 as usize], 99);
+        });
+    }
+}
@@ -0,0 +1,195 @@
+//! PWM mode duty cycle to drive level mapping (5.2.5 Pulse Width Modulation Mode).
+//!
+//! With Active Acceleration enabled, 0 % to 100 % duty cycle maps to 0 % to 100 % of
+//! ACTUATOR_NOMMAX. Without, 0 % to 100 % duty cycle maps to -100 % to 100 % of
+//! ACTUATOR_ABSMAX, with 50 % duty cycle being zero drive. Duty cycles within the
+//! full-brake threshold of the zero point are interpreted as zero drive. A lost PWM
+//! signal raises a PWM fault and stops the drive.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::errors::Error;
+use crate::events::{Events, HapticEvent, SeqFaultKind};
+use crate::registers::{Register, IRQ_EVENT1, TOP_CFG1, TOP_CFG2};
+use crate::DA728x;
+
+/// ACTUATOR_NOMMAX / ACTUATOR_ABSMAX step size in µV.
+const MICROVOLTS_PER_STEP: u32 = 23400;
+
+/// How the PWM input duty cycle maps to the output drive level.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PwmMapping {
+    /// Drive level is signed (Active Acceleration disabled), 50 % duty cycle is zero drive.
+    pub signed: bool,
+    /// Output voltage at 100 % drive level.
+    pub full_scale_mV: u16,
+    /// FULL_BRAKE_THR (0..=15) in 6.66 % steps.
+    pub full_brake_threshold: u8,
+}
+
+impl PwmMapping {
+    /// Duty cycle (in ‰) that results in zero drive.
+    pub fn zero_duty_permille(&self) -> u16 {
+        if self.signed { 500 } else { 0 }
+    }
+
+    /// Width of the full-brake region in ‰ duty cycle.
+    fn brake_permille(&self) -> u16 {
+        self.full_brake_threshold as u16 * 1000 / 15
+    }
+
+    /// Drive level in % (-100..=100) for a duty cycle in ‰ (0..=1000).
+    pub fn level_for_duty(&self, duty_permille: u16) -> i8 {
+        let duty = duty_permille.min(1000) as i32;
+        let zero = self.zero_duty_permille() as i32;
+
+        if (duty - zero).unsigned_abs() < self.brake_permille() as u32 {
+            return 0;
+        }
+
+        if self.signed {
+            ((duty - zero) / 5) as i8
+        } else {
+            (duty / 10) as i8
+        }
+    }
+
+    /// Duty cycle in ‰ for a drive level in % (-100..=100, negative only if `signed`).
+    ///
+    /// Returns `InvalidValue` if the level is out of range or falls into the full-brake region.
+    pub fn duty_for_level(&self, level_percent: i8) -> Result<u16, Error> {
+        if !(-100..=100).contains(&level_percent) || (!self.signed && level_percent < 0) {
+            return Err(Error::InvalidValue);
+        }
+
+        let duty = if self.signed {
+            (500 + level_percent as i32 * 5) as u16
+        } else {
+            level_percent as u16 * 10
+        };
+
+        if level_percent != 0 && self.level_for_duty(duty) == 0 {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(duty)
+    }
+
+    /// Output voltage in mV for a duty cycle in ‰, negative for reversed drive.
+    pub fn millivolts_for_duty(&self, duty_permille: u16) -> i32 {
+        self.level_for_duty(duty_permille) as i32 * self.full_scale_mV as i32 / 100
+    }
+
+    /// Duty cycle in ‰ to reach the given output voltage (rounded down to whole %).
+    pub fn duty_for_millivolts(&self, millivolts: i32) -> Result<u16, Error> {
+        if self.full_scale_mV == 0 {
+            return Err(Error::InvalidValue);
+        }
+
+        let level = millivolts * 100 / self.full_scale_mV as i32;
+        if !(-100..=100).contains(&level) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.duty_for_level(level as i8)
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Read the current PWM duty cycle mapping from the device.
+    pub async fn get_pwm_mapping(&mut self) -> Result<PwmMapping, Error> {
+        let top_cfg1 = TOP_CFG1::from(self.read_register(Register::TOP_CFG1).await?);
+        let top_cfg2 = TOP_CFG2::from(self.read_register(Register::TOP_CFG2).await?);
+
+        let reference = if top_cfg1.ACCELERATION_EN() {
+            Register::ACTUATOR1
+        } else {
+            Register::ACTUATOR2
+        };
+        let full_scale = self.read_register(reference).await? as u32 * MICROVOLTS_PER_STEP / 1000;
+
+        Ok(PwmMapping {
+            signed: !top_cfg1.ACCELERATION_EN(),
+            full_scale_mV: full_scale as u16,
+            full_brake_threshold: top_cfg2.FULL_BRAKE_THR(),
+        })
+    }
+
+    /// Handle a lost PWM input signal.
+    ///
+    /// The timeout is not configurable: when the PWM input stops, the device raises
+    /// E_SEQ_FAULT / E_PWM_FAULT and returns to IDLE (zero drive), so a crashed host
+    /// cannot leave the actuator running. This clears the fault and then either
+    /// re-enables PWM mode (`resume`, drive continues once the signal is back) or
+    /// disables the device.
+    ///
+    /// Returns `false` if no PWM timeout was latched.
+    pub async fn handle_pwm_timeout(&mut self, resume: bool) -> Result<bool, Error> {
+        let events = Events::from(self.peek_events().await?);
+        if !events.contains(HapticEvent::SeqFault(SeqFaultKind::Pwm)) {
+            return Ok(false);
+        }
+
+        self.clear_events(IRQ_EVENT1::new().with_E_SEQ_FAULT(true)).await?;
+
+        if resume && self.enabled {
+            self.enable().await?;
+        } else {
+            self.disable().await?;
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsigned_mapping() {
+        let mapping = PwmMapping {
+            signed: false,
+            full_scale_mV: 2000,
+            full_brake_threshold: 0,
+        };
+        assert_eq!(mapping.zero_duty_permille(), 0);
+        assert_eq!(mapping.level_for_duty(500), 50);
+        assert_eq!(mapping.duty_for_level(100).unwrap(), 1000);
+        assert!(mapping.duty_for_level(-10).is_err());
+        assert_eq!(mapping.millivolts_for_duty(250), 500);
+    }
+
+    #[test]
+    fn test_signed_mapping() {
+        let mapping = PwmMapping {
+            signed: true,
+            full_scale_mV: 3000,
+            full_brake_threshold: 0,
+        };
+        assert_eq!(mapping.zero_duty_permille(), 500);
+        assert_eq!(mapping.level_for_duty(0), -100);
+        assert_eq!(mapping.level_for_duty(500), 0);
+        assert_eq!(mapping.level_for_duty(1000), 100);
+        assert_eq!(mapping.duty_for_level(-50).unwrap(), 250);
+        assert_eq!(mapping.duty_for_millivolts(1500).unwrap(), 750);
+    }
+
+    #[test]
+    fn test_full_brake_threshold() {
+        // 1 step = 6.66 %
+        let mapping = PwmMapping {
+            signed: false,
+            full_scale_mV: 2000,
+            full_brake_threshold: 1,
+        };
+        assert_eq!(mapping.level_for_duty(50), 0);
+        assert_eq!(mapping.level_for_duty(70), 7);
+        assert!(mapping.duty_for_level(5).is_err());
+        assert_eq!(mapping.duty_for_level(0).unwrap(), 0);
+    }
+}
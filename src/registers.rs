@@ -6,7 +6,7 @@ use bitfield_struct::bitfield;
 /// Register addresses for DA728x devices
 #[allow(dead_code)]
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Register {
     CHIP_REV = 0x00,
     IRQ_EVENT1 = 0x03,
@@ -14,6 +14,7 @@ pub enum Register {
     IRQ_EVENT_SEQ_DIAG = 0x05,
     IRQ_STATUS1 = 0x06,
     IRQ_MASK1 = 0x07,
+    CIF_I2C1 = 0x08,
     FRQ_LRA_PER_H = 0x0A,
     FRQ_LRA_PER_L = 0x0B,
     ACTUATOR1 = 0x0C,
@@ -25,6 +26,11 @@ pub enum Register {
     TOP_CFG2 = 0x14,
     TOP_CFG4 = 0x16,
     TOP_INT_CFG1 = 0x17,
+    TOP_INT_CFG6_H = 0x1C,
+    TOP_INT_CFG6_L = 0x1D,
+    TOP_INT_CFG7_H = 0x1E,
+    TOP_INT_CFG7_L = 0x1F,
+    TOP_INT_CFG8 = 0x20,
     TOP_CTL1 = 0x22,
     TOP_CTL2 = 0x23,
     SEQ_CTL1 = 0x24,
@@ -37,9 +43,18 @@ pub enum Register {
     GPI_2_CTL = 0x2B,
     MEM_CTL1 = 0x2C,
     MEM_CTL2 = 0x2D,
+    ADC_DATA_H1 = 0x2E,
+    ADC_DATA_L1 = 0x2F,
     POLARITY = 0x43,
+    LRA_AVR_H = 0x44,
+    LRA_AVR_L = 0x45,
+    FRQ_LRA_PER_ACT_H = 0x46,
+    FRQ_LRA_PER_ACT_L = 0x47,
     FRQ_PHASE_H = 0x48,
     FRQ_PHASE_L = 0x49,
+    FRQ_CTL = 0x4C,
+    TRIM3 = 0x5F,
+    TRIM4 = 0x60,
     TOP_CFG5 = 0x6E,
     IRQ_MASK2 = 0x83,
     SNP_MEM_0 = 0x84,
@@ -123,6 +138,17 @@ pub struct IRQ_MASK1 {
     pub OC_M: bool,
 }
 
+/// CIF_I2C1 register (0x08)
+#[bitfield(u8)]
+pub struct CIF_I2C1 {
+    #[bits(6)]
+    __: u8,
+    /// I2C timeout: the interface resets if SCL has no falling edge for ~35 ms.
+    pub I2C_TO_ENABLE: bool,
+    /// 0 = auto-increment writes, 1 = repeated (addr, data) pairs.
+    pub I2C_WR_MODE: bool,
+}
+
 /// FRQ_LRA_PER_H register (0x0A)
 #[bitfield(u8)]
 pub struct FRQ_LRA_PER_H {
@@ -213,6 +239,17 @@ pub struct TOP_INT_CFG1 {
     #[bits(2)]
     pub BEMF_FAULT_LIM: u8,
     #[bits(6)]
+    pub FRQ_LOCKED_LIM: u8,
+}
+
+/// TOP_INT_CFG8 register (0x20)
+#[bitfield(u8)]
+pub struct TOP_INT_CFG8 {
+    #[bits(4)]
+    pub FRQ_TRACK_BEMF_LIM: u8,
+    #[bits(3)]
+    pub RAPID_STOP_LIM: u8,
+    #[bits(1)]
     __: u8,
 }
 
@@ -231,7 +268,7 @@ pub struct TOP_CTL1 {
 /// TOP_CTL2 register (0x23)
 #[bitfield(u8)]
 pub struct TOP_CTL2 {
-    OVERRIDE_VAL: u8
+    pub OVERRIDE_VAL: u8
 }
 
 /// SEQ_CTL1 register (0x24)
@@ -317,6 +354,39 @@ pub struct FRQ_PHASE_L {
     pub DELAY_FREEZE: bool
 }
 
+/// FRQ_CTL register (0x4C)
+#[bitfield(u8)]
+pub struct FRQ_CTL {
+    pub FREQ_TRACKING_FORCE_ON: bool,
+    pub FREQ_TRACKING_AUTO_ADJ: bool,
+    #[bits(6)]
+    __: u8,
+}
+
+/// TRIM3 register (0x5F)
+#[bitfield(u8)]
+pub struct TRIM3 {
+    #[bits(3)]
+    __: u8,
+    #[bits(2)]
+    pub REF_UVLO_THRES: u8,
+    pub LOOP_FILT_LOW_BW: bool,
+    pub LOOP_IDAC_DOUBLE_RANGE: bool,
+    #[bits(1)]
+    __: u8,
+}
+
+/// TRIM4 register (0x60)
+#[bitfield(u8)]
+pub struct TRIM4 {
+    #[bits(2)]
+    pub LOOP_FILT_RES_TRIM: u8,
+    #[bits(2)]
+    pub LOOP_FILT_CAP_TRIM: u8,
+    #[bits(4)]
+    __: u8,
+}
+
 /// TOP_CFG5 register (0x6E)
 #[bitfield(u8)]
 pub struct TOP_CFG5 {
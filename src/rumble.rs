@@ -0,0 +1,161 @@
+//! Game-controller style rumble on a single LRA.
+//!
+//! Classic rumble uses two eccentric motors: a heavy low-frequency one and a light
+//! high-frequency one, each driven with a 0..=255 strength. This emulates both in
+//! DRO mode with WIDEBAND driving by switching between two resonance frequencies,
+//! giving each active channel a time slot proportional to its strength.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::{DrivingMode, FrequencyUpdate, OperationMode};
+use crate::errors::Error;
+use crate::DA728x;
+
+/// Strength of the two rumble motors, 0 is off and 255 is full strength.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rumble {
+    pub low: u8,
+    pub high: u8,
+}
+
+/// One time slot of the multiplexed rumble: drive `level` at `frequency_Hz` for `duration_ms`.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleSlot {
+    pub frequency_Hz: u16,
+    pub level: i8,
+    pub duration_ms: u32,
+}
+
+/// Frequencies and timing used to emulate the two rumble motors.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleConfig {
+    /// Frequency emulating the heavy motor, within the WIDEBAND range (25..1024 Hz).
+    pub low_frequency_Hz: u16,
+    /// Frequency emulating the light motor, within the WIDEBAND range (25..1024 Hz).
+    pub high_frequency_Hz: u16,
+    /// Length of one multiplexing period containing both slots.
+    pub period_ms: u32,
+}
+
+impl RumbleConfig {
+    pub const DEFAULT: RumbleConfig = RumbleConfig {
+        low_frequency_Hz: 60,
+        high_frequency_Hz: 320,
+        period_ms: 40,
+    };
+
+    /// Split one multiplexing period into the slots for the given rumble.
+    ///
+    /// Each channel's level is its strength scaled to 0..=127. The period is shared
+    /// in proportion to the strengths, so a lone channel gets the whole period.
+    pub fn slots(&self, rumble: Rumble) -> [Option<RumbleSlot>; 2] {
+        let total = rumble.low as u32 + rumble.high as u32;
+        if total == 0 {
+            return [None, None];
+        }
+
+        let low_ms = self.period_ms * rumble.low as u32 / total;
+        let high_ms = self.period_ms - low_ms;
+
+        let slot = |frequency: u16, strength: u8, duration_ms: u32| {
+            (strength > 0 && duration_ms > 0).then_some(RumbleSlot {
+                frequency_Hz: frequency,
+                level: (strength as u16 * 127 / 255) as i8,
+                duration_ms,
+            })
+        };
+
+        [
+            slot(self.low_frequency_Hz, rumble.low, low_ms),
+            slot(self.high_frequency_Hz, rumble.high, high_ms),
+        ]
+    }
+}
+
+impl Default for RumbleConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a rumble for `duration_ms`, then stop the drive.
+    ///
+    /// Requires DRO_MODE with WIDEBAND driving and an enabled device. The resonant
+    /// frequency is changed every slot, call `set_frequency()` afterwards to restore it.
+    pub async fn rumble<D: DelayNs>(
+        &mut self,
+        rumble: Rumble,
+        config: &RumbleConfig,
+        duration_ms: u32,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::DRO_MODE
+            || device_config.driving_mode != DrivingMode::WIDEBAND
+            || !self.enabled
+        {
+            return Err(Error::WrongMode);
+        }
+        if config.period_ms == 0 {
+            return Err(Error::InvalidValue);
+        }
+
+        let slots = config.slots(rumble);
+        if let Some(level) = slots.iter().flatten().map(|slot| slot.level).max_by_key(|level| level.unsigned_abs()) {
+            self.check_protection(level, duration_ms)?;
+        }
+        let mut elapsed_ms = 0;
+
+        while elapsed_ms < duration_ms && slots.iter().any(Option::is_some) {
+            for slot in slots.iter().flatten() {
+                let slot_ms = slot.duration_ms.min(duration_ms - elapsed_ms);
+                if slot_ms == 0 {
+                    break;
+                }
+                self.set_frequency_with(slot.frequency_Hz, FrequencyUpdate::Burst).await?;
+                self.set_override_value(slot.level).await?;
+                delay.delay_ms(slot_ms).await;
+                elapsed_ms += slot_ms;
+            }
+        }
+
+        self.set_override_value(0).await?;
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_shared_period() {
+        let config = RumbleConfig::DEFAULT;
+        let [low, high] = config.slots(Rumble { low: 255, high: 85 });
+        let low = low.unwrap();
+        let high = high.unwrap();
+        assert_eq!(low.frequency_Hz, 60);
+        assert_eq!(low.level, 127);
+        assert_eq!(low.duration_ms, 30);
+        assert_eq!(high.frequency_Hz, 320);
+        assert_eq!(high.level, 42);
+        assert_eq!(high.duration_ms, 10);
+    }
+
+    #[test]
+    fn test_slots_single_channel() {
+        let config = RumbleConfig::DEFAULT;
+        let [low, high] = config.slots(Rumble { low: 0, high: 128 });
+        assert!(low.is_none());
+        assert_eq!(high.unwrap().duration_ms, 40);
+        assert_eq!(config.slots(Rumble::default()), [None, None]);
+    }
+}
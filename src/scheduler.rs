@@ -0,0 +1,194 @@
+//! Low-power pulse scheduling with coalesced I2C traffic.
+//!
+//! Wearables spend most of their time between effects, so every bus wake-up counts.
+//! The scheduler plays a list of pulses (waveform memory sequences) in RTWM mode
+//! with one bus wake-up per pulse: the sequence (PS_SEQ_ID / PS_SEQ_LOOP) is selected
+//! if it differs from the one selected last, and the pulse is triggered with a single
+//! TOP_CTL1 write derived from the configuration, both in the same wake-up. RTWM
+//! sequences are selected while the device is idle (5.6.5.4), so a pulse should be
+//! over before the next one is due. After each pulse the chip drops back into its
+//! configured idle state on its own (see `DeviceConfig::idle_state`), so no writes
+//! are needed to put it to sleep and the MCU can wait in `DelayNs` until the next
+//! pulse is due.
+//!
+//! The scheduler counts the transactions it issues, see [`SchedulerStats`].
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::DA728x;
+
+/// A waveform memory sequence played after waiting `delay_ms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pulse {
+    /// Sequence ID (0-15).
+    pub sequence_id: u8,
+    /// Number of times to loop (0-15, where 0 means play once).
+    pub loops: u8,
+    /// Time to wait before this pulse, counted from the previous pulse's trigger.
+    /// Should cover the previous pulse, which must be over before this one is selected.
+    pub delay_ms: u32,
+}
+
+/// Bus activity of a [`PulseScheduler`], for power budgeting.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SchedulerStats {
+    /// Pulses triggered.
+    pub pulses: u32,
+    /// Times the bus was used after waiting, one per pulse.
+    pub wakeups: u32,
+    /// I2C transactions issued, each a single register write.
+    pub transactions: u32,
+}
+
+/// Plays pulses with as few I2C transactions as possible.
+///
+/// The scheduler remembers which sequence is selected, so it assumes exclusive use
+/// of SEQ_CTL2. Call `invalidate()` after selecting sequences by other means.
+#[derive(Debug, Clone, Default)]
+pub struct PulseScheduler {
+    armed: Option<(u8, u8)>,
+    stats: SchedulerStats,
+}
+
+impl PulseScheduler {
+    pub const fn new() -> Self {
+        Self {
+            armed: None,
+            stats: SchedulerStats {
+                pulses: 0,
+                wakeups: 0,
+                transactions: 0,
+            },
+        }
+    }
+
+    /// Bus activity since creation or the last `reset_stats()`.
+    pub fn stats(&self) -> SchedulerStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = SchedulerStats::default();
+    }
+
+    /// Forget the selected sequence, e.g. after `select_sequence()` was called directly.
+    pub fn invalidate(&mut self) {
+        self.armed = None;
+    }
+
+    /// Bus activity `run()` would cause for `pulses`, without touching the device.
+    pub fn estimate(&self, pulses: &[Pulse]) -> SchedulerStats {
+        let mut scheduler = Self {
+            armed: self.armed,
+            stats: SchedulerStats::default(),
+        };
+        for pulse in pulses {
+            scheduler.plan(pulse);
+        }
+        scheduler.stats
+    }
+
+    /// Account for `pulse` in the stats, returning whether its sequence has to be
+    /// selected before the trigger.
+    fn plan(&mut self, pulse: &Pulse) -> bool {
+        let target = (pulse.sequence_id, pulse.loops);
+        let select = self.armed != Some(target);
+
+        self.armed = Some(target);
+        self.stats.pulses += 1;
+        self.stats.wakeups += 1;
+        self.stats.transactions += 1 + select as u32;
+
+        select
+    }
+
+    /// Play `pulses` in order, waiting `delay_ms` before each of them.
+    ///
+    /// The device must be configured for RTWM_MODE and enabled.
+    ///
+    /// # Errors
+    /// Returns `WrongMode` if the device is not in RTWM_MODE or not enabled, and
+    /// `InvalidValue` if a sequence ID or loop count is out of range.
+    pub async fn run<I2C, D>(
+        &mut self,
+        haptics: &mut DA728x<I2C>,
+        pulses: &[Pulse],
+        delay: &mut D,
+    ) -> Result<(), Error>
+    where
+        I2C: I2c,
+        D: DelayNs,
+    {
+        let device_config = haptics.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::RTWM_MODE || !haptics.enabled {
+            return Err(Error::WrongMode);
+        }
+        if pulses.iter().any(|pulse| pulse.sequence_id > 15 || pulse.loops > 15) {
+            return Err(Error::InvalidValue);
+        }
+        let trigger = haptics.trigger_command()?;
+
+        for pulse in pulses {
+            delay.delay_ms(pulse.delay_ms).await;
+
+            let select = self.plan(pulse);
+            let result = async {
+                if select {
+                    haptics.select_sequence(pulse.sequence_id, pulse.loops).await?;
+                }
+                haptics.send(trigger).await
+            }
+            .await;
+
+            if result.is_err() {
+                // Unknown what made it to the device
+                self.armed = None;
+                return result;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pulse(sequence_id: u8) -> Pulse {
+        Pulse {
+            sequence_id,
+            loops: 0,
+            delay_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_repeated_pulse_needs_one_transaction() {
+        let scheduler = PulseScheduler::new();
+        let stats = scheduler.estimate(&[pulse(2); 5]);
+        // Select once, then only triggers
+        assert_eq!(stats.pulses, 5);
+        assert_eq!(stats.wakeups, 5);
+        assert_eq!(stats.transactions, 6);
+    }
+
+    #[test]
+    fn test_select_before_trigger() {
+        let mut scheduler = PulseScheduler::new();
+
+        assert!(scheduler.plan(&pulse(1)));
+        assert!(scheduler.plan(&pulse(2)));
+        assert!(!scheduler.plan(&pulse(2)));
+        assert!(scheduler.plan(&pulse(1)));
+        assert_eq!(scheduler.stats().transactions, 7);
+
+        // Still selected from the last run
+        assert_eq!(scheduler.estimate(&[pulse(1)]).transactions, 1);
+        scheduler.invalidate();
+        assert_eq!(scheduler.estimate(&[pulse(1)]).transactions, 2);
+    }
+}
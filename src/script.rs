@@ -0,0 +1,177 @@
+//! Register scripts: ordered lists of raw register writes.
+//!
+//! Configurations generated by vendor tools or captured from a working unit can be
+//! replayed exactly with `apply_register_script()`. With the `std` feature,
+//! `parse_register_script()` reads the text and C header scripts emitted by the
+//! Renesas configuration tools. `export_register_script()` captures the live
+//! configuration of a unit in the same form.
+
+use embedded_hal_async::i2c::I2c;
+
+use crate::command::Command;
+use crate::errors::Error;
+use crate::registers::{Register, TOP_CTL1};
+use crate::DA728x;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+/// Registers captured by `export_register_script()`, in the order they are written.
+///
+/// Configuration comes first in the order used by `configure()`, TOP_CTL1 last so the
+/// operation mode is only enabled once everything else is set up.
+pub const SCRIPT_REGISTERS: [Register; 31] = [
+    Register::CIF_I2C1,
+    Register::TOP_CFG1,
+    Register::TOP_CFG2,
+    Register::ACTUATOR1,
+    Register::ACTUATOR2,
+    Register::ACTUATOR3,
+    Register::CALIB_V2I_H,
+    Register::CALIB_V2I_L,
+    Register::FRQ_LRA_PER_H,
+    Register::FRQ_LRA_PER_L,
+    Register::FRQ_PHASE_H,
+    Register::FRQ_PHASE_L,
+    Register::SEQ_CTL1,
+    Register::TOP_CFG4,
+    Register::TOP_CFG5,
+    Register::TOP_INT_CFG1,
+    Register::TOP_INT_CFG6_H,
+    Register::TOP_INT_CFG6_L,
+    Register::TOP_INT_CFG7_H,
+    Register::TOP_INT_CFG7_L,
+    Register::TOP_INT_CFG8,
+    Register::FRQ_CTL,
+    Register::TRIM3,
+    Register::TRIM4,
+    Register::IRQ_MASK1,
+    Register::IRQ_MASK2,
+    Register::GPI_0_CTL,
+    Register::GPI_1_CTL,
+    Register::GPI_2_CTL,
+    Register::SEQ_CTL2,
+    Register::TOP_CTL1,
+];
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Write a list of (address, value) pairs in order.
+    ///
+    /// The writes bypass the driver, so the configuration kept by `configure()` is not
+    /// updated and `health_check()` may report the device as `ConfigurationLost`.
+    /// Stops at the first failing write.
+    pub async fn apply_register_script(&mut self, script: &[(u8, u8)]) -> Result<(), Error> {
+        for &(address, value) in script {
+            self.send(Command::write_raw(address, value)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the configuration registers (`SCRIPT_REGISTERS`) as an ordered write list,
+    /// which `apply_register_script()` replays on another unit.
+    ///
+    /// SEQ_START is cleared in TOP_CTL1, so replaying the script does not start playback.
+    /// The waveform memory is not included, upload it separately.
+    pub async fn export_register_script(&mut self) -> Result<[(u8, u8); SCRIPT_REGISTERS.len()], Error> {
+        let mut script = [(0u8, 0u8); SCRIPT_REGISTERS.len()];
+
+        for (entry, register) in script.iter_mut().zip(SCRIPT_REGISTERS) {
+            let mut value = self.read_register(register).await?;
+            if register == Register::TOP_CTL1 {
+                value = TOP_CTL1::from(value).with_SEQ_START(false).into();
+            }
+            *entry = (register as u8, value);
+        }
+
+        Ok(script)
+    }
+}
+
+/// Parse a register script into (address, value) pairs.
+///
+/// Accepts one or more `0x`-prefixed address/value pairs per line, in any punctuation
+/// (`0x13 0x0F`, `0x13=0x0F`, `{ 0x13, 0x0F },`), or a bare hex pair (`13 0F`).
+/// Comments (`//`, `/* */` within a line, `#`, `;`) and lines without hex values,
+/// such as C declarations, are skipped.
+///
+/// # Errors
+/// Returns `InvalidScript` with the 1-based line number for an unpaired or out of range value.
+#[cfg(feature = "std")]
+pub fn parse_register_script(text: &str) -> Result<Vec<(u8, u8)>, Error> {
+    let mut script = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let error = || Error::InvalidScript { line: index + 1 };
+
+        let mut line = line;
+        for marker in ["//", "#", ";"] {
+            if let Some(position) = line.find(marker) {
+                line = &line[..position];
+            }
+        }
+
+        let mut values = Vec::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("/*") {
+            let end = rest[start..].find("*/").map_or(rest.len(), |end| start + end + 2);
+            collect_values(&rest[..start], &mut values).ok_or_else(error)?;
+            rest = &rest[end..];
+        }
+        collect_values(rest, &mut values).ok_or_else(error)?;
+
+        if values.is_empty() {
+            // A bare "addr value" pair without 0x prefixes
+            let tokens: Vec<&str> = rest.split_whitespace().collect();
+            if let [address, value] = tokens[..]
+                && let (Ok(address), Ok(value)) = (u8::from_str_radix(address, 16), u8::from_str_radix(value, 16))
+            {
+                values.extend([address, value]);
+            }
+        }
+
+        if !values.len().is_multiple_of(2) {
+            return Err(error());
+        }
+        script.extend(values.chunks_exact(2).map(|pair| (pair[0], pair[1])));
+    }
+
+    Ok(script)
+}
+
+/// Collect all `0x`-prefixed values of `text`, `None` if one doesn't fit a byte.
+#[cfg(feature = "std")]
+fn collect_values(text: &str, values: &mut Vec<u8>) -> Option<()> {
+    for token in text.split(|c: char| !c.is_ascii_alphanumeric()) {
+        if let Some(hex) = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+            values.push(u8::from_str_radix(hex, 16).ok()?);
+        }
+    }
+    Some(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_script() {
+        let text = "// DA7280 script\n0x13 0x0F\n\n0x0C=0x5A ; nommax\n22 01\n";
+        assert_eq!(parse_register_script(text).unwrap(), [(0x13, 0x0F), (0x0C, 0x5A), (0x22, 0x01)]);
+    }
+
+    #[test]
+    fn test_parse_c_header_script() {
+        let text = "#define DA7280_SCRIPT_LEN 2\nstatic const uint8_t script[][2] = {\n    { 0x13, 0x0F }, /* TOP_CFG1 */\n    { 0x0C, 0x5A }, { 0x0D, 0x60 },\n};\n";
+        assert_eq!(parse_register_script(text).unwrap(), [(0x13, 0x0F), (0x0C, 0x5A), (0x0D, 0x60)]);
+    }
+
+    #[test]
+    fn test_parse_invalid_script() {
+        assert!(matches!(parse_register_script("0x13 0x0F\n0x14\n"), Err(Error::InvalidScript { line: 2 })));
+        assert!(matches!(parse_register_script("0x13 0x100"), Err(Error::InvalidScript { line: 1 })));
+    }
+}
@@ -0,0 +1,133 @@
+//! Power-on built-in self-test.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::events::Events;
+use crate::registers::{Register, IRQ_EVENT1, TOP_CTL1};
+use crate::telemetry::Telemetry;
+use crate::{DA728x, Health};
+
+/// Override value of the test pulse (~50 % of the drive reference).
+const PULSE_LEVEL: i8 = 0x40;
+
+/// Result of [`DA728x::self_test`].
+#[derive(Debug, Clone, Copy)]
+pub struct SelfTestReport {
+    /// Register readback (CHIP_REV and configuration).
+    pub health: Health,
+    /// Telemetry taken halfway through the test pulse, `None` if the readback failed.
+    pub telemetry: Option<Telemetry>,
+    /// Events latched during the test pulse, `None` if the readback failed.
+    pub events: Option<Events>,
+}
+
+impl SelfTestReport {
+    /// Whether the readback succeeded, the device was driving during the pulse
+    /// and no fault was raised.
+    pub fn passed(&self) -> bool {
+        let (Some(telemetry), Some(events)) = (self.telemetry, self.events) else {
+            return false;
+        };
+
+        self.health == Health::Healthy
+            && telemetry.operation_mode == Some(OperationMode::DRO_MODE)
+            && !telemetry.events.has_fault()
+            && !events.has_fault()
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Run a self-test: register readback, a short DRO drive pulse, supply/thermal
+    /// readout during the pulse and a check of the latched events.
+    ///
+    /// Requires `configure()` to have been called. The pulse level is compensated and
+    /// clamped like `set_override_value()` and checked against the protection profile.
+    /// The operation mode and override value are restored afterwards, all latched
+    /// events are cleared.
+    ///
+    /// # Errors
+    /// Returns `ProtectionLimit` if the protection profile rejects the pulse.
+    pub async fn self_test<D: DelayNs>(&mut self, delay: &mut D, pulse_ms: u32) -> Result<SelfTestReport, Error> {
+        if self.actuator_config.is_none() || self.device_config.is_none() {
+            return Err(Error::NotConfigured);
+        }
+
+        let health = self.health_check(false).await?;
+        if health != Health::Healthy {
+            return Ok(SelfTestReport {
+                health,
+                telemetry: None,
+                events: None,
+            });
+        }
+
+        self.check_protection(PULSE_LEVEL, pulse_ms)?;
+        let level = self.compensated_level(PULSE_LEVEL);
+
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?).with_SEQ_START(false);
+        let top_ctl2 = self.read_register(Register::TOP_CTL2).await?;
+
+        self.clear_events(IRQ_EVENT1::from(0xFF)).await?;
+
+        self.write_register(Register::TOP_CTL2, level as u8).await?;
+        self.write_register(
+            Register::TOP_CTL1,
+            top_ctl1.with_OPERATION_MODE(OperationMode::DRO_MODE as u8).into(),
+        )
+        .await?;
+
+        // The supply is only measured during playback
+        delay.delay_ms(pulse_ms / 2).await;
+        let telemetry = self.get_telemetry().await?;
+        delay.delay_ms(pulse_ms - pulse_ms / 2).await;
+
+        self.write_register(Register::TOP_CTL2, 0).await?;
+        self.write_register(Register::TOP_CTL1, top_ctl1.into()).await?;
+        self.write_register(Register::TOP_CTL2, top_ctl2).await?;
+
+        let events = self.get_decoded_events().await?;
+        self.protection_rest(delay).await;
+
+        Ok(SelfTestReport {
+            health,
+            telemetry: Some(telemetry),
+            events: Some(events),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DrivingMode;
+    use crate::mock::{block_on, device_config, lra, MockDevice, NoDelay};
+    use crate::protection::{AmplitudeLimit, ProtectionProfile};
+    use crate::Variant;
+
+    #[test]
+    fn test_self_test_checks_protection() {
+        block_on(async {
+            let mut device = MockDevice::new(Variant::DA7280);
+            let mut haptic = DA728x::new(&mut device, 0x4A, Variant::DA7280).await.unwrap();
+            let config = device_config(OperationMode::RTWM_MODE, DrivingMode::FREQUENCY_TRACK);
+            haptic.configure(lra(), config).await.unwrap();
+
+            let mut profile = ProtectionProfile::NONE;
+            profile.amplitude_limits[0] = Some(AmplitudeLimit { min_duration_ms: 50, max_level: 32 });
+            haptic.set_protection_profile(Some(profile));
+            let writes = haptic.i2c.writes;
+            assert!(matches!(haptic.self_test(&mut NoDelay, 100).await, Err(Error::ProtectionLimit)));
+            assert_eq!(haptic.i2c.writes, writes);
+
+            // Clamped to the ceiling, the pulse is allowed
+            haptic.set_max_level(32).unwrap();
+            haptic.self_test(&mut NoDelay, 100).await.unwrap();
+        });
+    }
+}
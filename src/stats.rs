@@ -0,0 +1,87 @@
+//! I2C transaction counters for profiling.
+//!
+//! With the `stats` feature, the driver counts every I2C transfer it issues. Take
+//! a snapshot before an API call and use [`I2cStats::since`] afterwards to see what
+//! the call cost on the bus, e.g. to compare cached and uncached paths:
+//!
+//! ```ignore
+//! let before = haptics.i2c_stats();
+//! haptics.disable().await?;
+//! let cost = haptics.i2c_stats().since(&before);
+//! assert_eq!((cost.reads, cost.writes), (0, 1));
+//! ```
+//!
+//! Commands executed on a blocking bus with `Command::execute()` are not counted.
+
+use crate::DA728x;
+
+/// Totals of the I2C transfers issued by the driver.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct I2cStats {
+    /// Write-read transfers (register reads).
+    pub reads: u32,
+    /// Write-only transfers (register writes).
+    pub writes: u32,
+    /// Bytes read from the device.
+    pub bytes_read: u32,
+    /// Bytes written to the device, including register addresses.
+    pub bytes_written: u32,
+}
+
+impl I2cStats {
+    /// The transfers counted after `earlier` was taken.
+    pub fn since(&self, earlier: &I2cStats) -> I2cStats {
+        I2cStats {
+            reads: self.reads.wrapping_sub(earlier.reads),
+            writes: self.writes.wrapping_sub(earlier.writes),
+            bytes_read: self.bytes_read.wrapping_sub(earlier.bytes_read),
+            bytes_written: self.bytes_written.wrapping_sub(earlier.bytes_written),
+        }
+    }
+
+    /// Count a transfer writing `written` and reading `read` bytes.
+    pub(crate) fn record(&mut self, written: usize, read: usize) {
+        if read > 0 {
+            self.reads = self.reads.wrapping_add(1);
+        } else {
+            self.writes = self.writes.wrapping_add(1);
+        }
+        self.bytes_written = self.bytes_written.wrapping_add(written as u32);
+        self.bytes_read = self.bytes_read.wrapping_add(read as u32);
+    }
+}
+
+impl<I2C> DA728x<I2C> {
+    /// The transfers counted since the driver was created or `reset_i2c_stats()`.
+    pub fn i2c_stats(&self) -> I2cStats {
+        self.i2c_stats
+    }
+
+    pub fn reset_i2c_stats(&mut self) {
+        self.i2c_stats = I2cStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_since() {
+        let mut stats = I2cStats::default();
+        stats.record(1, 1);
+        let before = stats;
+        stats.record(1, 4);
+        stats.record(2, 0);
+
+        assert_eq!(
+            stats.since(&before),
+            I2cStats {
+                reads: 1,
+                writes: 1,
+                bytes_read: 4,
+                bytes_written: 3,
+            }
+        );
+    }
+}
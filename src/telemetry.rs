@@ -0,0 +1,338 @@
+//! Periodic telemetry snapshots for logging.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::events::Events;
+use crate::registers::{Register, IRQ_EVENT1, IRQ_EVENT_SEQ_DIAG, IRQ_EVENT_WARNING_DIAG, IRQ_STATUS1, TOP_CTL1, TOP_CTL2};
+use crate::DA728x;
+
+/// Thermal state as far as the device reports it.
+///
+/// The DA728x has no temperature ADC, only a warning (105 °C) and a critical (125 °C) threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThermalState {
+    Normal,
+    /// The 105 °C warning threshold was exceeded (latched E_OVERTEMP_WARN).
+    Warning,
+    /// The 125 °C critical threshold is exceeded (STA_OVERTEMP_CRIT).
+    Critical,
+}
+
+/// A snapshot of the device state, see [`DA728x::get_telemetry`].
+#[derive(Debug, Clone, Copy)]
+pub struct Telemetry {
+    /// Current status flags.
+    pub status: IRQ_STATUS1,
+    /// Latched events (not cleared by reading telemetry).
+    pub events: Events,
+    /// Raw 15-bit supply measurement (ADC_VDD_H/L), updated during playback.
+    /// See 5.7.13 Supply Monitoring for the conversion to volts.
+    pub supply_raw: u16,
+    pub thermal: ThermalState,
+    /// Raw 15-bit instantaneous resonant period (LRA_PER_ACTUAL_H/L), updated every half-period
+    /// while frequency tracking is active.
+    pub lra_period_raw: u16,
+    /// Current operation mode, `None` if TOP_CTL1 holds a reserved value.
+    pub operation_mode: Option<OperationMode>,
+}
+
+impl Telemetry {
+    /// The tracked resonant frequency in Hz, derived from `lra_period_raw` (1333.32 ns per LSB).
+    ///
+    /// Returns `None` if no period has been measured.
+    pub fn tracked_frequency_hz(&self) -> Option<u16> {
+        period_to_hz(self.lra_period_raw)
+    }
+}
+
+/// The drive level as held by the device, see [`DA728x::get_drive_level`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriveLevel {
+    /// Override value read back from TOP_CTL2 (DRO mode).
+    pub override_value: i8,
+    /// The drive was clipped below the requested level (latched E_LIM_DRIVE / E_LIM_DRIVE_ACC),
+    /// e.g. because the supply is too low for the configured voltage.
+    pub limited: bool,
+    /// Current operation mode, `None` if TOP_CTL1 holds a reserved value.
+    pub operation_mode: Option<OperationMode>,
+}
+
+impl DriveLevel {
+    /// Whether the device is driving the actuator at `commanded` without clipping.
+    pub fn confirms(&self, commanded: i8) -> bool {
+        self.operation_mode == Some(OperationMode::DRO_MODE) && self.override_value == commanded && !self.limited
+    }
+}
+
+/// One sample of `DA728x::sample_drive()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DriveSample {
+    /// Override value held in TOP_CTL2.
+    pub override_value: i8,
+    /// Drive limit warning latched (E_LIM_DRIVE / E_LIM_DRIVE_ACC).
+    pub limited: bool,
+    /// Raw 15-bit instantaneous resonant period (LRA_PER_ACTUAL_H/L).
+    pub lra_period_raw: u16,
+    /// Raw 15-bit supply measurement (ADC_VDD_H/L).
+    pub supply_raw: u16,
+}
+
+impl DriveSample {
+    /// The tracked resonant frequency in Hz, `None` if no period has been measured.
+    pub fn tracked_frequency_hz(&self) -> Option<u16> {
+        period_to_hz(self.lra_period_raw)
+    }
+}
+
+/// Internal measurements readable with [`DA728x::read_measurement`].
+///
+/// Temperature and BEMF are not exposed by the DA728x, see [`ThermalState`] for the
+/// temperature thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    /// Supply measurement (ADC_VDD_H/L), updated during playback.
+    Supply,
+    /// Instantaneous resonant period (LRA_PER_ACTUAL_H/L), updated every half-period.
+    ResonantPeriod,
+    /// Resonant period averaged over the last four half-periods (LRA_AVR_H/L).
+    AverageResonantPeriod,
+}
+
+impl Channel {
+    /// Register holding the high byte, the low byte follows it.
+    fn register(&self) -> Register {
+        match self {
+            Channel::Supply => Register::ADC_DATA_H1,
+            Channel::ResonantPeriod => Register::FRQ_LRA_PER_ACT_H,
+            Channel::AverageResonantPeriod => Register::LRA_AVR_H,
+        }
+    }
+}
+
+/// Unit of a [`Measurement`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    /// Unscaled ADC code, see 5.7.13 Supply Monitoring for the conversion to volts.
+    Raw,
+    Nanoseconds,
+}
+
+/// A measurement scaled to engineering units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub channel: Channel,
+    /// Raw 15-bit register value.
+    pub raw: u16,
+    pub value: u32,
+    pub unit: Unit,
+}
+
+impl Measurement {
+    /// Scale a raw register value of `channel`.
+    pub fn new(channel: Channel, raw: u16) -> Self {
+        let (value, unit) = match channel {
+            Channel::Supply => (raw as u32, Unit::Raw),
+            // 1333.32 ns per LSB
+            Channel::ResonantPeriod | Channel::AverageResonantPeriod => {
+                (raw as u32 * 133_332 / 100, Unit::Nanoseconds)
+            }
+        };
+
+        Self { channel, raw, value, unit }
+    }
+
+    /// The frequency corresponding to a resonant period measurement, `None` for other
+    /// channels or if no period has been measured.
+    pub fn frequency_hz(&self) -> Option<u16> {
+        match self.channel {
+            Channel::Supply => None,
+            Channel::ResonantPeriod | Channel::AverageResonantPeriod => period_to_hz(self.raw),
+        }
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Read back the drive level from the device.
+    ///
+    /// The DA728x has no register reporting the instantaneous output amplitude after
+    /// acceleration and gain, so this reads the level the device holds in TOP_CTL2
+    /// together with the drive limit warnings, which is as close as the hardware gets.
+    /// Reading does not clear the warnings.
+    pub async fn get_drive_level(&mut self) -> Result<DriveLevel, Error> {
+        let top_ctl2 = TOP_CTL2::from(self.read_register(Register::TOP_CTL2).await?);
+        let warning_diag = IRQ_EVENT_WARNING_DIAG::from(self.read_register(Register::IRQ_EVENT_WARNING_DIAG).await?);
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
+
+        Ok(DriveLevel {
+            override_value: top_ctl2.OVERRIDE_VAL() as i8,
+            limited: warning_diag.E_LIM_DRIVE() || warning_diag.E_LIM_DRIVE_ACC(),
+            operation_mode: OperationMode::from_register(top_ctl1.OPERATION_MODE()),
+        })
+    }
+
+    /// Read a telemetry snapshot.
+    ///
+    /// Consecutive registers are read in bursts (events/diagnostics/status, supply, period),
+    /// so the snapshot takes four I2C transactions.
+    pub async fn get_telemetry(&mut self) -> Result<Telemetry, Error> {
+        // IRQ_EVENT1, IRQ_EVENT_WARNING_DIAG, IRQ_EVENT_SEQ_DIAG, IRQ_STATUS1
+        let mut irq = [0u8; 4];
+        self.read_registers(Register::IRQ_EVENT1, &mut irq).await?;
+
+        let mut adc = [0u8; 2];
+        self.read_registers(Register::ADC_DATA_H1, &mut adc).await?;
+
+        let mut period = [0u8; 2];
+        self.read_registers(Register::FRQ_LRA_PER_ACT_H, &mut period).await?;
+
+        let top_ctl1 = TOP_CTL1::from(self.read_register(Register::TOP_CTL1).await?);
+
+        let events = Events::new(
+            IRQ_EVENT1::from(irq[0]),
+            IRQ_EVENT_WARNING_DIAG::from(irq[1]),
+            IRQ_EVENT_SEQ_DIAG::from(irq[2]),
+        );
+        let status = IRQ_STATUS1::from(irq[3]);
+
+        let thermal = if status.STA_OVERTEMP_CRIT() {
+            ThermalState::Critical
+        } else if IRQ_EVENT_WARNING_DIAG::from(irq[1]).E_OVERTEMP_WARN() {
+            ThermalState::Warning
+        } else {
+            ThermalState::Normal
+        };
+
+        Ok(Telemetry {
+            status,
+            events,
+            supply_raw: concat_15bit(adc[0], adc[1]),
+            thermal,
+            lra_period_raw: concat_15bit(period[0], period[1]),
+            operation_mode: OperationMode::from_register(top_ctl1.OPERATION_MODE()),
+        })
+    }
+
+    /// Read an internal measurement, scaled to engineering units where possible.
+    pub async fn read_measurement(&mut self, channel: Channel) -> Result<Measurement, Error> {
+        let mut buffer = [0u8; 2];
+        self.read_registers(channel.register(), &mut buffer).await?;
+        Ok(Measurement::new(channel, concat_15bit(buffer[0], buffer[1])))
+    }
+
+    /// Sample the drive `N` times, `interval_ms` apart, e.g. while tuning effects on a new actuator.
+    ///
+    /// Each sample takes four I2C transactions. The DA728x does not expose the BEMF
+    /// measurement, the tracked period is what the frequency tracking derives from it.
+    pub async fn sample_drive<const N: usize, D: DelayNs>(
+        &mut self,
+        interval_ms: u32,
+        delay: &mut D,
+    ) -> Result<[DriveSample; N], Error> {
+        let mut samples = [DriveSample::default(); N];
+
+        for (index, sample) in samples.iter_mut().enumerate() {
+            if index > 0 {
+                delay.delay_ms(interval_ms).await;
+            }
+
+            let mut adc = [0u8; 2];
+            self.read_registers(Register::ADC_DATA_H1, &mut adc).await?;
+
+            let mut period = [0u8; 2];
+            self.read_registers(Register::FRQ_LRA_PER_ACT_H, &mut period).await?;
+
+            let top_ctl2 = TOP_CTL2::from(self.read_register(Register::TOP_CTL2).await?);
+            let warning_diag = IRQ_EVENT_WARNING_DIAG::from(self.read_register(Register::IRQ_EVENT_WARNING_DIAG).await?);
+
+            *sample = DriveSample {
+                override_value: top_ctl2.OVERRIDE_VAL() as i8,
+                limited: warning_diag.E_LIM_DRIVE() || warning_diag.E_LIM_DRIVE_ACC(),
+                lra_period_raw: concat_15bit(period[0], period[1]),
+                supply_raw: concat_15bit(adc[0], adc[1]),
+            };
+        }
+
+        Ok(samples)
+    }
+}
+
+/// Convert a raw resonant period (1333.32 ns per LSB) into Hz, `None` for 0.
+fn period_to_hz(period_raw: u16) -> Option<u16> {
+    if period_raw == 0 {
+        return None;
+    }
+    Some((1_000_000_000 / (period_raw as u32 * 1333)) as u16)
+}
+
+/// Concatenate an 8-bit high and 7-bit low register.
+fn concat_15bit(high: u8, low: u8) -> u16 {
+    ((high as u16) << 7) | (low & 0x7F) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concat_15bit() {
+        assert_eq!(concat_15bit(0xFF, 0x7F), 0x7FFF);
+        assert_eq!(concat_15bit(0x21, 0x4F), (0x21 << 7) | 0x4F);
+        // Bit 7 of the low register is not part of the value
+        assert_eq!(concat_15bit(0x00, 0xFF), 0x7F);
+    }
+
+    #[test]
+    fn test_tracked_frequency() {
+        let mut telemetry = Telemetry {
+            status: IRQ_STATUS1::new(),
+            events: Events::new(IRQ_EVENT1::new(), IRQ_EVENT_WARNING_DIAG::new(), IRQ_EVENT_SEQ_DIAG::new()),
+            supply_raw: 0,
+            thermal: ThermalState::Normal,
+            lra_period_raw: 0,
+            operation_mode: None,
+        };
+        assert_eq!(telemetry.tracked_frequency_hz(), None);
+
+        // Reset value 0x21/0x4F corresponds to ~174 Hz
+        telemetry.lra_period_raw = concat_15bit(0x21, 0x4F);
+        assert_eq!(telemetry.tracked_frequency_hz(), Some(174));
+    }
+
+    #[test]
+    fn test_drive_level_confirms() {
+        let mut level = DriveLevel {
+            override_value: -64,
+            limited: false,
+            operation_mode: Some(OperationMode::DRO_MODE),
+        };
+        assert!(level.confirms(-64));
+        assert!(!level.confirms(64));
+
+        level.limited = true;
+        assert!(!level.confirms(-64));
+
+        level.limited = false;
+        level.operation_mode = Some(OperationMode::INACTIVE);
+        assert!(!level.confirms(-64));
+    }
+
+    #[test]
+    fn test_measurement_scaling() {
+        let supply = Measurement::new(Channel::Supply, 0x1234);
+        assert_eq!(supply.value, 0x1234);
+        assert_eq!(supply.unit, Unit::Raw);
+        assert_eq!(supply.frequency_hz(), None);
+
+        let period = Measurement::new(Channel::ResonantPeriod, concat_15bit(0x21, 0x4F));
+        assert_eq!(period.unit, Unit::Nanoseconds);
+        assert_eq!(period.value, 4303 * 133_332 / 100);
+        assert_eq!(period.frequency_hz(), Some(174));
+        assert_eq!(Measurement::new(Channel::AverageResonantPeriod, 0).frequency_hz(), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Textured feedback by amplitude modulation in DRO mode.
+//!
+//! A texture periodically dips the drive level at a low rate, which feels like a
+//! rough surface or detents when used with sliders and scroll wheels.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::config::OperationMode;
+use crate::errors::Error;
+use crate::DA728x;
+
+/// Shape of the amplitude modulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureShape {
+    /// Hard switching between full and reduced level, feels like detents.
+    Square,
+    /// Linear ramps between full and reduced level, feels like a rough surface.
+    Triangle,
+}
+
+/// Amplitude modulation applied while driving.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Texture {
+    /// Override value at the top of the modulation.
+    pub level: i8,
+    /// How far the level dips, in percent of `level` (0..=100).
+    pub depth_percent: u8,
+    /// Modulation rate (1..=100 Hz).
+    pub rate_Hz: u16,
+    /// Interval between DRO updates, should be well below the modulation period.
+    pub update_ms: u32,
+    pub shape: TextureShape,
+}
+
+impl Texture {
+    /// Check the ranges of the texture parameters.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.depth_percent > 100 || !(1..=100).contains(&self.rate_Hz) || self.update_ms == 0 {
+            return Err(Error::InvalidValue);
+        }
+        Ok(())
+    }
+
+    /// Override value `elapsed_ms` after the start of the texture.
+    pub fn level_at(&self, elapsed_ms: u32) -> i8 {
+        let phase = (elapsed_ms as u64 * self.rate_Hz as u64 % 1000) as i32;
+
+        // 1000 at the top of the modulation, 0 at the bottom
+        let envelope = match self.shape {
+            TextureShape::Square => {
+                if phase < 500 { 1000 } else { 0 }
+            }
+            TextureShape::Triangle => {
+                if phase < 500 { 1000 - phase * 2 } else { (phase - 500) * 2 }
+            }
+        };
+
+        let dip = self.depth_percent as i32 * 10 * (1000 - envelope) / 1000;
+        (self.level as i32 * (1000 - dip) / 1000) as i8
+    }
+}
+
+impl<I2C> DA728x<I2C>
+where
+    I2C: I2c,
+{
+    /// Play a texture for `duration_ms` by updating the override value, then stop the drive.
+    ///
+    /// The whole texture counts as one continuous drive at `level` for the protection profile.
+    pub async fn play_texture<D: DelayNs>(
+        &mut self,
+        texture: &Texture,
+        duration_ms: u32,
+        delay: &mut D,
+    ) -> Result<(), Error> {
+        let device_config = self.device_config.ok_or(Error::NotConfigured)?;
+        if device_config.operation_mode != OperationMode::DRO_MODE {
+            return Err(Error::WrongMode);
+        }
+        texture.validate()?;
+        self.check_protection(texture.level, duration_ms)?;
+
+        let mut elapsed_ms = 0;
+        while elapsed_ms < duration_ms {
+            self.set_override_value(texture.level_at(elapsed_ms)).await?;
+            let step_ms = texture.update_ms.min(duration_ms - elapsed_ms);
+            delay.delay_ms(step_ms).await;
+            elapsed_ms += step_ms;
+        }
+
+        self.set_override_value(0).await?;
+        self.protection_rest(delay).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_texture() {
+        let texture = Texture {
+            level: 100,
+            depth_percent: 60,
+            rate_Hz: 10,
+            update_ms: 5,
+            shape: TextureShape::Square,
+        };
+        assert!(texture.validate().is_ok());
+        assert_eq!(texture.level_at(0), 100);
+        assert_eq!(texture.level_at(49), 100);
+        assert_eq!(texture.level_at(50), 40);
+        assert_eq!(texture.level_at(100), 100);
+    }
+
+    #[test]
+    fn test_triangle_texture() {
+        let texture = Texture {
+            level: -100,
+            depth_percent: 100,
+            rate_Hz: 20,
+            update_ms: 2,
+            shape: TextureShape::Triangle,
+        };
+        assert_eq!(texture.level_at(0), -100);
+        assert_eq!(texture.level_at(25), 0);
+        assert_eq!(texture.level_at(12), -52);
+        assert!(Texture { rate_Hz: 0, ..texture }.validate().is_err());
+    }
+}
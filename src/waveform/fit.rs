@@ -0,0 +1,157 @@
+//! Fitting an amplitude envelope with PWL points.
+
+use crate::errors::{Error, WaveformElement};
+use super::snippet::{PwlPoint, Snippet, SnippetBuilder, MAX_POINTS_PER_SNIPPET};
+
+/// Quality knobs for `fit_envelope()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FitOptions {
+    /// Maximum number of PWL points (1-16), i.e. bytes of waveform memory.
+    pub max_points: u8,
+    /// Maximum allowed difference between envelope and fitted amplitude (0-15).
+    pub max_error: u8,
+}
+
+impl FitOptions {
+    /// Exact fit with as many points as a snippet can hold.
+    pub const EXACT: FitOptions = FitOptions {
+        max_points: MAX_POINTS_PER_SNIPPET as u8,
+        max_error: 0,
+    };
+}
+
+/// A fitted snippet and how far it deviates from the envelope.
+#[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    pub snippet: Snippet,
+    /// Largest difference between envelope and fitted amplitude.
+    pub error: u8,
+}
+
+/// Largest error when covering `envelope` (starting from `start`) with a single point.
+fn segment_error(start: u8, envelope: &[u8], ramp: bool) -> u8 {
+    let n = envelope.len() as i32;
+    let target = envelope[envelope.len() - 1] as i32;
+
+    envelope
+        .iter()
+        .enumerate()
+        .map(|(k, &value)| {
+            let fitted = if ramp {
+                start as i32 + (target - start as i32) * (k as i32 + 1) / n
+            } else {
+                target
+            };
+            (fitted - value as i32).unsigned_abs() as u8
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Fit an amplitude envelope with ramp and step points.
+///
+/// `envelope` holds one amplitude (0-15) per timebase, the waveform starts at 0.
+/// Each point covers as many timebases (up to 8) as possible while staying within
+/// `max_error`, so a larger error results in fewer points.
+///
+/// # Errors
+/// Returns `EmptySnippet` for an empty envelope, `InvalidAmplitude` for values above 15,
+/// `InvalidValue` for `max_points` outside 1-16 and `TooManyPoints` if the envelope
+/// can't be fitted within `max_points`.
+pub fn fit_envelope(envelope: &[u8], options: &FitOptions) -> Result<Fit, Error> {
+    if envelope.is_empty() {
+        return Err(Error::EmptySnippet);
+    }
+    if envelope.iter().any(|&value| value > 15) {
+        return Err(Error::InvalidAmplitude);
+    }
+    if options.max_points == 0 || options.max_points as usize > MAX_POINTS_PER_SNIPPET {
+        return Err(Error::InvalidValue);
+    }
+
+    let mut builder = SnippetBuilder::new();
+    let mut points = 0;
+    let mut error = 0;
+    let mut current = 0u8;
+    let mut index = 0;
+
+    while index < envelope.len() {
+        let max_len = (envelope.len() - index).min(8);
+
+        // A single timebase ramp is always exact, so this always finds a point
+        let (len, ramp, segment) = (1..=max_len)
+            .rev()
+            .find_map(|len| {
+                let segment = &envelope[index..index + len];
+                let ramp_error = segment_error(current, segment, true);
+                let step_error = segment_error(current, segment, false);
+                let (ramp, segment_error) = if ramp_error <= step_error {
+                    (true, ramp_error)
+                } else {
+                    (false, step_error)
+                };
+                (segment_error <= options.max_error).then_some((len, ramp, segment_error))
+            })
+            .unwrap_or((1, true, 0));
+
+        if points >= options.max_points {
+            return Err(Error::TooManyPoints {
+                element: WaveformElement::Point(points),
+            });
+        }
+
+        let amplitude = envelope[index + len - 1];
+        let point = if ramp {
+            PwlPoint::ramp(len as u8, amplitude)?
+        } else {
+            PwlPoint::step(len as u8, amplitude)?
+        };
+        builder = builder.point(point)?;
+        points += 1;
+        error = error.max(segment);
+        current = amplitude;
+        index += len;
+    }
+
+    Ok(Fit {
+        snippet: builder.build()?,
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_exact_ramp() {
+        // Linear rise over 3 timebases, then hold for 4
+        let envelope = [4, 8, 12, 15, 15, 15, 15];
+        let fit = fit_envelope(&envelope, &FitOptions::EXACT).unwrap();
+        assert_eq!(fit.snippet.points().len(), 2);
+        assert_eq!(fit.error, 0);
+        assert!(fit.snippet.points()[0].is_ramp());
+        assert_eq!(fit.snippet.points()[0].timebases(), 3);
+        assert!(!fit.snippet.points()[1].is_ramp());
+        assert_eq!(fit.snippet.points()[1].timebases(), 4);
+    }
+
+    #[test]
+    fn test_fit_error_tradeoff() {
+        let envelope = [2, 5, 6, 9, 10, 13, 15, 15, 12, 8, 3, 0];
+        let exact = fit_envelope(&envelope, &FitOptions::EXACT).unwrap();
+        let coarse = fit_envelope(&envelope, &FitOptions { max_points: 16, max_error: 2 }).unwrap();
+        assert!(coarse.snippet.points().len() < exact.snippet.points().len());
+        assert!(coarse.error <= 2);
+        assert!(matches!(
+            fit_envelope(&envelope, &FitOptions { max_points: 1, max_error: 0 }),
+            Err(Error::TooManyPoints { element: WaveformElement::Point(1) })
+        ));
+    }
+
+    #[test]
+    fn test_fit_invalid_envelope() {
+        assert!(matches!(fit_envelope(&[], &FitOptions::EXACT), Err(Error::EmptySnippet)));
+        assert!(matches!(fit_envelope(&[16], &FitOptions::EXACT), Err(Error::InvalidAmplitude)));
+    }
+}
@@ -1,6 +1,7 @@
 //! Frame construction for waveform sequences.
 
 use crate::errors::Error;
+use super::snippet::PwlPoint;
 
 /// Gain multiplier for frame playback.
 ///
@@ -19,6 +20,54 @@ pub enum Gain {
     Eighth = 3,
 }
 
+impl Gain {
+    const ALL: [Gain; 4] = [Gain::Full, Gain::Half, Gain::Quarter, Gain::Eighth];
+
+    /// Decode the GAIN field of a frame.
+    pub(crate) fn from_bits(bits: u8) -> Self {
+        Self::ALL[(bits & 0x03) as usize]
+    }
+
+    /// Approximate gain in dB (0, -6, -12 or -18).
+    pub fn db(&self) -> i8 {
+        -6 * *self as i8
+    }
+
+    /// The gain closest to `db`, saturating at `Full` and `Eighth`.
+    pub fn from_db(db: i8) -> Self {
+        let steps = (-(db as i16) + 3).div_euclid(6).clamp(0, 3);
+        Self::ALL[steps as usize]
+    }
+
+    /// Linear factor in ‰ (1000, 500, 250 or 125).
+    pub fn permille(&self) -> u16 {
+        1000 >> *self as u8
+    }
+
+    /// The gain closest to a linear factor in ‰, rounded on the dB scale.
+    pub fn from_permille(permille: u16) -> Self {
+        // Geometric midpoints between the gain steps
+        match permille {
+            707.. => Gain::Full,
+            354..707 => Gain::Half,
+            177..354 => Gain::Quarter,
+            _ => Gain::Eighth,
+        }
+    }
+
+    /// Apply both gains one after the other, e.g. a frame gain on top of an effect gain.
+    ///
+    /// Returns `None` if the combined attenuation is below -18 dB.
+    pub fn combine(self, other: Gain) -> Option<Self> {
+        Self::ALL.get(self as usize + other as usize).copied()
+    }
+
+    /// Scale an amplitude in ‰ of full scale the way the device does (a right shift).
+    pub fn apply(&self, permille: i32) -> i32 {
+        permille >> *self as u8
+    }
+}
+
 /// Timebase for PWL point duration.
 ///
 /// Each PWL point's TIME field is multiplied by this timebase
@@ -39,6 +88,16 @@ pub enum Timebase {
     Ms87_04 = 3,
 }
 
+/// Duration of the timebases in µs, indexed by TIMEBASE.
+pub(crate) const TIMEBASE_US: [u32; 4] = [5_440, 21_760, 43_520, 87_040];
+
+impl Timebase {
+    /// Duration of one timebase in µs.
+    pub fn duration_us(&self) -> u32 {
+        TIMEBASE_US[*self as usize]
+    }
+}
+
 /// Maximum frame size in bytes.
 const MAX_FRAME_BYTES: usize = 3;
 
@@ -65,6 +124,18 @@ impl Frame {
         &self.bytes[..self.len as usize]
     }
 
+    /// Get the gain of this frame.
+    pub fn gain(&self) -> Gain {
+        Gain::from_bits(self.bytes[0] >> 5)
+    }
+
+    /// Effective amplitude of `point` played by this frame, in ‰ of full scale.
+    ///
+    /// See `PwlPoint::amplitude_permille()` for the scaling of the point itself.
+    pub fn effective_amplitude(&self, point: PwlPoint, acceleration_enabled: bool) -> i32 {
+        self.gain().apply(point.amplitude_permille(acceleration_enabled))
+    }
+
     /// Encode the frame into the provided buffer.
     ///
     /// Returns the number of bytes written.
@@ -180,6 +251,28 @@ impl FrameBuilder {
         Ok(self)
     }
 
+    /// Set a frequency override as a multiple of the actuator's resonant frequency.
+    ///
+    /// # Arguments
+    /// * `resonance_hz` - Resonant frequency of the actuator in Hz
+    /// * `percent` - Multiple in percent, e.g. 200 for an octave up or 50 for an octave down
+    ///
+    /// # Errors
+    /// Returns `InvalidFrequency` if the resulting frequency (rounded to Hz) is > 511.
+    pub fn frequency_multiple(self, resonance_hz: u16, percent: u16) -> Result<Self, Error> {
+        let freq_hz = (resonance_hz as u32 * percent as u32 + 50) / 100;
+        self.frequency_hz(u16::try_from(freq_hz).map_err(|_| Error::InvalidFrequency)?)
+    }
+
+    /// Set a frequency override as an offset from the actuator's resonant frequency.
+    ///
+    /// # Errors
+    /// Returns `InvalidFrequency` if the resulting frequency is negative or > 511.
+    pub fn frequency_offset(self, resonance_hz: u16, offset_hz: i16) -> Result<Self, Error> {
+        let freq_hz = resonance_hz as i32 + offset_hz as i32;
+        self.frequency_hz(u16::try_from(freq_hz).map_err(|_| Error::InvalidFrequency)?)
+    }
+
     /// Build the frame.
     pub fn build(self) -> Result<Frame, Error> {
         let mut bytes = [0u8; MAX_FRAME_BYTES];
@@ -306,6 +399,20 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_frame_frequency_relative_to_resonance() {
+        let frame = FrameBuilder::new(1).unwrap().frequency_multiple(170, 150).unwrap().build().unwrap();
+        // 255 Hz
+        assert_eq!(frame.as_bytes()[1] & 0x06, 0x04);
+        assert_eq!(frame.as_bytes()[2], 255);
+
+        let frame = FrameBuilder::new(1).unwrap().frequency_offset(170, -20).unwrap().build().unwrap();
+        assert_eq!(frame.as_bytes()[2], 150);
+
+        assert!(matches!(FrameBuilder::new(1).unwrap().frequency_multiple(170, 400), Err(Error::InvalidFrequency)));
+        assert!(matches!(FrameBuilder::new(1).unwrap().frequency_offset(170, -171), Err(Error::InvalidFrequency)));
+    }
+
     #[test]
     fn test_frame_invalid_frequency() {
         assert!(matches!(
@@ -338,4 +445,29 @@ mod tests {
         // Byte 3: 256 & 0xFF = 0x00
         assert_eq!(frame.as_bytes()[2], 0x00);
     }
+
+    #[test]
+    fn test_gain_conversions() {
+        assert_eq!(Gain::Quarter.db(), -12);
+        assert_eq!(Gain::from_db(0), Gain::Full);
+        assert_eq!(Gain::from_db(-4), Gain::Half);
+        assert_eq!(Gain::from_db(-14), Gain::Quarter);
+        assert_eq!(Gain::from_db(-40), Gain::Eighth);
+        assert_eq!(Gain::from_db(6), Gain::Full);
+
+        assert_eq!(Gain::Eighth.permille(), 125);
+        assert_eq!(Gain::from_permille(1000), Gain::Full);
+        assert_eq!(Gain::from_permille(600), Gain::Half);
+        assert_eq!(Gain::from_permille(300), Gain::Quarter);
+        assert_eq!(Gain::from_permille(0), Gain::Eighth);
+
+        assert_eq!(Gain::Half.combine(Gain::Quarter), Some(Gain::Eighth));
+        assert_eq!(Gain::Full.combine(Gain::Half), Some(Gain::Half));
+        assert_eq!(Gain::Quarter.combine(Gain::Quarter), None);
+
+        let frame = FrameBuilder::new(3).unwrap().gain(Gain::Quarter).loop_count(2).unwrap().build().unwrap();
+        assert_eq!(frame.gain(), Gain::Quarter);
+        assert_eq!(frame.effective_amplitude(PwlPoint::step(1, 15).unwrap(), true), 250);
+        assert_eq!(frame.effective_amplitude(PwlPoint::step(1, 7).unwrap(), false), 250);
+    }
 }
@@ -1,10 +1,13 @@
 //! Waveform memory layout and construction.
 
-use crate::errors::Error;
-use super::snippet::Snippet;
+use core::ops::Range;
+
+use crate::errors::{Error, WaveformElement};
+use crate::Variant;
+use super::snippet::{PwlPoint, Snippet, SnippetBuilder};
 use super::sequence::Sequence;
 
-/// Maximum waveform memory size in bytes.
+/// Largest waveform memory of all variants in bytes, see `Variant::waveform_memory_size()`.
 pub const MAX_MEMORY_SIZE: usize = 100;
 
 /// Maximum number of snippets (1-15, ID 0 is reserved).
@@ -57,6 +60,105 @@ impl WaveformMemory {
     pub fn num_sequences(&self) -> u8 {
         self.num_sequences
     }
+
+    /// Parse an image, e.g. read back from the device with `DA728x::read_waveform_memory_image()`.
+    ///
+    /// Trailing bytes after the last sequence are ignored.
+    ///
+    /// # Errors
+    /// Returns `EmptySnippet` / `EmptySequence` for a header without snippets or sequences,
+    /// `TooManySnippets` / `TooManySequences` if the header exceeds the limits and
+    /// `InvalidValue` if the end pointers are not increasing or point outside `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (&num_snippets, &num_sequences) = match bytes {
+            [num_snippets, num_sequences, ..] => (num_snippets, num_sequences),
+            _ => return Err(Error::InvalidValue),
+        };
+
+        if num_snippets == 0 {
+            return Err(Error::EmptySnippet);
+        }
+        if num_sequences == 0 {
+            return Err(Error::EmptySequence);
+        }
+        if num_snippets as usize > MAX_SNIPPETS {
+            return Err(Error::TooManySnippets {
+                element: WaveformElement::Snippet(num_snippets),
+            });
+        }
+        if num_sequences as usize > MAX_SEQUENCES {
+            return Err(Error::TooManySequences {
+                element: WaveformElement::Sequence(num_sequences - 1),
+            });
+        }
+
+        let data_area_start = 2 + num_snippets as usize + num_sequences as usize;
+        let pointers = bytes.get(2..data_area_start).ok_or(Error::InvalidValue)?;
+
+        // Every element holds at least one byte
+        let mut previous_end = data_area_start - 1;
+        for &end in pointers {
+            if (end as usize) <= previous_end {
+                return Err(Error::InvalidValue);
+            }
+            previous_end = end as usize;
+        }
+
+        let len = previous_end + 1;
+        if len > MAX_MEMORY_SIZE || len > bytes.len() {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut data = [0u8; MAX_MEMORY_SIZE];
+        data[..len].copy_from_slice(&bytes[..len]);
+        Ok(Self {
+            data,
+            len: len as u8,
+            num_snippets,
+            num_sequences,
+        })
+    }
+
+    /// Byte range of a snippet or sequence in the image, e.g. for `DA728x::patch_waveform_memory()`.
+    ///
+    /// Returns `None` for points, frames and IDs that are not in the memory.
+    pub fn range_of(&self, element: WaveformElement) -> Option<Range<usize>> {
+        let pointer_index = match element {
+            WaveformElement::Snippet(id) if (1..=self.num_snippets).contains(&id) => id as usize - 1,
+            WaveformElement::Sequence(id) if id < self.num_sequences => self.num_snippets as usize + id as usize,
+            _ => return None,
+        };
+
+        let start = if pointer_index == 0 {
+            2 + self.num_snippets as usize + self.num_sequences as usize
+        } else {
+            self.data[2 + pointer_index - 1] as usize + 1
+        };
+        let end = self.data[2 + pointer_index] as usize + 1;
+
+        Some(start..end)
+    }
+
+    /// Stable fingerprint of the image (CRC-32/ISO-HDLC of `as_bytes()`).
+    ///
+    /// Compare with `DA728x::verify_waveform_memory()` to skip uploading an image
+    /// that is already on the device.
+    pub fn fingerprint(&self) -> u32 {
+        crc32(self.as_bytes())
+    }
+}
+
+/// CRC-32/ISO-HDLC (as used by zlib and Ethernet), bitwise to avoid a lookup table.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
 }
 
 /// Builder for constructing waveform memory.
@@ -105,6 +207,7 @@ pub struct WaveformMemoryBuilder {
     num_sequences: u8,
     #[allow(dead_code)]
     acceleration_enabled: bool,
+    memory_size: usize,
 }
 
 impl WaveformMemoryBuilder {
@@ -120,9 +223,73 @@ impl WaveformMemoryBuilder {
             num_snippets: 0,
             num_sequences: 0,
             acceleration_enabled,
+            memory_size: MAX_MEMORY_SIZE,
         }
     }
 
+    /// Create a builder limited to the waveform memory size of `variant`.
+    pub fn for_variant(variant: Variant, acceleration_enabled: bool) -> Self {
+        Self {
+            memory_size: variant.waveform_memory_size().min(MAX_MEMORY_SIZE),
+            ..Self::new(acceleration_enabled)
+        }
+    }
+
+    /// The size limit of the image in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    /// Create a builder holding the snippets and sequences of an existing image,
+    /// so it can be edited and built again.
+    ///
+    /// # Errors
+    /// Returns the errors of `SnippetBuilder` and `Sequence` for malformed elements.
+    pub fn from_memory(memory: &WaveformMemory, acceleration_enabled: bool) -> Result<Self, Error> {
+        let mut builder = Self::new(acceleration_enabled);
+        let bytes = memory.as_bytes();
+
+        for id in 1..=memory.num_snippets() {
+            let range = memory.range_of(WaveformElement::Snippet(id)).ok_or(Error::InvalidSnippetId)?;
+            let mut snippet = SnippetBuilder::new();
+            for &byte in &bytes[range] {
+                snippet = snippet.point(PwlPoint::from_byte(byte))?;
+            }
+            builder = builder.add_snippet(snippet.build()?)?;
+        }
+
+        for id in 0..memory.num_sequences() {
+            let range = memory.range_of(WaveformElement::Sequence(id)).ok_or(Error::InvalidValue)?;
+            builder = builder.add_sequence(Sequence::from_bytes(&bytes[range])?)?;
+        }
+
+        Ok(builder)
+    }
+
+    /// Replace the snippet with the given ID (1-15).
+    ///
+    /// # Errors
+    /// Returns `InvalidSnippetId` if no snippet with this ID has been added.
+    pub fn set_snippet(mut self, id: u8, snippet: Snippet) -> Result<Self, Error> {
+        if id == 0 || id > self.num_snippets {
+            return Err(Error::InvalidSnippetId);
+        }
+        self.snippets[id as usize - 1] = Some(snippet);
+        Ok(self)
+    }
+
+    /// Replace the sequence with the given ID (0-15).
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if no sequence with this ID has been added.
+    pub fn set_sequence(mut self, id: u8, sequence: Sequence) -> Result<Self, Error> {
+        if id >= self.num_sequences {
+            return Err(Error::InvalidValue);
+        }
+        self.sequences[id as usize] = Some(sequence);
+        Ok(self)
+    }
+
     /// Add a snippet to the waveform memory.
     ///
     /// Returns the assigned snippet ID (1-15). Snippet ID 0 is reserved.
@@ -131,7 +298,9 @@ impl WaveformMemoryBuilder {
     /// Returns `TooManySnippets` if 15 snippets have already been added.
     pub fn add_snippet(mut self, snippet: Snippet) -> Result<Self, Error> {
         if self.num_snippets as usize >= MAX_SNIPPETS {
-            return Err(Error::TooManySnippets);
+            return Err(Error::TooManySnippets {
+                element: WaveformElement::Snippet(self.num_snippets + 1),
+            });
         }
         self.snippets[self.num_snippets as usize] = Some(snippet);
         self.num_snippets += 1;
@@ -151,7 +320,9 @@ impl WaveformMemoryBuilder {
     /// Returns `TooManySequences` if 16 sequences have already been added.
     pub fn add_sequence(mut self, sequence: Sequence) -> Result<Self, Error> {
         if self.num_sequences as usize >= MAX_SEQUENCES {
-            return Err(Error::TooManySequences);
+            return Err(Error::TooManySequences {
+                element: WaveformElement::Sequence(self.num_sequences),
+            });
         }
         self.sequences[self.num_sequences as usize] = Some(sequence);
         self.num_sequences += 1;
@@ -163,6 +334,24 @@ impl WaveformMemoryBuilder {
         self.num_sequences
     }
 
+    /// Get the number of unused bytes in the waveform memory.
+    ///
+    /// Every snippet or sequence also takes one byte for its end pointer, so adding
+    /// an element of `n` bytes requires `n + 1` remaining bytes.
+    pub fn remaining_bytes(&self) -> usize {
+        self.memory_size.saturating_sub(self.calculate_size())
+    }
+
+    /// Get the number of snippets that can still be added.
+    pub fn remaining_snippets(&self) -> usize {
+        MAX_SNIPPETS - self.num_snippets as usize
+    }
+
+    /// Get the number of sequences that can still be added.
+    pub fn remaining_sequences(&self) -> usize {
+        MAX_SEQUENCES - self.num_sequences as usize
+    }
+
     /// Calculate the total size of the waveform memory.
     fn calculate_size(&self) -> usize {
         let header_size = 2; // num_snippets + num_sequences
@@ -181,10 +370,31 @@ impl WaveformMemoryBuilder {
         header_size + pointer_size + snippet_data_size + sequence_data_size
     }
 
+    /// Find the first snippet or sequence that does not fit into the memory anymore.
+    fn first_overflowing_element(&self) -> WaveformElement {
+        let mut size = 2 + self.num_snippets as usize + self.num_sequences as usize;
+
+        for (i, snippet) in self.snippets[..self.num_snippets as usize].iter().flatten().enumerate() {
+            size += snippet.byte_len();
+            if size > self.memory_size {
+                return WaveformElement::Snippet(i as u8 + 1);
+            }
+        }
+
+        for (i, sequence) in self.sequences[..self.num_sequences as usize].iter().flatten().enumerate() {
+            size += sequence.byte_len();
+            if size > self.memory_size {
+                return WaveformElement::Sequence(i as u8);
+            }
+        }
+
+        WaveformElement::Sequence(self.num_sequences.saturating_sub(1))
+    }
+
     /// Build the waveform memory.
     ///
     /// # Errors
-    /// Returns `WaveformMemoryFull` if the total size exceeds 100 bytes.
+    /// Returns `WaveformMemoryFull` if the total size exceeds `memory_size()` (100 bytes).
     /// Returns `EmptySnippet` if no snippets have been added.
     /// Returns `EmptySequence` if no sequences have been added.
     pub fn build(self) -> Result<WaveformMemory, Error> {
@@ -196,8 +406,11 @@ impl WaveformMemoryBuilder {
         }
 
         let total_size = self.calculate_size();
-        if total_size > MAX_MEMORY_SIZE {
-            return Err(Error::WaveformMemoryFull);
+        if total_size > self.memory_size {
+            return Err(Error::WaveformMemoryFull {
+                element: self.first_overflowing_element(),
+                excess_bytes: total_size - self.memory_size,
+            });
         }
 
         let mut data = [0u8; MAX_MEMORY_SIZE];
@@ -319,6 +532,11 @@ mod tests {
         assert_eq!(bytes[4], 0x8F); // snippet data
         assert_eq!(bytes[5], 0x80);
         assert_eq!(bytes[6], 0x01); // sequence data (gain=Full(0), timebase=0, snp_id=1)
+
+        assert_eq!(memory.range_of(WaveformElement::Snippet(1)), Some(4..6));
+        assert_eq!(memory.range_of(WaveformElement::Sequence(0)), Some(6..7));
+        assert_eq!(memory.range_of(WaveformElement::Snippet(0)), None);
+        assert_eq!(memory.range_of(WaveformElement::Sequence(1)), None);
     }
 
     #[test]
@@ -369,6 +587,50 @@ mod tests {
         assert_eq!(bytes[4], 9); // sequence (2 bytes at pos 8-9) ends at index 9
     }
 
+    #[test]
+    fn test_memory_remaining_capacity() {
+        let builder = WaveformMemoryBuilder::new(true);
+        assert_eq!(builder.remaining_bytes(), 98);
+        assert_eq!(builder.remaining_snippets(), 15);
+        assert_eq!(builder.remaining_sequences(), 16);
+
+        let snippet = SnippetBuilder::new()
+            .ramp(1, 15).unwrap()
+            .ramp(1, 0).unwrap()
+            .build()
+            .unwrap();
+        let frame = FrameBuilder::new(1).unwrap().loop_count(2).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+
+        let builder = builder.add_snippet(snippet).unwrap().add_sequence(sequence).unwrap();
+        // 2 header + 2 pointers + 2 snippet + 2 sequence bytes
+        assert_eq!(builder.remaining_bytes(), 92);
+        assert_eq!(builder.remaining_snippets(), 14);
+        assert_eq!(builder.remaining_sequences(), 15);
+    }
+
+    #[test]
+    fn test_memory_full_reports_element() {
+        let mut builder = WaveformMemoryBuilder::new(true);
+        for _ in 0..10 {
+            let mut snippet = SnippetBuilder::new();
+            for _ in 0..10 {
+                snippet = snippet.step(1, 15).unwrap();
+            }
+            builder = builder.add_snippet(snippet.build().unwrap()).unwrap();
+        }
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+
+        // 2 header + 11 pointers + 100 snippet + 1 sequence bytes,
+        // the 9th snippet is the first one crossing 100 bytes
+        let result = builder.add_sequence(sequence).unwrap().build();
+        assert!(matches!(
+            result,
+            Err(Error::WaveformMemoryFull { element: WaveformElement::Snippet(9), excess_bytes: 14 })
+        ));
+    }
+
     #[test]
     fn test_memory_too_many_snippets() {
         let mut builder = WaveformMemoryBuilder::new(true);
@@ -388,7 +650,7 @@ mod tests {
 
         assert!(matches!(
             builder.add_snippet(snippet),
-            Err(Error::TooManySnippets)
+            Err(Error::TooManySnippets { element: WaveformElement::Snippet(16) })
         ));
     }
 
@@ -424,6 +686,66 @@ mod tests {
         assert!(matches!(result, Err(Error::EmptySequence)));
     }
 
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+
+        let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(memory.fingerprint(), crc32(memory.as_bytes()));
+    }
+
+    #[test]
+    fn test_memory_round_trip() {
+        let snippet1 = SnippetBuilder::new().ramp(1, 15).unwrap().ramp(1, 0).unwrap().build().unwrap();
+        let snippet2 = SnippetBuilder::new().step(2, 8).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(2).unwrap().loop_count(3).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap();
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet1).unwrap()
+            .add_snippet(snippet2).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+
+        // Trailing bytes, as read back from the full SNP_MEM area
+        let mut image = [0xFFu8; MAX_MEMORY_SIZE];
+        image[..memory.len()].copy_from_slice(memory.as_bytes());
+        let parsed = WaveformMemory::from_bytes(&image).unwrap();
+        assert_eq!(parsed.as_bytes(), memory.as_bytes());
+
+        let rebuilt = WaveformMemoryBuilder::from_memory(&parsed, true).unwrap().build().unwrap();
+        assert_eq!(rebuilt.as_bytes(), memory.as_bytes());
+
+        // Tweak a snippet and add a sequence
+        let louder = SnippetBuilder::new().step(2, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().build().unwrap();
+        let edited = WaveformMemoryBuilder::from_memory(&parsed, true).unwrap()
+            .set_snippet(2, louder).unwrap()
+            .add_sequence(SequenceBuilder::new().add_frame(frame).unwrap().build().unwrap()).unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(edited.num_sequences(), 2);
+        assert_eq!(edited.as_bytes()[edited.range_of(WaveformElement::Snippet(2)).unwrap()], [0x1F]);
+    }
+
+    #[test]
+    fn test_memory_from_invalid_bytes() {
+        assert!(matches!(WaveformMemory::from_bytes(&[1]), Err(Error::InvalidValue)));
+        assert!(matches!(WaveformMemory::from_bytes(&[0, 1, 3]), Err(Error::EmptySnippet)));
+        // Decreasing end pointers
+        assert!(matches!(WaveformMemory::from_bytes(&[1, 1, 5, 4, 0, 0]), Err(Error::InvalidValue)));
+        // End pointer past the image
+        assert!(matches!(WaveformMemory::from_bytes(&[1, 1, 4, 6, 0, 0]), Err(Error::InvalidValue)));
+    }
+
     #[test]
     fn test_next_ids() {
         let builder = WaveformMemoryBuilder::new(true);
@@ -439,4 +761,13 @@ mod tests {
         assert_eq!(builder.next_snippet_id(), 2);
         assert_eq!(builder.next_sequence_id(), 0);
     }
+
+    #[test]
+    fn test_variant_memory_size() {
+        for variant in [Variant::DA7280, Variant::DA7281, Variant::DA7282] {
+            let builder = WaveformMemoryBuilder::for_variant(variant, false);
+            assert_eq!(builder.memory_size(), variant.waveform_memory_size());
+            assert_eq!(builder.remaining_bytes(), builder.memory_size() - 2);
+        }
+    }
 }
@@ -42,11 +42,25 @@
 //! ```
 
 mod snippet;
+mod fit;
 mod frame;
 mod sequence;
 mod memory;
+mod notifications;
+mod ui;
+#[cfg(feature = "simulator")]
+mod simulator;
 
 pub use snippet::{PwlPoint, Snippet, SnippetBuilder};
+pub use fit::{fit_envelope, Fit, FitOptions};
 pub use frame::{Frame, FrameBuilder, Gain, Timebase};
-pub use sequence::{Sequence, SequenceBuilder};
+pub use sequence::{Sequence, SequenceBuilder, SequenceLimits};
 pub use memory::{WaveformMemory, WaveformMemoryBuilder};
+pub(crate) use memory::{crc32, MAX_MEMORY_SIZE};
+pub use notifications::{
+    notification_memory, notification_snippets, Notification, BUZZ_SNIPPET_ID,
+    MAX_NOTIFICATION_COUNT, TAP_SNIPPET_ID,
+};
+pub use ui::{ui_effect_memory, ui_effect_snippets, UiEffect};
+#[cfg(feature = "simulator")]
+pub use simulator::{render_sequence, sequence_duration_us};
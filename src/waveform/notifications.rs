@@ -0,0 +1,189 @@
+//! Ready-made notification patterns for non-visual signalling.
+//!
+//! All notifications share two snippets, a short tap and a longer buzz, so a
+//! full set of notifications fits into the waveform memory. Count-coded
+//! notifications repeat the tap `n` times, rhythm-coded ones combine taps and
+//! buzzes into patterns that are easy to tell apart without looking.
+
+use crate::errors::Error;
+use super::frame::{Frame, FrameBuilder, Timebase};
+use super::memory::{WaveformMemory, WaveformMemoryBuilder};
+use super::sequence::{Sequence, SequenceBuilder};
+use super::snippet::{Snippet, SnippetBuilder};
+
+/// Snippet ID of the short tap in `notification_snippets()`.
+pub const TAP_SNIPPET_ID: u8 = 1;
+
+/// Snippet ID of the long buzz in `notification_snippets()`.
+pub const BUZZ_SNIPPET_ID: u8 = 2;
+
+/// Maximum count of a count-coded notification.
+pub const MAX_NOTIFICATION_COUNT: u8 = 8;
+
+/// A notification with a distinct vibration pattern.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notification {
+    /// `n` evenly spaced taps (1..=8), e.g. number of unread messages.
+    Count(u8),
+    /// Two quick taps.
+    Message,
+    /// Two long buzzes with a pause.
+    Call,
+    /// One long, continuous buzz.
+    Alarm,
+    /// Short then long.
+    Success,
+    /// Long then two short.
+    Failure,
+}
+
+/// Build the tap and buzz snippets used by all notifications.
+///
+/// Without acceleration the amplitude is signed, so the maximum is 7 instead of 15.
+pub fn notification_snippets(acceleration_enabled: bool) -> Result<[Snippet; 2], Error> {
+    let amplitude = if acceleration_enabled { 15 } else { 7 };
+
+    let tap = SnippetBuilder::new()
+        .step(4, amplitude)?
+        .step(1, 0)?
+        .build()?;
+
+    let buzz = SnippetBuilder::new()
+        .ramp(1, amplitude)?
+        .step(8, amplitude)?
+        .ramp(1, 0)?
+        .build()?;
+
+    Ok([tap, buzz])
+}
+
+fn tap(timebase: Timebase) -> Result<Frame, Error> {
+    FrameBuilder::new(TAP_SNIPPET_ID)?.timebase(timebase).build()
+}
+
+fn buzz(timebase: Timebase) -> Result<Frame, Error> {
+    FrameBuilder::new(BUZZ_SNIPPET_ID)?.timebase(timebase).build()
+}
+
+fn pause(timebase: Timebase) -> Result<Frame, Error> {
+    FrameBuilder::silence().timebase(timebase).build()
+}
+
+impl Notification {
+    /// Build the sequence for this notification, referencing the snippets of
+    /// `notification_snippets()` by `TAP_SNIPPET_ID` and `BUZZ_SNIPPET_ID`.
+    ///
+    /// # Errors
+    /// Returns `InvalidValue` if the count is 0 or exceeds `MAX_NOTIFICATION_COUNT`.
+    pub fn sequence(&self) -> Result<Sequence, Error> {
+        let mut builder = SequenceBuilder::new();
+
+        match *self {
+            Notification::Count(n) => {
+                if n == 0 || n > MAX_NOTIFICATION_COUNT {
+                    return Err(Error::InvalidValue);
+                }
+                for i in 0..n {
+                    if i > 0 {
+                        builder = builder.add_frame(pause(Timebase::Ms87_04)?)?;
+                    }
+                    builder = builder.add_frame(tap(Timebase::Ms21_76)?)?;
+                }
+            }
+            Notification::Message => {
+                builder = builder
+                    .add_frame(tap(Timebase::Ms5_44)?)?
+                    .add_frame(pause(Timebase::Ms21_76)?)?
+                    .add_frame(tap(Timebase::Ms5_44)?)?;
+            }
+            Notification::Call => {
+                builder = builder
+                    .add_frame(buzz(Timebase::Ms21_76)?)?
+                    .add_frame(pause(Timebase::Ms87_04)?)?
+                    .add_frame(buzz(Timebase::Ms21_76)?)?;
+            }
+            Notification::Alarm => {
+                builder = builder.add_frame(
+                    FrameBuilder::new(BUZZ_SNIPPET_ID)?
+                        .timebase(Timebase::Ms43_52)
+                        .loop_count(2)?
+                        .build()?,
+                )?;
+            }
+            Notification::Success => {
+                builder = builder
+                    .add_frame(tap(Timebase::Ms21_76)?)?
+                    .add_frame(pause(Timebase::Ms43_52)?)?
+                    .add_frame(buzz(Timebase::Ms21_76)?)?;
+            }
+            Notification::Failure => {
+                builder = builder
+                    .add_frame(buzz(Timebase::Ms21_76)?)?
+                    .add_frame(pause(Timebase::Ms43_52)?)?
+                    .add_frame(tap(Timebase::Ms21_76)?)?
+                    .add_frame(pause(Timebase::Ms43_52)?)?
+                    .add_frame(tap(Timebase::Ms21_76)?)?;
+            }
+        }
+
+        builder.build()
+    }
+}
+
+/// Build a waveform memory with one sequence per notification.
+///
+/// The sequence ID of each notification is its index in `notifications`.
+///
+/// # Errors
+/// Returns `TooManySequences` for more than 16 notifications and
+/// `WaveformMemoryFull` if the patterns don't fit into the memory.
+pub fn notification_memory(
+    notifications: &[Notification],
+    acceleration_enabled: bool,
+) -> Result<WaveformMemory, Error> {
+    let [tap, buzz] = notification_snippets(acceleration_enabled)?;
+    let mut builder = WaveformMemoryBuilder::new(acceleration_enabled)
+        .add_snippet(tap)?
+        .add_snippet(buzz)?;
+
+    for notification in notifications {
+        builder = builder.add_sequence(notification.sequence()?)?;
+    }
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_sequence() {
+        // n taps and n - 1 pauses, one byte each
+        let sequence = Notification::Count(3).sequence().unwrap();
+        assert_eq!(sequence.byte_len(), 5);
+        assert!(matches!(Notification::Count(0).sequence(), Err(Error::InvalidValue)));
+        assert!(matches!(Notification::Count(9).sequence(), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_full_set_fits() {
+        let memory = notification_memory(
+            &[
+                Notification::Count(1),
+                Notification::Count(2),
+                Notification::Count(3),
+                Notification::Message,
+                Notification::Call,
+                Notification::Alarm,
+                Notification::Success,
+                Notification::Failure,
+            ],
+            true,
+        )
+        .unwrap();
+        assert_eq!(memory.num_snippets(), 2);
+        assert_eq!(memory.num_sequences(), 8);
+        assert!(memory.len() <= 100);
+    }
+}
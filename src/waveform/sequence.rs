@@ -1,7 +1,8 @@
 //! Sequence construction for waveform memory.
 
-use crate::errors::Error;
-use super::frame::Frame;
+use crate::errors::{Error, WaveformElement};
+use super::frame::{Frame, Gain, TIMEBASE_US};
+use super::snippet::Snippet;
 
 /// Maximum number of frames per sequence.
 /// Limited to keep total memory under 100 bytes.
@@ -10,6 +11,15 @@ pub const MAX_FRAMES_PER_SEQUENCE: usize = 32;
 /// Maximum total bytes per sequence.
 pub const MAX_SEQUENCE_BYTES: usize = 96;
 
+/// Caller-provided sanity limits, see `SequenceBuilder::build_with_limits()`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SequenceLimits {
+    /// Maximum number of frames.
+    pub max_frames: Option<u8>,
+    /// Maximum playback duration, including loops.
+    pub max_duration_ms: Option<u32>,
+}
+
 /// A sequence of frames to be played back.
 ///
 /// Sequences are collections of frames that are played in order.
@@ -41,6 +51,95 @@ impl Sequence {
         buffer[..len].copy_from_slice(&self.data[..len]);
         len
     }
+
+    /// Wrap already encoded frames, e.g. read back from the device.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.is_empty() {
+            return Err(Error::EmptySequence);
+        }
+        if bytes.len() > MAX_SEQUENCE_BYTES {
+            return Err(Error::SequenceTooLong);
+        }
+
+        let mut data = [0u8; MAX_SEQUENCE_BYTES];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Ok(Self { data, len: bytes.len() as u8 })
+    }
+
+    /// Get the playback duration in µs, including loops.
+    ///
+    /// `snippets` are the snippets of the waveform memory, starting with snippet ID 1.
+    ///
+    /// # Errors
+    /// Returns `InvalidSnippetId` if a frame references a snippet not in `snippets`.
+    pub fn duration_us(&self, snippets: &[Snippet]) -> Result<u32, Error> {
+        duration_us(self.as_bytes(), snippets)
+    }
+}
+
+/// A frame decoded from the waveform memory, see `Frame` for the encoding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DecodedFrame {
+    pub gain: Gain,
+    /// Timebase in µs, assuming FREQ_WAVEFORM_TIMEBASE = 0.
+    pub timebase_us: u32,
+    pub snippet_id: u8,
+    pub loops: u8,
+}
+
+/// Decode the frames of an encoded sequence.
+pub(crate) fn decode_frames(data: &[u8]) -> impl Iterator<Item = DecodedFrame> + '_ {
+    let mut pos = 0;
+
+    core::iter::from_fn(move || {
+        let byte1 = *data.get(pos)?;
+        pos += 1;
+
+        let mut frame = DecodedFrame {
+            gain: Gain::from_bits(byte1 >> 5),
+            timebase_us: TIMEBASE_US[((byte1 >> 3) & 0x03) as usize],
+            snippet_id: byte1 & 0x07,
+            loops: 0,
+        };
+
+        if let Some(&byte2) = data.get(pos)
+            && byte2 & 0x80 != 0
+        {
+            pos += 1;
+            frame.loops = (byte2 >> 3) & 0x0F;
+            frame.snippet_id |= (byte2 & 0x01) << 3;
+            if byte2 & 0x04 != 0 {
+                // Frequency low byte
+                pos += 1;
+            }
+        }
+
+        Some(frame)
+    })
+}
+
+/// Playback duration of encoded frames in µs.
+fn duration_us(data: &[u8], snippets: &[Snippet]) -> Result<u32, Error> {
+    let mut duration_us: u32 = 0;
+
+    for frame in decode_frames(data) {
+        let timebases: u32 = if frame.snippet_id == 0 {
+            // Built-in silence snippet
+            2
+        } else {
+            snippets
+                .get(frame.snippet_id as usize - 1)
+                .ok_or(Error::InvalidSnippetId)?
+                .points()
+                .iter()
+                .map(|point| point.timebases() as u32)
+                .sum()
+        };
+
+        duration_us = duration_us.saturating_add(timebases * frame.timebase_us * (frame.loops as u32 + 1));
+    }
+
+    Ok(duration_us)
 }
 
 /// Builder for constructing sequences.
@@ -85,17 +184,23 @@ impl SequenceBuilder {
     /// Add a frame to the sequence.
     ///
     /// # Errors
-    /// Returns `TooManySequences` if adding this frame would exceed
-    /// the maximum sequence size.
+    /// Returns `WaveformMemoryFull` if adding this frame would exceed
+    /// the maximum sequence size, or `TooManySequences` if the sequence
+    /// already has the maximum number of frames.
     pub fn add_frame(mut self, frame: Frame) -> Result<Self, Error> {
         let frame_bytes = frame.as_bytes();
 
         // Check if we have room
         if self.len as usize + frame_bytes.len() > MAX_SEQUENCE_BYTES {
-            return Err(Error::WaveformMemoryFull);
+            return Err(Error::WaveformMemoryFull {
+                element: WaveformElement::Frame(self.frame_count),
+                excess_bytes: self.len as usize + frame_bytes.len() - MAX_SEQUENCE_BYTES,
+            });
         }
         if self.frame_count as usize >= MAX_FRAMES_PER_SEQUENCE {
-            return Err(Error::TooManySequences);
+            return Err(Error::TooManySequences {
+                element: WaveformElement::Frame(self.frame_count),
+            });
         }
 
         for &byte in frame_bytes {
@@ -122,6 +227,37 @@ impl SequenceBuilder {
         }
         Ok(Sequence { data: self.data, len: self.len })
     }
+
+    /// Build the sequence, rejecting it if it exceeds the given limits.
+    ///
+    /// Catches mistakes like an accidental multi-second buzz before the sequence
+    /// reaches the device. `snippets` are the snippets of the waveform memory,
+    /// starting with snippet ID 1, and are used to calculate the duration.
+    ///
+    /// # Errors
+    /// Returns `SequenceTooLong` if the frame count or duration exceeds the limits,
+    /// in addition to the errors of `build()` and `Sequence::duration_us()`.
+    pub fn build_with_limits(
+        self,
+        limits: &SequenceLimits,
+        snippets: &[Snippet],
+    ) -> Result<Sequence, Error> {
+        if let Some(max_frames) = limits.max_frames
+            && self.frame_count > max_frames
+        {
+            return Err(Error::SequenceTooLong);
+        }
+
+        let sequence = self.build()?;
+
+        if let Some(max_duration_ms) = limits.max_duration_ms
+            && sequence.duration_us(snippets)? > max_duration_ms.saturating_mul(1000)
+        {
+            return Err(Error::SequenceTooLong);
+        }
+
+        Ok(sequence)
+    }
 }
 
 #[cfg(test)]
@@ -166,6 +302,67 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_sequence_duration() {
+        use crate::waveform::{SnippetBuilder, Timebase};
+
+        // 4 timebases
+        let snippet = SnippetBuilder::new().ramp(1, 15).unwrap().step(3, 15).unwrap().build().unwrap();
+        let sequence = SequenceBuilder::new()
+            .add_frame(FrameBuilder::new(1).unwrap().timebase(Timebase::Ms21_76).loop_count(2).unwrap().build().unwrap())
+            .unwrap()
+            .add_frame(FrameBuilder::silence().timebase(Timebase::Ms5_44).build().unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(sequence.duration_us(&[snippet]).unwrap(), 3 * 4 * 21_760 + 2 * 5_440);
+        assert!(matches!(sequence.duration_us(&[]), Err(Error::InvalidSnippetId)));
+    }
+
+    #[test]
+    fn test_decode_frames() {
+        use crate::waveform::Timebase;
+
+        let sequence = SequenceBuilder::new()
+            .add_frame(FrameBuilder::new(9).unwrap().gain(Gain::Half).frequency_hz(300).unwrap().build().unwrap())
+            .unwrap()
+            .add_frame(FrameBuilder::new(2).unwrap().timebase(Timebase::Ms43_52).loop_count(4).unwrap().build().unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut frames = decode_frames(sequence.as_bytes());
+        assert_eq!(
+            frames.next(),
+            Some(DecodedFrame { gain: Gain::Half, timebase_us: 5_440, snippet_id: 9, loops: 0 })
+        );
+        // The frequency byte of the first frame is skipped
+        assert_eq!(
+            frames.next(),
+            Some(DecodedFrame { gain: Gain::Full, timebase_us: 43_520, snippet_id: 2, loops: 4 })
+        );
+        assert_eq!(frames.next(), None);
+    }
+
+    #[test]
+    fn test_sequence_limits() {
+        use crate::waveform::{SnippetBuilder, Timebase};
+
+        // 8 timebases of 87.04 ms, looped 16 times: over 11 seconds
+        let snippet = SnippetBuilder::new().step(8, 15).unwrap().build().unwrap();
+        let frame = FrameBuilder::new(1).unwrap().timebase(Timebase::Ms87_04).loop_count(15).unwrap().build().unwrap();
+        let builder = SequenceBuilder::new().add_frame(frame).unwrap().add_frame(frame).unwrap();
+
+        let limits = SequenceLimits { max_frames: None, max_duration_ms: Some(2_000) };
+        assert!(matches!(builder.build_with_limits(&limits, &[snippet]), Err(Error::SequenceTooLong)));
+
+        let limits = SequenceLimits { max_frames: Some(1), max_duration_ms: None };
+        assert!(matches!(builder.build_with_limits(&limits, &[snippet]), Err(Error::SequenceTooLong)));
+
+        assert!(builder.build_with_limits(&SequenceLimits::default(), &[snippet]).is_ok());
+    }
+
     #[test]
     fn test_sequence_encode() {
         let frame = FrameBuilder::new(1).unwrap().build().unwrap();
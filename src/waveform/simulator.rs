@@ -0,0 +1,201 @@
+//! Host-side rendering of sequences (requires the `simulator` feature).
+//!
+//! Evaluates a sequence from a `WaveformMemory` the way the device plays it:
+//! every frame plays its snippet `loop + 1` times with the frame's timebase and
+//! gain, and every snippet starts from zero amplitude. The result is a sampled
+//! amplitude in ‰ of the full-scale drive, which can be asserted on in unit tests
+//! or plotted.
+//!
+//! Frequency commands only affect the carrier, not the envelope, and are ignored.
+//! Frames are decoded by the same code as `Sequence::duration_us()`, with the
+//! timebases of FREQ_WAVEFORM_TIMEBASE = 0, which the driver doesn't change.
+
+use crate::errors::{Error, WaveformElement};
+use super::memory::WaveformMemory;
+use super::sequence::decode_frames;
+use super::snippet::PwlPoint;
+
+/// Call `segment(duration_us, from, to, ramp)` for every PWL segment of a sequence.
+fn for_each_segment<F>(
+    memory: &WaveformMemory,
+    sequence_id: u8,
+    acceleration_enabled: bool,
+    mut segment: F,
+) -> Result<(), Error>
+where
+    F: FnMut(u32, i32, i32, bool),
+{
+    if sequence_id >= memory.num_sequences() {
+        return Err(Error::InvalidValue);
+    }
+
+    let bytes = memory.as_bytes();
+    let range = memory.range_of(WaveformElement::Sequence(sequence_id)).ok_or(Error::InvalidValue)?;
+
+    for frame in decode_frames(&bytes[range]) {
+        for _ in 0..=frame.loops {
+            if frame.snippet_id == 0 {
+                // Built-in silence snippet
+                segment(2 * frame.timebase_us, 0, 0, false);
+                continue;
+            }
+
+            let snippet = memory.range_of(WaveformElement::Snippet(frame.snippet_id)).ok_or(Error::InvalidSnippetId)?;
+            let mut current = 0;
+            for &byte in &bytes[snippet] {
+                let point = PwlPoint::from_byte(byte);
+                let target = frame.gain.apply(point.amplitude_permille(acceleration_enabled));
+                segment(point.timebases() as u32 * frame.timebase_us, current, target, point.is_ramp());
+                current = target;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Total playback duration of a sequence in µs, saturating like `Sequence::duration_us()`.
+pub fn sequence_duration_us(
+    memory: &WaveformMemory,
+    sequence_id: u8,
+    acceleration_enabled: bool,
+) -> Result<u32, Error> {
+    let mut duration_us: u32 = 0;
+    for_each_segment(memory, sequence_id, acceleration_enabled, |segment_us, _, _, _| {
+        duration_us = duration_us.saturating_add(segment_us);
+    })?;
+    Ok(duration_us)
+}
+
+/// Render a sequence into `samples`, one amplitude in ‰ of full scale every `sample_period_us`.
+///
+/// Returns the number of samples of the whole sequence. If this is larger than
+/// `samples.len()`, the rendering was truncated.
+pub fn render_sequence(
+    memory: &WaveformMemory,
+    sequence_id: u8,
+    acceleration_enabled: bool,
+    sample_period_us: u32,
+    samples: &mut [i16],
+) -> Result<usize, Error> {
+    if sample_period_us == 0 {
+        return Err(Error::InvalidValue);
+    }
+
+    let mut segment_start_us: u64 = 0;
+    let mut index = 0usize;
+
+    for_each_segment(memory, sequence_id, acceleration_enabled, |duration_us, from, to, ramp| {
+        let segment_end_us = segment_start_us + duration_us as u64;
+
+        loop {
+            let t = index as u64 * sample_period_us as u64;
+            if t >= segment_end_us {
+                break;
+            }
+            if let Some(sample) = samples.get_mut(index) {
+                let value = if ramp {
+                    let elapsed = (t - segment_start_us) as i64;
+                    from as i64 + (to - from) as i64 * elapsed / duration_us as i64
+                } else {
+                    to as i64
+                };
+                *sample = value as i16;
+            }
+            index += 1;
+        }
+
+        segment_start_us = segment_end_us;
+    })?;
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waveform::{FrameBuilder, Gain, SequenceBuilder, SnippetBuilder, Timebase, WaveformMemoryBuilder};
+
+    fn memory() -> WaveformMemory {
+        // Ramp up over 2 timebases, hold for 2
+        let snippet = SnippetBuilder::new()
+            .ramp(2, 15).unwrap()
+            .step(2, 15).unwrap()
+            .build()
+            .unwrap();
+
+        let sequence = SequenceBuilder::new()
+            .add_frame(FrameBuilder::new(1).unwrap().timebase(Timebase::Ms21_76).build().unwrap())
+            .unwrap()
+            .add_frame(FrameBuilder::silence().timebase(Timebase::Ms21_76).build().unwrap())
+            .unwrap()
+            .add_frame(
+                FrameBuilder::new(1).unwrap()
+                    .gain(Gain::Half)
+                    .timebase(Timebase::Ms21_76)
+                    .loop_count(1).unwrap()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sequence_duration() {
+        // 4 + 2 (silence) + 2 * 4 timebases
+        assert_eq!(sequence_duration_us(&memory(), 0, true).unwrap(), 14 * 21_760);
+        assert!(sequence_duration_us(&memory(), 1, true).is_err());
+    }
+
+    #[test]
+    fn test_sequence_duration_saturates() {
+        let mut snippet = SnippetBuilder::new();
+        for _ in 0..16 {
+            snippet = snippet.step(8, 15).unwrap();
+        }
+        let snippet = snippet.build().unwrap();
+
+        // 16 * 8 * 87.04 ms * 16 loops = 178.3 s per frame, more than u32 µs in total
+        let frame = FrameBuilder::new(1).unwrap()
+            .timebase(Timebase::Ms87_04)
+            .loop_count(15).unwrap()
+            .build()
+            .unwrap();
+        let mut sequence = SequenceBuilder::new();
+        for _ in 0..25 {
+            sequence = sequence.add_frame(frame).unwrap();
+        }
+        let sequence = sequence.build().unwrap();
+
+        let memory = WaveformMemoryBuilder::new(true)
+            .add_snippet(snippet).unwrap()
+            .add_sequence(sequence).unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(sequence_duration_us(&memory, 0, true).unwrap(), u32::MAX);
+        assert_eq!(sequence.duration_us(&[snippet]).unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn test_render_sequence() {
+        let mut samples = [0i16; 32];
+        let count = render_sequence(&memory(), 0, true, 21_760, &mut samples).unwrap();
+        assert_eq!(count, 14);
+        // Ramp from 0 to 1000 over 2 samples, then hold
+        assert_eq!(&samples[..4], &[0, 500, 1000, 1000]);
+        // Silence
+        assert_eq!(&samples[4..6], &[0, 0]);
+        // Half gain, played twice
+        assert_eq!(&samples[6..10], &[0, 250, 500, 500]);
+        assert_eq!(&samples[10..14], &[0, 250, 500, 500]);
+    }
+}
@@ -1,6 +1,7 @@
 //! PWL (Piecewise Linear) point and snippet construction.
 
-use crate::errors::Error;
+use crate::errors::{Error, WaveformElement};
+use super::frame::Timebase;
 
 /// Maximum number of PWL points per snippet.
 pub const MAX_POINTS_PER_SNIPPET: usize = 16;
@@ -88,6 +89,20 @@ impl PwlPoint {
     pub fn amplitude(&self) -> u8 {
         self.byte & 0x0F
     }
+
+    /// Get the amplitude in ‰ of full scale.
+    ///
+    /// With acceleration 0-15 maps to 0-1000, without it the signed value
+    /// -8..=7 maps to -1000..=1000.
+    pub fn amplitude_permille(&self, acceleration_enabled: bool) -> i32 {
+        let amplitude = self.amplitude();
+        if acceleration_enabled {
+            amplitude as i32 * 1000 / 15
+        } else {
+            let signed = ((amplitude << 4) as i8 >> 4) as i32;
+            if signed < 0 { signed * 1000 / 8 } else { signed * 1000 / 7 }
+        }
+    }
 }
 
 /// A waveform snippet containing PWL points.
@@ -101,11 +116,81 @@ pub struct Snippet {
 }
 
 impl Snippet {
+    /// Create a snippet from a list of points, e.g. a precomputed table.
+    ///
+    /// # Errors
+    /// Returns `EmptySnippet` if `points` is empty, or `TooManyPoints` if it holds
+    /// more than 16 points.
+    pub fn from_points(points: &[PwlPoint]) -> Result<Self, Error> {
+        SnippetBuilder::new().extend(points)?.build()
+    }
+
     /// Get the points in this snippet.
     pub fn points(&self) -> &[PwlPoint] {
         &self.points[..self.len as usize]
     }
 
+    /// Get the time-reversed snippet, e.g. a release effect from a press effect.
+    ///
+    /// Each point ramps or steps from the level of the point before it (0 at the
+    /// start of playback), so a reversed ramp targets the level its original started
+    /// from, while a step keeps its level. This is exact for snippets ending at 0 as
+    /// long as no step to a different level directly follows a ramp, otherwise the
+    /// reversed ramp starts from the step's level instead of jumping first.
+    pub fn reversed(&self) -> Self {
+        let points = self.points();
+        let mut reversed = [PwlPoint::from_byte(0); MAX_POINTS_PER_SNIPPET];
+        for (i, point) in points.iter().enumerate() {
+            let start = match i {
+                0 => 0,
+                _ => points[i - 1].amplitude(),
+            };
+            let amplitude = if point.is_ramp() { start } else { point.amplitude() };
+            reversed[points.len() - 1 - i] = PwlPoint::from_byte((point.as_byte() & 0xF0) | amplitude);
+        }
+
+        Self {
+            points: reversed,
+            len: self.len,
+        }
+    }
+
+    /// Get the palindromic snippet, this snippet followed by its reversal.
+    ///
+    /// See `reversed()` for how the second half is derived.
+    ///
+    /// # Errors
+    /// Returns `TooManyPoints` if the result exceeds 16 points.
+    pub fn mirrored(&self) -> Result<Self, Error> {
+        self.concat(&self.reversed())
+    }
+
+    /// Get this snippet followed by `other`, e.g. to compose attack, sustain and release.
+    ///
+    /// `other` starts from the level this snippet ends on.
+    ///
+    /// # Errors
+    /// Returns `TooManyPoints` if the result exceeds 16 points.
+    pub fn concat(&self, other: &Snippet) -> Result<Self, Error> {
+        SnippetBuilder::new()
+            .extend(self.points())?
+            .extend(other.points())?
+            .build()
+    }
+
+    /// Get this snippet repeated `count` times.
+    ///
+    /// # Errors
+    /// Returns `EmptySnippet` if `count` is 0, or `TooManyPoints` if the result
+    /// exceeds 16 points.
+    pub fn repeat(&self, count: u8) -> Result<Self, Error> {
+        let mut builder = SnippetBuilder::new();
+        for _ in 0..count {
+            builder = builder.extend(self.points())?;
+        }
+        builder.build()
+    }
+
     /// Get the number of bytes this snippet occupies in memory.
     pub fn byte_len(&self) -> usize {
         self.len as usize
@@ -141,6 +226,7 @@ impl Snippet {
 pub struct SnippetBuilder {
     points: [PwlPoint; MAX_POINTS_PER_SNIPPET],
     len: u8,
+    timebase: Timebase,
 }
 
 impl Default for SnippetBuilder {
@@ -148,6 +234,7 @@ impl Default for SnippetBuilder {
         Self {
             points: [PwlPoint::from_byte(0); MAX_POINTS_PER_SNIPPET],
             len: 0,
+            timebase: Timebase::default(),
         }
     }
 }
@@ -169,7 +256,9 @@ impl SnippetBuilder {
     pub fn ramp(mut self, timebases: u8, amplitude: u8) -> Result<Self, Error> {
         let point = PwlPoint::ramp(timebases, amplitude)?;
         if self.len as usize >= MAX_POINTS_PER_SNIPPET {
-            return Err(Error::TooManySnippets);
+            return Err(Error::TooManyPoints {
+                element: WaveformElement::Point(self.len),
+            });
         }
         self.points[self.len as usize] = point;
         self.len += 1;
@@ -187,23 +276,103 @@ impl SnippetBuilder {
     pub fn step(mut self, timebases: u8, amplitude: u8) -> Result<Self, Error> {
         let point = PwlPoint::step(timebases, amplitude)?;
         if self.len as usize >= MAX_POINTS_PER_SNIPPET {
-            return Err(Error::TooManySnippets);
+            return Err(Error::TooManyPoints {
+                element: WaveformElement::Point(self.len),
+            });
         }
         self.points[self.len as usize] = point;
         self.len += 1;
         Ok(self)
     }
 
+    /// Set the timebase used by `ramp_ms()` and `hold_ms()` to convert milliseconds.
+    ///
+    /// The snippet itself doesn't store a timebase, so this must match the timebase
+    /// of the frames playing it.
+    pub fn timebase(mut self, timebase: Timebase) -> Self {
+        self.timebase = timebase;
+        self
+    }
+
+    /// Ramp to `amplitude` (0-15) over `ms` milliseconds.
+    ///
+    /// The duration is rounded to the nearest number of timebases (at least one).
+    /// Ramps longer than 8 timebases are split into several points on the same line.
+    ///
+    /// # Errors
+    /// Returns `InvalidAmplitude` if amplitude > 15, or `TooManyPoints` if the
+    /// points don't fit into the snippet.
+    pub fn ramp_ms(self, ms: u32, amplitude: u8) -> Result<Self, Error> {
+        self.add_ms(true, ms, amplitude)
+    }
+
+    /// Step to `amplitude` (0-15) and hold it for `ms` milliseconds.
+    ///
+    /// The duration is rounded to the nearest number of timebases (at least one).
+    /// Holds longer than 8 timebases are split into several points.
+    ///
+    /// # Errors
+    /// Returns `InvalidAmplitude` if amplitude > 15, or `TooManyPoints` if the
+    /// points don't fit into the snippet.
+    pub fn hold_ms(self, ms: u32, amplitude: u8) -> Result<Self, Error> {
+        self.add_ms(false, ms, amplitude)
+    }
+
+    fn add_ms(mut self, ramp: bool, ms: u32, amplitude: u8) -> Result<Self, Error> {
+        if amplitude > 15 {
+            return Err(Error::InvalidAmplitude);
+        }
+
+        let timebase_us = self.timebase.duration_us();
+        let total = ((ms.saturating_mul(1000) + timebase_us / 2) / timebase_us).max(1);
+        let start = match self.len {
+            0 => 0,
+            len => self.points[len as usize - 1].amplitude(),
+        } as i32;
+
+        let mut done = 0;
+        while done < total {
+            let timebases = (total - done).min(8);
+            done += timebases;
+            self = if ramp {
+                let target = start + (amplitude as i32 - start) * done as i32 / total as i32;
+                self.ramp(timebases as u8, target as u8)?
+            } else {
+                self.step(timebases as u8, amplitude)?
+            };
+        }
+
+        Ok(self)
+    }
+
     /// Add a raw PWL point to the snippet.
     pub fn point(mut self, point: PwlPoint) -> Result<Self, Error> {
         if self.len as usize >= MAX_POINTS_PER_SNIPPET {
-            return Err(Error::TooManySnippets);
+            return Err(Error::TooManyPoints {
+                element: WaveformElement::Point(self.len),
+            });
         }
         self.points[self.len as usize] = point;
         self.len += 1;
         Ok(self)
     }
 
+    /// Append a list of points to the snippet.
+    ///
+    /// # Errors
+    /// Returns `TooManyPoints` if the snippet would exceed 16 points.
+    pub fn extend(mut self, points: &[PwlPoint]) -> Result<Self, Error> {
+        if self.len as usize + points.len() > MAX_POINTS_PER_SNIPPET {
+            return Err(Error::TooManyPoints {
+                element: WaveformElement::Point(MAX_POINTS_PER_SNIPPET as u8),
+            });
+        }
+        for &point in points {
+            self = self.point(point)?;
+        }
+        Ok(self)
+    }
+
     /// Build the snippet.
     ///
     /// # Errors
@@ -294,4 +463,101 @@ mod tests {
         assert_eq!(buffer[0], 0x8F); // ramp, 1 timebase, amp 15
         assert_eq!(buffer[1], 0x80); // ramp, 1 timebase, amp 0
     }
+
+    #[test]
+    fn test_snippet_builder_ms() {
+        let snippet = SnippetBuilder::new()
+            .timebase(Timebase::Ms21_76)
+            .ramp_ms(40, 15).unwrap()
+            .hold_ms(100, 15).unwrap()
+            .build()
+            .unwrap();
+        let points = snippet.points();
+        assert_eq!(points.len(), 2);
+        assert!(points[0].is_ramp());
+        assert_eq!(points[0].timebases(), 2);
+        assert!(!points[1].is_ramp());
+        assert_eq!(points[1].timebases(), 5);
+
+        // 12 timebases of 5.44 ms, split into 8 + 4 along the ramp
+        let snippet = SnippetBuilder::new().ramp_ms(65, 12).unwrap().build().unwrap();
+        let points = snippet.points();
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[0].timebases(), points[0].amplitude()), (8, 8));
+        assert_eq!((points[1].timebases(), points[1].amplitude()), (4, 12));
+
+        assert!(matches!(SnippetBuilder::new().hold_ms(10, 16), Err(Error::InvalidAmplitude)));
+    }
+
+    #[test]
+    fn test_snippet_from_points() {
+        let points = [
+            PwlPoint::ramp(2, 15).unwrap(),
+            PwlPoint::step(4, 15).unwrap(),
+            PwlPoint::ramp(2, 0).unwrap(),
+        ];
+        let snippet = Snippet::from_points(&points).unwrap();
+        assert_eq!(snippet.points(), &points);
+
+        let snippet = SnippetBuilder::new().step(1, 8).unwrap().extend(&points).unwrap().build().unwrap();
+        assert_eq!(snippet.byte_len(), 4);
+        assert_eq!(&snippet.points()[1..], &points);
+
+        assert!(matches!(Snippet::from_points(&[]), Err(Error::EmptySnippet)));
+        let too_many = [points[0]; MAX_POINTS_PER_SNIPPET + 1];
+        assert!(matches!(Snippet::from_points(&too_many), Err(Error::TooManyPoints { .. })));
+        assert!(matches!(
+            SnippetBuilder::new().step(1, 8).unwrap().extend(&too_many[1..]),
+            Err(Error::TooManyPoints { .. })
+        ));
+    }
+
+    #[test]
+    fn test_snippet_reversed_and_mirrored() {
+        // Attack, hold, decay to a lower level, stepped release. The reversed decay
+        // starts from 0 instead of jumping to 6 first.
+        let snippet = SnippetBuilder::new()
+            .ramp(2, 15).unwrap()
+            .step(4, 15).unwrap()
+            .ramp(3, 6).unwrap()
+            .step(1, 0).unwrap()
+            .build()
+            .unwrap();
+
+        let reversed = snippet.reversed();
+        let expected = [
+            PwlPoint::step(1, 0).unwrap(),
+            PwlPoint::ramp(3, 15).unwrap(),
+            PwlPoint::step(4, 15).unwrap(),
+            PwlPoint::ramp(2, 0).unwrap(),
+        ];
+        assert_eq!(reversed.points(), &expected);
+
+        let mirrored = snippet.mirrored().unwrap();
+        assert_eq!(mirrored.byte_len(), 8);
+        assert_eq!(&mirrored.points()[..4], snippet.points());
+        assert_eq!(&mirrored.points()[4..], &expected);
+
+        let long = Snippet::from_points(&[PwlPoint::step(1, 1).unwrap(); 9]).unwrap();
+        assert!(matches!(long.mirrored(), Err(Error::TooManyPoints { .. })));
+    }
+
+    #[test]
+    fn test_snippet_concat_and_repeat() {
+        let attack = Snippet::from_points(&[PwlPoint::ramp(1, 15).unwrap()]).unwrap();
+        let release = Snippet::from_points(&[PwlPoint::step(2, 15).unwrap(), PwlPoint::ramp(2, 0).unwrap()]).unwrap();
+
+        let click = attack.concat(&release).unwrap();
+        assert_eq!(click.byte_len(), 3);
+        assert_eq!(&click.points()[..1], attack.points());
+        assert_eq!(&click.points()[1..], release.points());
+
+        let buzz = click.repeat(5).unwrap();
+        assert_eq!(buzz.byte_len(), 15);
+        assert_eq!(&buzz.points()[12..], click.points());
+
+        assert!(matches!(click.repeat(6), Err(Error::TooManyPoints { .. })));
+        assert!(matches!(click.repeat(0), Err(Error::EmptySnippet)));
+        assert!(matches!(buzz.concat(&release), Err(Error::TooManyPoints { .. })));
+    }
 }
@@ -0,0 +1,181 @@
+//! Semantic UI feedback effects (success, warning, error, progress).
+//!
+//! Unlike the notifications, which announce that something happened, these
+//! confirm the outcome of a user action. They use three snippets with clearly
+//! different shapes, a rising chirp, a heavy thud and a short tick, so the
+//! effects can be told apart blind even when they are played back to back.
+//!
+//! The snippets are referenced relative to `first_snippet_id`, so the set can
+//! share a waveform memory with other snippets, e.g. the notification set:
+//!
+//! ```
+//! use da728x::waveform::{notification_snippets, ui_effect_snippets, Notification, UiEffect, WaveformMemoryBuilder};
+//!
+//! let [tap, buzz] = notification_snippets(true)?;
+//! let [chirp, thud, tick] = ui_effect_snippets(true)?;
+//! let mut builder = WaveformMemoryBuilder::new(true).add_snippet(tap)?.add_snippet(buzz)?;
+//! let first_snippet_id = builder.next_snippet_id();
+//! let memory = builder
+//!     .add_snippet(chirp)?
+//!     .add_snippet(thud)?
+//!     .add_snippet(tick)?
+//!     .add_sequence(Notification::Message.sequence()?)?
+//!     .add_sequence(UiEffect::Success.sequence(first_snippet_id)?)?
+//!     .build()?;
+//! # Ok::<(), da728x::errors::Error>(())
+//! ```
+
+use crate::errors::Error;
+use super::frame::{Frame, FrameBuilder, Gain, Timebase};
+use super::memory::{WaveformMemory, WaveformMemoryBuilder};
+use super::sequence::{Sequence, SequenceBuilder};
+use super::snippet::{Snippet, SnippetBuilder};
+
+/// Offset of the chirp in `ui_effect_snippets()`.
+const CHIRP: u8 = 0;
+/// Offset of the thud in `ui_effect_snippets()`.
+const THUD: u8 = 1;
+/// Offset of the tick in `ui_effect_snippets()`.
+const TICK: u8 = 2;
+
+/// A UI feedback effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiEffect {
+    /// A tick followed by a rising chirp.
+    Success,
+    /// A train of five evenly spaced pulses.
+    Warning,
+    /// Two heavy thuds.
+    Error,
+    /// A single soft tick, e.g. per step of a slider or progress bar.
+    Progress,
+}
+
+/// Build the chirp, thud and tick snippets used by all UI effects, in this order.
+///
+/// Without acceleration the amplitude is signed, so the maximum is 7 instead of 15.
+pub fn ui_effect_snippets(acceleration_enabled: bool) -> Result<[Snippet; 3], Error> {
+    let amplitude = if acceleration_enabled { 15 } else { 7 };
+
+    let chirp = SnippetBuilder::new()
+        .ramp(4, amplitude)?
+        .step(1, 0)?
+        .build()?;
+
+    let thud = SnippetBuilder::new()
+        .step(3, amplitude)?
+        .ramp(2, 0)?
+        .build()?;
+
+    // Equally long on and off, so looping it gives an even pulse train
+    let tick = SnippetBuilder::new()
+        .step(1, amplitude)?
+        .step(1, 0)?
+        .build()?;
+
+    Ok([chirp, thud, tick])
+}
+
+impl UiEffect {
+    /// Append the frames of this effect to `builder`, e.g. to chain several effects
+    /// in one sequence.
+    ///
+    /// `first_snippet_id` is the ID of the first snippet of `ui_effect_snippets()`.
+    ///
+    /// # Errors
+    /// Returns `InvalidSnippetId` if the snippets don't fit into IDs 1..=15.
+    pub fn append_to(&self, builder: SequenceBuilder, first_snippet_id: u8) -> Result<SequenceBuilder, Error> {
+        let frame = |offset: u8, timebase: Timebase| -> Result<FrameBuilder, Error> {
+            Ok(FrameBuilder::new(first_snippet_id.saturating_add(offset))?.timebase(timebase))
+        };
+        // Reject sets that only partially fit, even if this effect doesn't use the last snippet
+        frame(TICK, Timebase::Ms5_44)?;
+
+        match *self {
+            UiEffect::Success => builder
+                .add_frame(frame(TICK, Timebase::Ms5_44)?.build()?)?
+                .add_frame(pause(Timebase::Ms21_76)?)?
+                .add_frame(frame(CHIRP, Timebase::Ms21_76)?.build()?),
+            UiEffect::Warning => builder.add_frame(frame(TICK, Timebase::Ms21_76)?.loop_count(4)?.build()?),
+            UiEffect::Error => builder
+                .add_frame(frame(THUD, Timebase::Ms21_76)?.build()?)?
+                .add_frame(pause(Timebase::Ms21_76)?)?
+                .add_frame(frame(THUD, Timebase::Ms21_76)?.build()?),
+            UiEffect::Progress => builder.add_frame(frame(TICK, Timebase::Ms5_44)?.gain(Gain::Half).build()?),
+        }
+    }
+
+    /// Build the sequence for this effect.
+    ///
+    /// `first_snippet_id` is the ID of the first snippet of `ui_effect_snippets()`.
+    ///
+    /// # Errors
+    /// Returns `InvalidSnippetId` if the snippets don't fit into IDs 1..=15.
+    pub fn sequence(&self, first_snippet_id: u8) -> Result<Sequence, Error> {
+        self.append_to(SequenceBuilder::new(), first_snippet_id)?.build()
+    }
+}
+
+fn pause(timebase: Timebase) -> Result<Frame, Error> {
+    FrameBuilder::silence().timebase(timebase).build()
+}
+
+/// Build a waveform memory with one sequence per UI effect.
+///
+/// The sequence ID of each effect is its index in `effects`.
+///
+/// # Errors
+/// Returns `TooManySequences` for more than 16 effects and
+/// `WaveformMemoryFull` if the patterns don't fit into the memory.
+pub fn ui_effect_memory(effects: &[UiEffect], acceleration_enabled: bool) -> Result<WaveformMemory, Error> {
+    let mut builder = WaveformMemoryBuilder::new(acceleration_enabled);
+    let first_snippet_id = builder.next_snippet_id();
+    for snippet in ui_effect_snippets(acceleration_enabled)? {
+        builder = builder.add_snippet(snippet)?;
+    }
+
+    for effect in effects {
+        builder = builder.add_sequence(effect.sequence(first_snippet_id)?)?;
+    }
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effects_are_distinct() {
+        let effects = [UiEffect::Success, UiEffect::Warning, UiEffect::Error, UiEffect::Progress];
+        for (i, a) in effects.iter().enumerate() {
+            for b in &effects[i + 1..] {
+                assert_ne!(a.sequence(1).unwrap().as_bytes(), b.sequence(1).unwrap().as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_chained_effects_and_snippet_ids() {
+        let builder = UiEffect::Progress.append_to(SequenceBuilder::new(), 4).unwrap();
+        let builder = UiEffect::Progress.append_to(builder, 4).unwrap();
+        let sequence = UiEffect::Success.append_to(builder, 4).unwrap().build().unwrap();
+        assert_eq!(sequence.byte_len(), 5);
+
+        // Tick at first_snippet_id + 2
+        assert_eq!(sequence.as_bytes()[0] & 0x07, 6);
+        assert!(matches!(UiEffect::Progress.sequence(14), Err(Error::InvalidSnippetId)));
+        assert!(matches!(UiEffect::Success.sequence(0), Err(Error::InvalidSnippetId)));
+    }
+
+    #[test]
+    fn test_full_set_fits() {
+        let memory = ui_effect_memory(
+            &[UiEffect::Success, UiEffect::Warning, UiEffect::Error, UiEffect::Progress],
+            false,
+        )
+        .unwrap();
+        assert_eq!(memory.num_snippets(), 3);
+        assert_eq!(memory.num_sequences(), 4);
+    }
+}